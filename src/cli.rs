@@ -0,0 +1,153 @@
+//! 命令行子命令。
+//!
+//! 不带参数启动时运行 HTTP 服务；带子命令时执行一次性的维护任务后退出：
+//!
+//! - `rescore`：按当前评分模型重新计算存储中全部代理的评分，不重新测速；
+//! - `train [模型文件]`：从检测历史训练逻辑回归评分模型并保存，同时输出与加权模型的对比；
+//! - `compare [模型文件]`：在检测历史上比较加权模型与已训练模型的预测效果；
+//! - `migrate [--dry-run]`：执行尚未执行的表结构迁移，`--dry-run` 时只输出将要执行的语句。
+
+use crate::db;
+use crate::db::get_storage;
+use crate::db::manager::StorageBackend;
+use crate::db::migration::PlannedMigration;
+use crate::db::manager::ProxyStorage;
+use crate::model::APP_CONFIG;
+use crate::service::quality;
+use crate::service::scoring::{self, Evaluation, LogisticModel, WeightedModel};
+use anyhow::{bail, Result};
+
+/// 维护子命令。
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// 重新计算全部代理的评分。
+    Rescore,
+    /// 训练逻辑回归评分模型，未指定路径时保存到 `[scoring] model_file`。
+    Train { output: Option<String> },
+    /// 比较加权模型与已训练模型，未指定路径时读取 `[scoring] model_file`。
+    Compare { model: Option<String> },
+    /// 执行表结构迁移，`dry_run` 时只输出计划。
+    Migrate { dry_run: bool },
+}
+
+impl Command {
+    /// 解析命令行参数（不含程序名），没有子命令时返回 `None`。
+    ///
+    /// # 错误
+    /// 子命令或参数无法识别时返回错误。
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>> {
+        let mut args = args.into_iter();
+        let Some(name) = args.next() else { return Ok(None) };
+        let command = match name.as_str() {
+            "rescore" => Command::Rescore,
+            "train" => Command::Train { output: args.next() },
+            "compare" => Command::Compare { model: args.next() },
+            "migrate" => match args.next().as_deref() {
+                None => Command::Migrate { dry_run: false },
+                Some("--dry-run") => Command::Migrate { dry_run: true },
+                Some(other) => bail!("子命令 migrate 不支持参数：{}", other),
+            },
+            other => bail!("未知的子命令：{}（可用：rescore、train、compare、migrate）", other),
+        };
+        if let Some(extra) = args.next() {
+            bail!("子命令 {} 不支持参数：{}", name, extra);
+        }
+        Ok(Some(command))
+    }
+
+    /// 执行子命令。
+    ///
+    /// `migrate` 直接连接数据库执行迁移，其余子命令先完成数据库初始化。
+    pub async fn run(self) -> Result<()> {
+        if let Command::Migrate { dry_run } = self {
+            let plan = StorageBackend::connect().await?.migrate(dry_run).await?;
+            print_plan(&plan, dry_run);
+            return Ok(());
+        }
+        db::init().await?;
+
+        match self {
+            Command::Rescore => {
+                let changed = quality::rescore_all(scoring::active_model().as_ref()).await?;
+                println!("重新评分完成，{} 条代理的评分发生变化", changed);
+            }
+            Command::Train { output } => {
+                let path = output.unwrap_or_else(|| APP_CONFIG.scoring.model_file.clone());
+                let samples = scoring::build_samples(&get_storage().list_all_checks().await?);
+                let (train, test) = scoring::split_samples(&samples)?;
+
+                let model = LogisticModel::train(train);
+                model.save(&path)?;
+                println!("模型已保存到 {}（训练样本 {} 条，验证样本 {} 条）", path, train.len(), test.len());
+
+                let weighted = WeightedModel::new(APP_CONFIG.scoring.clone());
+                print_report(&scoring::compare(&[&weighted, &model], test));
+            }
+            Command::Compare { model } => {
+                let path = model.unwrap_or_else(|| APP_CONFIG.scoring.model_file.clone());
+                let model = LogisticModel::load(&path)?;
+                let samples = scoring::build_samples(&get_storage().list_all_checks().await?);
+                let (_, test) = scoring::split_samples(&samples)?;
+
+                let weighted = WeightedModel::new(APP_CONFIG.scoring.clone());
+                print_report(&scoring::compare(&[&weighted, &model], test));
+            }
+            Command::Migrate { .. } => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// 输出迁移计划，`dry_run` 时列出将要执行的语句。
+fn print_plan(plan: &[PlannedMigration], dry_run: bool) {
+    if plan.is_empty() {
+        println!("表结构已是最新，无需迁移");
+        return;
+    }
+    for migration in plan {
+        println!("#{} {}", migration.version, migration.description);
+        if dry_run {
+            for statement in &migration.statements {
+                println!("    {};", statement.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+        }
+        for skipped in &migration.skipped {
+            println!("    -- 跳过：{} 已存在", skipped);
+        }
+    }
+    let verb = if dry_run { "待执行" } else { "已执行" };
+    println!("{} {} 个迁移", verb, plan.len());
+}
+
+/// 输出模型对比结果，对数损失与 Brier 分数越小越好。
+fn print_report(report: &[Evaluation]) {
+    println!("{:<10} {:>8} {:>10} {:>8} {:>8}", "model", "samples", "log_loss", "brier", "accuracy");
+    for e in report {
+        println!("{:<10} {:>8} {:>10.4} {:>8.4} {:>8.2}%", e.model, e.samples, e.log_loss, e.brier, e.accuracy * 100.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse(args(&[])).unwrap(), None);
+        assert_eq!(Command::parse(args(&["rescore"])).unwrap(), Some(Command::Rescore));
+        assert_eq!(Command::parse(args(&["train"])).unwrap(), Some(Command::Train { output: None }));
+        assert_eq!(
+            Command::parse(args(&["compare", "model.json"])).unwrap(),
+            Some(Command::Compare { model: Some("model.json".into()) })
+        );
+        assert_eq!(Command::parse(args(&["migrate"])).unwrap(), Some(Command::Migrate { dry_run: false }));
+        assert_eq!(Command::parse(args(&["migrate", "--dry-run"])).unwrap(), Some(Command::Migrate { dry_run: true }));
+        assert!(Command::parse(args(&["migrate", "--force"])).is_err());
+        assert!(Command::parse(args(&["rescore", "--all"])).is_err());
+        assert!(Command::parse(args(&["unknown"])).is_err());
+    }
+}
@@ -1,40 +1,544 @@
+
+//! 全局缓存与代理池缓存。
+//!
+//! 代理池缓存（键 `proxies`）保存存储中的全部代理，供 `/proxy`、代理网关等高频读取场景使用：
+//!
+//! - 缓存缺失或超过 `[cache] ttl` 时按需从存储加载；
+//! - 存储后端每次写入（`upsert_quality_proxy` / `upsert_many`）或删除（`remove_proxy` / `remove_many`）
+//!   成功后增量更新缓存；网关与反馈产生的单个代理写入先合并缓冲，攒够一批或等待超过
+//!   [`UPSERT_FLUSH_INTERVAL`] 后再一次性写入，避免每次请求都复制整个代理池；
+//! - 后台按配置的间隔定期整体刷新，也可通过 `POST /cache/refresh` 手动刷新。
+//!
+//! 加载或刷新期间发生的增量写入会被记录下来，在新快照写入缓存前重放，
+//! 因此不会被加载开始时读出的旧数据覆盖，缓存缺失时的写入也不会丢失。
+
+use crate::db::get_storage;
+use crate::db::manager::ProxyStorage;
+use crate::model::{Proxy, ProxyBasic, APP_CONFIG};
+use anyhow::Result;
+use arc_swap::ArcSwap;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::sync::{Arc, RwLock};
-use crate::model::Proxy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// 全局缓存最多保存的条目数。
+const CACHE_CAPACITY: usize = 64;
+
+/// 单个代理写入在缓冲中最多等待的时间，超过后由下一次读取写入缓存。
+const UPSERT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 单个代理写入缓冲的容量，攒满后立即写入缓存。
+const UPSERT_BATCH_SIZE: usize = 256;
+
+/// 缓存条目：值以 [`ArcSwap`] 保存，读取时只是一次原子加载并增加引用计数，不加锁也不复制数据。
+struct Entry<V> {
+    value: ArcSwap<V>,
+    expires_at: Option<Instant>,
+    /// 最近一次访问距缓存创建的毫秒数，用于容量满时淘汰最久未访问的条目。
+    last_access: AtomicU64,
+}
+
+impl<V> Entry<V> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|t| t <= now)
+    }
+
+    fn value(&self) -> Arc<V> {
+        self.value.load_full()
+    }
+}
+
+/// 代理池缓存的值：代理列表及其按 `(ip, port)` 的位置索引，单个代理的写入与删除均为 O(1)。
+///
+/// 删除时以末尾元素填补空位，列表顺序不代表评分顺序。
+#[derive(Debug, Clone, Default)]
+pub struct ProxyPool {
+    proxies: Vec<Proxy>,
+    index: HashMap<(String, String), usize>,
+}
+
+impl ProxyPool {
+    pub fn new(proxies: Vec<Proxy>) -> Self {
+        let mut pool = Self { proxies: Vec::with_capacity(proxies.len()), index: HashMap::with_capacity(proxies.len()) };
+        for proxy in proxies {
+            pool.upsert(proxy);
+        }
+        pool
+    }
+
+    /// 写入代理：已存在则替换，否则追加。
+    fn upsert(&mut self, proxy: Proxy) {
+        match self.index.get(&(proxy.ip.clone(), proxy.port.clone())) {
+            Some(&i) => self.proxies[i] = proxy,
+            None => {
+                self.index.insert((proxy.ip.clone(), proxy.port.clone()), self.proxies.len());
+                self.proxies.push(proxy);
+            }
+        }
+    }
+
+    fn remove(&mut self, ip: &str, port: &str) {
+        let Some(i) = self.index.remove(&(ip.to_string(), port.to_string())) else { return };
+        self.proxies.swap_remove(i);
+        if let Some(moved) = self.proxies.get(i) {
+            self.index.insert((moved.ip.clone(), moved.port.clone()), i);
+        }
+    }
+}
+
+impl std::ops::Deref for ProxyPool {
+    type Target = [Proxy];
+
+    fn deref(&self) -> &[Proxy] {
+        &self.proxies
+    }
+}
+
+/// 缓存统计信息。
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheStats {
+    /// 当前条目数（含尚未清理的过期条目）。
+    pub len: usize,
+    /// 最大条目数。
+    pub capacity: usize,
+    /// 命中次数。
+    pub hits: u64,
+    /// 未命中次数（含已过期）。
+    pub misses: u64,
+    /// 因容量已满被淘汰的条目数。
+    pub evictions: u64,
+}
 
-pub struct GlobalCache<K, V> {
-    store: Arc<RwLock<HashMap<K, V>>>,
+/// 带过期时间与容量上限的泛型缓存。
+///
+/// 整张表与每个条目的值都以 [`ArcSwap`] 保存，读取时只有原子加载，不加锁；
+/// 插入与删除时复制表结构（条目本身以 `Arc` 共享）后整体替换，写入之间通过互斥锁串行化。
+///
+/// [`Cache::update`] 为写时复制：每次修改都会完整克隆一份值再替换，代价与值的大小成正比
+/// （代理池缓存即复制全部代理），因此批量修改应合并为一次调用。适合读多写少的场景。
+pub struct Cache<K, V> {
+    entries: ArcSwap<HashMap<K, Arc<Entry<V>>>>,
+    write_lock: Mutex<()>,
+    capacity: usize,
+    created_at: Instant,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
-impl<K: Eq + Hash + Clone, V: Clone> GlobalCache<K, V> {
-    fn new() -> Self {
-        GlobalCache {
-            store: Arc::new(RwLock::new(HashMap::new())),
+impl<K: Eq + Hash + Clone, V> Cache<K, V> {
+    /// 创建一个最多保存 `capacity` 个条目的缓存。
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: ArcSwap::from_pointee(HashMap::new()),
+            write_lock: Mutex::new(()),
+            capacity: capacity.max(1),
+            created_at: Instant::now(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<V> {
-        let store = self.store.read().unwrap();
-        store.get(key).cloned()
+    fn now_ms(&self) -> u64 {
+        self.created_at.elapsed().as_millis() as u64
     }
 
-    pub fn set(&self, key: K, value: V) {
-        let mut store = self.store.write().unwrap();
-        store.insert(key, value);
+    /// 在写锁内基于当前表生成新表并替换。
+    fn write<R>(&self, f: impl FnOnce(&mut HashMap<K, Arc<Entry<V>>>) -> R) -> R {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = HashMap::clone(&self.entries.load());
+        let result = f(&mut entries);
+        self.entries.store(Arc::new(entries));
+        result
     }
 
-    pub fn remove(&self, key: &K) -> Option<V> {
-        let mut store = self.store.write().unwrap();
-        store.remove(key)
+    /// 读取未过期的值，返回共享的 `Arc` 快照。
+    pub fn get(&self, key: &K) -> Option<Arc<V>> {
+        let entries = self.entries.load();
+        match entries.get(key) {
+            Some(entry) if !entry.is_expired(Instant::now()) => {
+                entry.last_access.store(self.now_ms(), Ordering::Relaxed);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.value())
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// 写入值，`ttl` 为 `None` 时永不过期。
+    ///
+    /// 容量已满时先清理过期条目，仍然不足则淘汰最久未访问的条目。
+    pub fn insert(&self, key: K, value: V, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let entry = Arc::new(Entry {
+            value: ArcSwap::from_pointee(value),
+            expires_at: ttl.map(|ttl| now + ttl),
+            last_access: AtomicU64::new(self.now_ms()),
+        });
+
+        self.write(|entries| {
+            if !entries.contains_key(&key) && entries.len() >= self.capacity {
+                entries.retain(|_, e| !e.is_expired(now));
+                if entries.len() >= self.capacity
+                    && let Some(oldest) = entries
+                        .iter()
+                        .min_by_key(|(_, e)| e.last_access.load(Ordering::Relaxed))
+                        .map(|(k, _)| k.clone())
+                {
+                    entries.remove(&oldest);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            entries.insert(key, entry);
+        });
     }
 
-    pub fn clear(&self) {
-        let mut store = self.store.write().unwrap();
-        store.clear();
+    /// 若键存在且未过期，则修改其值，过期时间保持不变。
+    ///
+    /// 修改在当前值的完整副本上进行，完成后原子替换，已被读取的旧快照不受影响。
+    pub fn update(&self, key: &K, f: impl FnOnce(&mut V))
+    where
+        V: Clone,
+    {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let entries = self.entries.load();
+        if let Some(entry) = entries.get(key)
+            && !entry.is_expired(Instant::now())
+        {
+            let mut value = V::clone(&entry.value.load());
+            f(&mut value);
+            entry.value.store(Arc::new(value));
+        }
+    }
+
+    /// 当前统计信息。
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.load().len(),
+            capacity: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
     }
 }
 
 // 全局唯一缓存实例
-pub static CACHE: Lazy<GlobalCache<&str, Vec<Proxy>>> = Lazy::new(|| GlobalCache::new());
\ No newline at end of file
+pub static CACHE: Lazy<Cache<&str, ProxyPool>> = Lazy::new(|| Cache::new(CACHE_CAPACITY));
+
+/// 代理池在缓存中的键。
+const PROXIES_KEY: &str = "proxies";
+
+/// 尚未写入缓存的单个代理写入，同一代理只保留最新的一条。
+static PENDING_UPSERTS: Lazy<Mutex<HashMap<(String, String), Proxy>>> = Lazy::new(Default::default);
+
+/// 缓冲中最早一条写入的时间（距 [`PENDING_EPOCH`] 的毫秒数加 1），为 0 时缓冲为空。
+///
+/// 读取时只需一次原子加载即可判断是否需要写入缓冲，不必获取缓冲的锁。
+static PENDING_SINCE: AtomicU64 = AtomicU64::new(0);
+static PENDING_EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// 读取缓存的代理池快照，缓存缺失或过期时从存储加载并写入缓存。
+///
+/// 缓冲的单个代理写入等待超过 [`UPSERT_FLUSH_INTERVAL`] 时先写入缓存，
+/// 因此读到的数据最多比存储晚这么久。
+pub async fn cached_proxies() -> Result<Arc<ProxyPool>> {
+    let since = PENDING_SINCE.load(Ordering::Acquire);
+    if since > 0 && PENDING_EPOCH.elapsed().as_millis() as u64 + 1 >= since + UPSERT_FLUSH_INTERVAL.as_millis() as u64 {
+        flush_pending_upserts();
+    }
+    if let Some(proxies) = CACHE.get(&PROXIES_KEY) {
+        return Ok(proxies);
+    }
+    load_proxies().await?;
+    Ok(CACHE.get(&PROXIES_KEY).unwrap_or_default())
+}
+
+/// 从存储重新加载全部代理并替换缓存，返回加载的代理数量。
+pub async fn refresh_proxies() -> Result<usize> {
+    load_proxies().await
+}
+
+/// 从存储加载全部代理，按 `[cache] ttl` 写入代理池缓存。
+async fn load_proxies() -> Result<usize> {
+    let ttl = APP_CONFIG.cache.ttl;
+    load_with(get_storage().list_all_proxies(), (ttl > 0).then(|| Duration::from_secs(ttl))).await
+}
+
+/// 以 `list` 读出的代理替换代理池缓存，返回写入的代理数量。
+///
+/// 读取期间的增量写入在写入缓存前重放到新快照上。
+async fn load_with(list: impl Future<Output = Result<Vec<Proxy>>>, ttl: Option<Duration>) -> Result<usize> {
+    let load = LoadGuard::begin();
+    let mut pool = ProxyPool::new(list.await?);
+
+    let loads = LOADS.lock().unwrap_or_else(|e| e.into_inner());
+    for delta in &loads.deltas[load.start..] {
+        delta.clone().apply(&mut pool);
+    }
+    let count = pool.len();
+    CACHE.insert(PROXIES_KEY, pool, ttl);
+    drop(loads);
+    Ok(count)
+}
+
+/// 代理池的一次增量写入。
+#[derive(Debug, Clone)]
+enum Delta {
+    Upsert(Proxy),
+    Remove(String, String),
+}
+
+impl Delta {
+    fn apply(self, pool: &mut ProxyPool) {
+        match self {
+            Delta::Upsert(proxy) => pool.upsert(proxy),
+            Delta::Remove(ip, port) => pool.remove(&ip, &port),
+        }
+    }
+}
+
+/// 正在进行的加载数量，以及最早一次加载开始以来的增量写入。
+#[derive(Default)]
+struct Loads {
+    active: usize,
+    deltas: Vec<Delta>,
+}
+
+static LOADS: Lazy<Mutex<Loads>> = Lazy::new(Default::default);
+
+/// 一次进行中的加载，`start` 为其开始时增量写入记录的位置；结束（含出错或被取消）时注销。
+struct LoadGuard {
+    start: usize,
+}
+
+impl LoadGuard {
+    fn begin() -> Self {
+        let mut loads = LOADS.lock().unwrap_or_else(|e| e.into_inner());
+        loads.active += 1;
+        Self { start: loads.deltas.len() }
+    }
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        let mut loads = LOADS.lock().unwrap_or_else(|e| e.into_inner());
+        loads.active -= 1;
+        if loads.active == 0 {
+            loads.deltas.clear();
+        }
+    }
+}
+
+/// 将增量写入应用到缓存，有加载正在进行时同时记录下来供其重放。
+///
+/// 应用与记录在同一把锁内完成，加载写入新快照时不会遗漏或重复中间的写入。
+fn apply_deltas(deltas: Vec<Delta>) {
+    let mut loads = LOADS.lock().unwrap_or_else(|e| e.into_inner());
+    if loads.active == 0 {
+        CACHE.update(&PROXIES_KEY, |pool| deltas.into_iter().for_each(|d| d.apply(pool)));
+    } else {
+        CACHE.update(&PROXIES_KEY, |pool| deltas.iter().cloned().for_each(|d| d.apply(pool)));
+        loads.deltas.extend(deltas);
+    }
+}
+
+/// 单个代理写入存储后更新缓存：已缓存则替换，否则追加。
+///
+/// 写入先进入缓冲，攒满 [`UPSERT_BATCH_SIZE`] 条时立即写入缓存，否则由等待超过
+/// [`UPSERT_FLUSH_INTERVAL`] 后的下一次读取写入，多次写入只复制一次代理池。
+/// 缓存尚未加载且没有正在进行的加载时不做处理，下次读取时会从存储完整加载。
+pub fn cache_upsert_proxy(proxy: &Proxy) {
+    let full = {
+        let mut pending = PENDING_UPSERTS.lock().unwrap_or_else(|e| e.into_inner());
+        pending.insert((proxy.ip.clone(), proxy.port.clone()), proxy.clone());
+        let _ = PENDING_SINCE.compare_exchange(
+            0,
+            PENDING_EPOCH.elapsed().as_millis() as u64 + 1,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        pending.len() >= UPSERT_BATCH_SIZE
+    };
+    if full {
+        flush_pending_upserts();
+    }
+}
+
+/// 将缓冲中的单个代理写入一次性写入缓存。
+///
+/// 写入缓存期间持有缓冲的锁，同时发生的删除会等待写入完成，不会被缓冲中的旧数据覆盖。
+fn flush_pending_upserts() {
+    let mut pending = PENDING_UPSERTS.lock().unwrap_or_else(|e| e.into_inner());
+    if !pending.is_empty() {
+        let proxies = std::mem::take(&mut *pending);
+        apply_deltas(proxies.into_values().map(Delta::Upsert).collect());
+    }
+    PENDING_SINCE.store(0, Ordering::Release);
+}
+
+/// 从缓冲中丢弃被更新的写入覆盖的代理。
+fn discard_pending<'a>(keys: impl IntoIterator<Item = (&'a str, &'a str)>) {
+    if PENDING_SINCE.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    let mut pending = PENDING_UPSERTS.lock().unwrap_or_else(|e| e.into_inner());
+    for (ip, port) in keys {
+        pending.remove(&(ip.to_string(), port.to_string()));
+    }
+}
+
+/// 批量写入存储后立即更新缓存，规则同 [`cache_upsert_proxy`]，缓冲中同一代理的旧写入被丢弃。
+pub fn cache_upsert_proxies(updated: &[Proxy]) {
+    discard_pending(updated.iter().map(|p| (p.ip.as_str(), p.port.as_str())));
+    apply_deltas(updated.iter().cloned().map(Delta::Upsert).collect());
+}
+
+/// 代理从存储删除后立即移除缓存中的条目。
+pub fn cache_remove_proxy(ip: &str, port: &str) {
+    discard_pending([(ip, port)]);
+    apply_deltas(vec![Delta::Remove(ip.to_string(), port.to_string())]);
+}
+
+/// 批量删除代理后立即移除缓存中的条目。
+pub fn cache_remove_proxies(removed: &[ProxyBasic]) {
+    discard_pending(removed.iter().map(|p| (p.ip.as_str(), p.port.as_str())));
+    apply_deltas(removed.iter().map(|p| Delta::Remove(p.ip.clone(), p.port.clone())).collect());
+}
+
+/// 按 `[cache] refresh_interval` 启动后台定期刷新，间隔为 0 时不启动。
+pub fn start_refresh() {
+    let interval = APP_CONFIG.cache.refresh_interval;
+    if interval == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            match refresh_proxies().await {
+                Ok(count) => info!("🔄 代理缓存已刷新：{} 条", count),
+                Err(e) => warn!("代理缓存刷新失败：{}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(ip: &str, port: &str, score: f64) -> Proxy {
+        let mut proxy = Proxy::new(ip.into(), port.into());
+        proxy.score = Some(score);
+        proxy
+    }
+
+    #[test]
+    fn test_ttl_and_stats() {
+        let cache: Cache<&str, u32> = Cache::new(4);
+        cache.insert("a", 1, Some(Duration::ZERO));
+        cache.insert("b", 2, None);
+
+        assert!(cache.get(&"a").is_none());
+        assert_eq!(cache.get(&"b").as_deref(), Some(&2));
+        assert!(cache.get(&"c").is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+    }
+
+    #[test]
+    fn test_capacity_eviction() {
+        let cache: Cache<u32, u32> = Cache::new(2);
+        cache.insert(1, 1, None);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert(2, 2, None);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.get(&1);
+        cache.insert(3, 3, None);
+
+        assert!(cache.get(&2).is_none());
+        assert!(cache.get(&1).is_some() && cache.get(&3).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().len, 2);
+    }
+
+    #[test]
+    fn test_snapshot_isolation() {
+        let cache: Cache<&str, Vec<u32>> = Cache::new(1);
+        cache.insert("list", vec![1, 2], None);
+        let snapshot = cache.get(&"list").unwrap();
+
+        cache.update(&"list", |list| list.push(3));
+        assert_eq!(*snapshot, vec![1, 2]);
+        assert_eq!(*cache.get(&"list").unwrap(), vec![1, 2, 3]);
+    }
+
+    /// 代理池缓存是全局的，修改它的用例需要串行执行。
+    static POOL_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_incremental_update() {
+        let _lock = POOL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        CACHE.insert(PROXIES_KEY, ProxyPool::new(vec![proxy("1.1.1.1", "80", 0.5), proxy("2.2.2.2", "80", 0.5)]), None);
+
+        cache_upsert_proxy(&proxy("1.1.1.1", "80", 0.9));
+        cache_upsert_proxy(&proxy("3.3.3.3", "80", 0.1));
+        cache_upsert_proxy(&proxy("2.2.2.2", "80", 0.7));
+        assert_eq!(CACHE.get(&PROXIES_KEY).unwrap()[0].score, Some(0.5));
+        flush_pending_upserts();
+        cache_remove_proxy("2.2.2.2", "81");
+        cache_remove_proxy("2.2.2.2", "80");
+
+        let proxies = CACHE.get(&PROXIES_KEY).unwrap();
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(proxies[0].score, Some(0.9));
+        assert_eq!(proxies[1].ip, "3.3.3.3");
+
+        cache_upsert_proxies(&[proxy("4.4.4.4", "80", 0.2), proxy("3.3.3.3", "80", 0.3)]);
+        cache_remove_proxies(&[ProxyBasic::new("1.1.1.1", "80")]);
+        let proxies = CACHE.get(&PROXIES_KEY).unwrap();
+        let mut entries: Vec<_> = proxies.iter().map(|p| (p.ip.as_str(), p.score)).collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(entries, vec![("3.3.3.3", Some(0.3)), ("4.4.4.4", Some(0.2))]);
+        for (i, p) in proxies.iter().enumerate() {
+            assert_eq!(proxies.index[&(p.ip.clone(), p.port.clone())], i);
+        }
+    }
+
+    #[test]
+    fn test_write_during_load() {
+        let _lock = POOL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        CACHE.insert(PROXIES_KEY, ProxyPool::default(), None);
+
+        // 读出旧数据之后、写入缓存之前，另一处更新并删除了代理
+        let load = load_with(
+            async {
+                let stale = vec![proxy("1.1.1.1", "80", 0.1), proxy("2.2.2.2", "80", 0.5)];
+                cache_upsert_proxies(&[proxy("1.1.1.1", "80", 0.9), proxy("3.3.3.3", "80", 0.3)]);
+                cache_remove_proxy("2.2.2.2", "80");
+                Ok(stale)
+            },
+            None,
+        );
+        let count = futures::executor::block_on(load).unwrap();
+
+        let proxies = CACHE.get(&PROXIES_KEY).unwrap();
+        let mut entries: Vec<_> = proxies.iter().map(|p| (p.ip.as_str(), p.score)).collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(entries, vec![("1.1.1.1", Some(0.9)), ("3.3.3.3", Some(0.3))]);
+        assert_eq!(count, 2);
+        assert!(LOADS.lock().unwrap().deltas.is_empty());
+    }
+}
@@ -30,7 +30,7 @@ pub fn init_logging() -> anyhow::Result<()> {
         .with_writer(std::io::stdout)
         .with_ansi(true)
         .with_filter(filter_fn(move |metadata: &Metadata| {
-            allowed_levels.contains(&metadata.level())
+            allowed_levels.contains(metadata.level())
         }));
 
     // 4. 组合所有层
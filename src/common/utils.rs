@@ -1,4 +1,4 @@
-use crate::model::ProxyBasic;
+use crate::model::{ProxyBasic, ScoringConfig};
 use std::collections::HashSet;
 use tracing::Level;
 
@@ -7,6 +7,11 @@ pub fn round2(val: f64) -> f64 {
     (val * 100.0).round() / 100.0
 }
 
+/// 将以秒为单位的耗时四舍五入到毫秒（三位小数），避免几毫秒的响应时间被舍为 0。
+pub fn round_ms(secs: f64) -> f64 {
+    (secs * 1000.0).round() / 1000.0
+}
+
 pub fn dedup_proxies(proxies: Vec<ProxyBasic>) -> Vec<ProxyBasic> {
     let mut seen = HashSet::new();
     let mut result = Vec::new();
@@ -20,22 +25,29 @@ pub fn dedup_proxies(proxies: Vec<ProxyBasic>) -> Vec<ProxyBasic> {
     result
 }
 
-/// 根据速度（毫秒）计算评分，0.0~1.0。越快得分越高。
-pub fn speed_to_score(speed: f64) -> f64 {
-    if speed <= 0.0 {
+/// 响应时间达到 `speed_slow_ms` 时的速度得分。
+pub const SLOW_SPEED_SCORE: f64 = 0.3;
+
+/// 根据响应时间（毫秒）计算速度评分，0.0~1.0。越快得分越高。
+///
+/// 分段由 [`ScoringConfig`] 的 `speed_fast_ms`、`speed_slow_ms`、`speed_max_ms` 决定，
+/// 曲线在各分段处连续。没有测得响应时间（`None`）时为 0。
+pub fn speed_to_score(speed_ms: Option<f64>, config: &ScoringConfig) -> f64 {
+    let (fast, slow, max) = (config.speed_fast_ms, config.speed_slow_ms, config.speed_max_ms);
+    let Some(speed_ms) = speed_ms else {
         return 0.0;
-    }
+    };
 
-    if speed < 300.0 {
+    if speed_ms <= fast {
         1.0
-    } else if speed < 1000.0 {
-        // 300ms 到 1000ms：对数型递减
-        let ratio = (speed - 300.0) / 700.0; // 归一化到 0~1
-        1.0 - ratio.powf(0.5) // 平滑递减
-    } else if speed < 5000.0 {
-        // 1s~5s 之间：线性快速下降
-        let ratio = (speed - 1000.0) / 4000.0;
-        (0.3 - ratio).max(0.0) // 最多给 0.3 分
+    } else if speed_ms < slow {
+        // fast 到 slow：平方根型递减，从 1.0 平滑降到 SLOW_SPEED_SCORE
+        let ratio = (speed_ms - fast) / (slow - fast);
+        1.0 - (1.0 - SLOW_SPEED_SCORE) * ratio.sqrt()
+    } else if speed_ms < max {
+        // slow 到 max：线性下降到 0
+        let ratio = (speed_ms - slow) / (max - slow);
+        SLOW_SPEED_SCORE * (1.0 - ratio)
     } else {
         0.0
     }
@@ -43,6 +55,7 @@ pub fn speed_to_score(speed: f64) -> f64 {
 
 
 /// 表名基本校验
+#[allow(clippy::let_and_return)]
 pub fn validate_table_name(name: &str) -> bool {
     // 限定表名为英文字母、下划线、数字，且不能以数字开头
     let is_valid = name
@@ -0,0 +1,226 @@
+//! 存储后端一致性测试：任何 [`ProxyStorage`] 实现都必须通过的公共用例。
+//!
+//! 各后端在自己的测试模块中构造存储实例后调用 [`run`]：
+//!
+//! - SQLite 使用内存数据库、内存存储直接构造，随 `cargo test` 运行；
+//! - MySQL / PostgreSQL / Redis 需要本地服务，默认忽略，设置 `MYSQL_TEST_URL` / `POSTGRES_TEST_URL` /
+//!   `REDIS_TEST_URL` 后通过 `cargo test --features mysql,postgres,redis -- --ignored` 运行。
+//!
+//! 传入的代理表必须为空；检测记录与站点结果表可能与其他数据共用，
+//! 因此用例只使用随机端口的代理，互不干扰。
+
+use crate::db::manager::ProxyStorage;
+use crate::db::query::BATCH_ROWS;
+use crate::model::{
+    Anonymity, CheckErrorKind, CheckSource, Protocol, Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult,
+};
+use chrono::{Duration, Utc};
+
+/// 用例使用的代理 IP。
+const IP: &str = "10.21.0.1";
+
+/// 依次执行全部一致性用例。
+pub async fn run(storage: &impl ProxyStorage) {
+    let base = rand::random_range(10000..60000u32);
+    let port = |offset: u32| (base + offset).to_string();
+
+    empty_pool(storage).await;
+    basic_proxy(storage, &port(0)).await;
+    quality_proxy(storage, &port(1)).await;
+    query_proxies(storage, &port(2), &port(3)).await;
+    random_proxy(storage).await;
+    remove_proxy(storage, &port(2)).await;
+    bulk_proxies(storage, &port(6), &port(7)).await;
+    checks_history(storage, &port(4)).await;
+    site_results(storage, &port(5)).await;
+}
+
+fn quality(port: &str, score: f64, protocol: Protocol) -> Proxy {
+    Proxy {
+        protocol,
+        speed: Some(0.5),
+        success_rate: Some(0.75),
+        stability: Some(0.25),
+        anonymity: Some(Anonymity::Elite),
+        score: Some(score),
+        last_checked: Some(Utc::now().naive_utc()),
+        ..Proxy::new(IP.into(), port.into())
+    }
+}
+
+async fn empty_pool(storage: &impl ProxyStorage) {
+    assert!(storage.list_all_proxies().await.unwrap().is_empty(), "一致性测试需要空的代理表");
+    assert!(storage.random_proxy().await.is_err());
+    assert!(!storage.remove_proxy(IP, "1").await.unwrap());
+    assert_eq!(storage.count().await.unwrap(), 0);
+}
+
+async fn basic_proxy(storage: &impl ProxyStorage, port: &str) {
+    let basic = ProxyBasic::new(IP, port).with_protocol(Protocol::Socks5);
+    storage.insert_basic_proxy(&basic).await.unwrap();
+    storage.insert_basic_proxy(&basic).await.unwrap();
+
+    let found = storage.find_proxy_by_ip_port(IP, port).await.unwrap().unwrap();
+    assert_eq!(found.protocol, Protocol::Socks5);
+    assert!(!found.tampered);
+    assert!(storage.find_proxy_by_ip_port(IP, "1").await.unwrap().is_none());
+    assert_eq!(storage.list_all_proxies().await.unwrap().len(), 1);
+}
+
+async fn quality_proxy(storage: &impl ProxyStorage, port: &str) {
+    storage.upsert_quality_proxy(&quality(port, 0.5, Protocol::Http)).await.unwrap();
+    let mut updated = quality(port, 0.75, Protocol::Https);
+    updated.tampered = true;
+    storage.upsert_quality_proxy(&updated).await.unwrap();
+
+    let found = storage.find_proxy_by_ip_port(IP, port).await.unwrap().unwrap();
+    assert_eq!(found.protocol, Protocol::Https);
+    assert_eq!(found.score, Some(0.75));
+    assert_eq!(found.success_rate, Some(0.75));
+    assert_eq!(found.anonymity, Some(Anonymity::Elite));
+    assert!(found.tampered);
+    assert!(found.last_checked.is_some());
+    assert_eq!(storage.list_all_proxies().await.unwrap().len(), 2);
+}
+
+async fn query_proxies(storage: &impl ProxyStorage, high: &str, low: &str) {
+    storage.upsert_quality_proxy(&quality(high, 1.0, Protocol::Socks5)).await.unwrap();
+    storage.upsert_quality_proxy(&quality(low, 0.25, Protocol::Http)).await.unwrap();
+
+    let proxies = storage.query_proxies(&ProxyQuery { min_score: Some(0.5), ..Default::default() }).await.unwrap();
+    assert_eq!(proxies.iter().map(|p| p.port.as_str()).collect::<Vec<_>>(), vec![high]);
+
+    let query = ProxyQuery { include_tampered: true, limit: Some(2), ..Default::default() };
+    let proxies = storage.query_proxies(&query).await.unwrap();
+    assert_eq!(proxies.len(), 2);
+    assert!(proxies[0].score >= proxies[1].score);
+
+    let query = ProxyQuery { protocol: Some(Protocol::Http), ..Default::default() };
+    let proxies = storage.query_proxies(&query).await.unwrap();
+    assert_eq!(proxies.iter().map(|p| p.port.as_str()).collect::<Vec<_>>(), vec![low]);
+}
+
+async fn random_proxy(storage: &impl ProxyStorage) {
+    let ports: Vec<String> = storage.list_all_proxies().await.unwrap().into_iter().map(|p| p.port).collect();
+    for _ in 0..5 {
+        let proxy = storage.random_proxy().await.unwrap();
+        assert_eq!(proxy.ip, IP);
+        assert!(ports.contains(&proxy.port));
+    }
+}
+
+async fn remove_proxy(storage: &impl ProxyStorage, port: &str) {
+    let total = storage.count().await.unwrap();
+    assert!(storage.remove_proxy(IP, port).await.unwrap());
+    assert!(!storage.remove_proxy(IP, port).await.unwrap());
+    assert!(storage.find_proxy_by_ip_port(IP, port).await.unwrap().is_none());
+    assert_eq!(storage.count().await.unwrap(), total - 1);
+
+    let rest: Vec<ProxyBasic> = storage.list_all_proxies().await.unwrap().iter().map(|p| p.basic()).collect();
+    assert_eq!(storage.remove_many(&rest).await.unwrap(), total - 1);
+    assert_eq!(storage.count().await.unwrap(), 0);
+}
+
+async fn bulk_proxies(storage: &impl ProxyStorage, first: &str, second: &str) {
+    storage.upsert_many(&[]).await.unwrap();
+    storage
+        .upsert_many(&[quality(first, 0.25, Protocol::Http), quality(second, 0.5, Protocol::Http), quality(first, 0.75, Protocol::Socks5)])
+        .await
+        .unwrap();
+    assert_eq!(storage.count().await.unwrap(), 2);
+    let found = storage.find_proxy_by_ip_port(IP, first).await.unwrap().unwrap();
+    assert_eq!((found.protocol, found.score), (Protocol::Socks5, Some(0.75)));
+
+    storage.upsert_many(&[quality(second, 1.0, Protocol::Https)]).await.unwrap();
+    assert_eq!(storage.find_proxy_by_ip_port(IP, second).await.unwrap().unwrap().score, Some(1.0));
+
+    let removals = [ProxyBasic::new(IP, first), ProxyBasic::new(IP, second), ProxyBasic::new(IP, "1")];
+    assert_eq!(storage.remove_many(&[]).await.unwrap(), 0);
+    assert_eq!(storage.remove_many(&removals).await.unwrap(), 2);
+    assert_eq!(storage.count().await.unwrap(), 0);
+}
+
+async fn checks_history(storage: &impl ProxyStorage, port: &str) {
+    let now = Utc::now().naive_utc();
+    let check = |minutes: i64, success: bool| ProxyCheck {
+        ip: IP.into(),
+        port: port.into(),
+        checked_at: now - Duration::minutes(minutes),
+        source: if success { CheckSource::Verify } else { CheckSource::Feedback },
+        target: "https://example.com".into(),
+        success,
+        latency: success.then_some(0.5),
+        status_code: success.then_some(200),
+        error_kind: (!success).then_some(CheckErrorKind::Tampered),
+    };
+    storage.insert_checks(&[]).await.unwrap();
+    storage.insert_checks(&[check(120, true), check(30, true), check(1, false)]).await.unwrap();
+
+    let checks = storage.list_checks(IP, port, 2).await.unwrap();
+    assert_eq!(checks.len(), 2);
+    assert_eq!(checks[0].source, CheckSource::Feedback);
+    assert_eq!(checks[0].error_kind, Some(CheckErrorKind::Tampered));
+    assert_eq!(checks[1].latency, Some(0.5));
+    assert!(checks[0].checked_at >= checks[1].checked_at);
+    assert!(storage.list_all_checks().await.unwrap().iter().filter(|c| c.port == port).count() == 3);
+
+    assert!(storage.prune_checks(now - Duration::minutes(60)).await.unwrap() >= 1);
+    assert_eq!(storage.list_checks(IP, port, 10).await.unwrap().len(), 2);
+
+    // 超过单条语句行数上限的批量写入
+    let bulk: Vec<_> = (0..BATCH_ROWS + 1).map(|_| check(0, true)).collect();
+    storage.insert_checks(&bulk).await.unwrap();
+    assert_eq!(storage.list_checks(IP, port, BATCH_ROWS as u32 + 10).await.unwrap().len(), BATCH_ROWS + 3);
+}
+
+async fn site_results(storage: &impl ProxyStorage, port: &str) {
+    storage.upsert_quality_proxy(&quality(port, 0.5, Protocol::Http)).await.unwrap();
+    let site = |name: &str, success_rate: f64| ProxySiteResult {
+        ip: IP.into(),
+        port: port.into(),
+        site: name.into(),
+        speed: (success_rate > 0.0).then_some(0.5),
+        success_rate,
+        score: success_rate,
+        last_checked: Utc::now().naive_utc(),
+    };
+    storage.upsert_site_results(&[site("site_a", 0.0), site("site_b", 1.0)]).await.unwrap();
+    storage.upsert_site_results(&[site("site_a", 0.5)]).await.unwrap();
+
+    let sites = storage.list_site_results(IP, port).await.unwrap();
+    assert_eq!(sites.iter().map(|s| (s.site.as_str(), s.success_rate)).collect::<Vec<_>>(), vec![("site_a", 0.5), ("site_b", 1.0)]);
+
+    for (name, expected) in [("site_a", 1), ("site_b", 1), ("site_c", 0)] {
+        let query = ProxyQuery { site: Some(name.into()), ..Default::default() };
+        assert_eq!(storage.query_proxies(&query).await.unwrap().len(), expected, "site = {}", name);
+    }
+
+    // 删除代理时一并删除站点结果，重新收录后不再满足站点过滤条件；检测记录保留，用于查看下线原因
+    let check = ProxyCheck {
+        ip: IP.into(),
+        port: port.into(),
+        checked_at: Utc::now().naive_utc(),
+        source: CheckSource::Verify,
+        target: "site_a".into(),
+        success: true,
+        latency: Some(0.5),
+        status_code: Some(200),
+        error_kind: None,
+    };
+    let site_query = ProxyQuery { site: Some("site_b".into()), ..Default::default() };
+    for (round, remove_many) in [false, true].into_iter().enumerate() {
+        storage.upsert_site_results(&[site("site_b", 1.0)]).await.unwrap();
+        storage.insert_checks(std::slice::from_ref(&check)).await.unwrap();
+        if remove_many {
+            assert_eq!(storage.remove_many(&[ProxyBasic::new(IP, port)]).await.unwrap(), 1);
+        } else {
+            assert!(storage.remove_proxy(IP, port).await.unwrap());
+        }
+        assert!(storage.list_site_results(IP, port).await.unwrap().is_empty());
+        assert_eq!(storage.list_checks(IP, port, 10).await.unwrap().len(), round + 1);
+
+        storage.insert_basic_proxy(&ProxyBasic::new(IP, port)).await.unwrap();
+        assert!(storage.query_proxies(&site_query).await.unwrap().is_empty());
+    }
+    assert!(storage.remove_proxy(IP, port).await.unwrap());
+}
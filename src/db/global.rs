@@ -1,7 +1,7 @@
 use once_cell::sync::OnceCell;
 use crate::db::manager::StorageBackend;
 
-/// 全局的可配置后端存储，支持 Sqlite/MySQL/Postgres
+/// 全局的可配置后端存储，支持 Sqlite/MySQL/Postgres/内存
 static GLOBAL_STORAGE: OnceCell<StorageBackend> = OnceCell::new();
 
 /// 初始化全局存储，程序启动时调用一次即可
@@ -18,4 +18,4 @@ pub async fn init() -> anyhow::Result<()> {
     let storage = StorageBackend::new().await?;
     set_global_storage(storage);
     Ok(())
-}
\ No newline at end of file
+}
@@ -3,7 +3,7 @@
 
 //! 存储模块：支持多种数据库后端的代理数据读写接口。
 //!
-//! 支持的后端包括：SQLite、MySQL、PostgreSQL（按编译特性启用）
+//! 支持的后端包括：SQLite、MySQL、PostgreSQL、Redis（按编译特性启用），以及不持久化的内存存储
 //! 提供统一的异步 trait [`ProxyStorage`]，便于通过 [`StorageBackend`] 多态调度。
 use async_trait::async_trait;
 use anyhow::Result;
@@ -11,8 +11,14 @@ use anyhow::Result;
 use crate::db::mysql::MySqlStorage;
 #[cfg(feature = "postgres")]
 use crate::db::postgres::PgStorage;
+#[cfg(feature = "redis")]
+use crate::db::redis::RedisStorage;
 use crate::db::sqlite::SqliteStorage;
-use crate::model::{Proxy, ProxyBasic, APP_CONFIG};
+use crate::db::memory::MemoryStorage;
+use crate::common::cache::{cache_remove_proxies, cache_remove_proxy, cache_upsert_proxies, cache_upsert_proxy};
+use crate::db::migration::PlannedMigration;
+use crate::model::{Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult, APP_CONFIG};
+use chrono::NaiveDateTime;
 
 /// 定义代理存储操作的通用异步接口。
 ///
@@ -32,13 +38,47 @@ pub trait ProxyStorage: Send + Sync {
     /// 列出数据库中所有代理记录。
     async fn list_all_proxies(&self) -> Result<Vec<Proxy>>;
 
+    /// 按查询条件过滤、排序并分页查询代理，条件在数据库中执行。
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>>;
+
     async fn random_proxy(&self) -> Result<ProxyBasic>;
-    async fn remove_proxy(&self, ip: &str) -> Result<bool>;
+
+    /// 删除指定 IP 和端口的代理及其站点结果，返回代理是否存在；检测记录保留，由 [`prune_checks`](Self::prune_checks) 按保留期清理。
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool>;
+
+    /// 在一个事务中批量插入或更新代理，同一代理出现多次时以最后一条为准。
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()>;
+
+    /// 在一个事务中按 IP 和端口批量删除代理及其站点结果（检测记录保留），返回实际删除的代理条数。
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64>;
+
+    /// 代理总数。
+    async fn count(&self) -> Result<u64>;
+
+    /// 批量写入代理检测历史记录。
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()>;
+
+    /// 查询指定代理最近的检测记录，按检测时间倒序，最多返回 `limit` 条。
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>>;
+
+    /// 删除早于 `before` 的检测记录，返回删除的条数。
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64>;
+
+    /// 列出全部检测记录，按检测时间正序，用于离线训练评分模型。
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>>;
+
+    /// 插入或更新代理在各目标站点上的验证结果。
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()>;
+
+    /// 查询代理在各目标站点上的验证结果。
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>>;
 }
 
 /// 数据库后端枚举，按启用特性动态支持多种数据库驱动。
 ///
-/// 编译期通过 `features = ["sqlite", "mysql", "postgres"]` 控制可用性。
+/// 写入与删除成功后会同步更新代理池缓存，保证缓存与存储一致。
+///
+/// 编译期通过 `features = ["sqlite", "mysql", "postgres", "redis"]` 控制可用性。
 /// 运行时可通过配置项动态选择使用哪种后端。
 #[derive(Debug)]
 pub enum StorageBackend {
@@ -53,12 +93,19 @@ pub enum StorageBackend {
     /// PostgreSQL 存储实现（事务强、扩展性好）
     #[cfg(feature = "postgres")]
     Postgres(PgStorage),
+
+    /// Redis 存储实现（多个实例共享代理池）
+    #[cfg(feature = "redis")]
+    Redis(RedisStorage),
+
+    /// 内存存储实现（不持久化，用于测试与临时部署）
+    Memory(MemoryStorage),
 }
 
 impl StorageBackend {
-    /// 根据配置项创建对应的数据库后端实例。
+    /// 根据配置项创建对应的数据库后端实例，并执行尚未执行的表结构迁移。
     ///
-    /// 依据 `APP_CONFIG.db.driver` 字符串值（如 "sqlite", "mysql", "postgres"），
+    /// 依据 `APP_CONFIG.db.driver` 字符串值（如 "sqlite", "mysql", "postgres", "redis", "memory"），
     /// 创建相应的存储后端实例。
     ///
     /// # 返回
@@ -71,9 +118,46 @@ impl StorageBackend {
             "mysql" => Ok(Self::MySql(MySqlStorage::new().await?)),
             #[cfg(feature = "postgres")]
             "postgres" => Ok(Self::Postgres(PgStorage::new().await?)),
+            #[cfg(feature = "redis")]
+            "redis" => Ok(Self::Redis(RedisStorage::new().await?)),
+            "memory" => Ok(Self::Memory(MemoryStorage::new())),
+            other => Err(anyhow::anyhow!("Unsupported DB type: {}", other)),
+        }
+    }
+
+    /// 根据配置项连接数据库，不执行迁移，供 `migrate` 子命令预览或执行迁移。
+    pub async fn connect() -> Result<Self> {
+        let db = &APP_CONFIG.db;
+        match db.driver.as_str() {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::Sqlite(SqliteStorage::connect(&db.connection_string, &db.table_name, db.max_connections).await?)),
+            #[cfg(feature = "mysql")]
+            "mysql" => Ok(Self::MySql(MySqlStorage::connect(&db.connection_string, &db.table_name, db.max_connections).await?)),
+            #[cfg(feature = "postgres")]
+            "postgres" => Ok(Self::Postgres(PgStorage::connect(&db.connection_string, &db.table_name, db.max_connections).await?)),
+            #[cfg(feature = "redis")]
+            "redis" => Ok(Self::Redis(RedisStorage::new().await?)),
+            "memory" => Ok(Self::Memory(MemoryStorage::new())),
             other => Err(anyhow::anyhow!("Unsupported DB type: {}", other)),
         }
     }
+
+    /// 执行尚未执行的表结构迁移，`dry_run` 为 `true` 时只返回待执行的迁移。
+    ///
+    /// Redis 与内存存储没有表结构，始终返回空列表。
+    pub async fn migrate(&self, dry_run: bool) -> Result<Vec<PlannedMigration>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.migrate(dry_run).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.migrate(dry_run).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.migrate(dry_run).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(_) => Ok(Vec::new()),
+            Self::Memory(_) => Ok(Vec::new()),
+        }
+    }
 }
 
 #[async_trait]
@@ -86,6 +170,9 @@ impl ProxyStorage for StorageBackend {
             Self::MySql(s) => s.insert_basic_proxy(proxy).await,
             #[cfg(feature = "postgres")]
             Self::Postgres(s) => s.insert_basic_proxy(proxy).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.insert_basic_proxy(proxy).await,
+            Self::Memory(s) => s.insert_basic_proxy(proxy).await,
         }
     }
 
@@ -97,7 +184,12 @@ impl ProxyStorage for StorageBackend {
             Self::MySql(s) => s.upsert_quality_proxy(proxy).await,
             #[cfg(feature = "postgres")]
             Self::Postgres(s) => s.upsert_quality_proxy(proxy).await,
-        }
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.upsert_quality_proxy(proxy).await,
+            Self::Memory(s) => s.upsert_quality_proxy(proxy).await,
+        }?;
+        cache_upsert_proxy(proxy);
+        Ok(())
     }
 
     async fn find_proxy_by_ip_port(&self, ip: &str, port: &str) -> Result<Option<Proxy>> {
@@ -108,6 +200,9 @@ impl ProxyStorage for StorageBackend {
             Self::MySql(s) => s.find_proxy_by_ip_port(ip, port).await,
             #[cfg(feature = "postgres")]
             Self::Postgres(s) => s.find_proxy_by_ip_port(ip, port).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.find_proxy_by_ip_port(ip, port).await,
+            Self::Memory(s) => s.find_proxy_by_ip_port(ip, port).await,
         }
     }
 
@@ -119,6 +214,23 @@ impl ProxyStorage for StorageBackend {
             Self::MySql(s) => s.list_all_proxies().await,
             #[cfg(feature = "postgres")]
             Self::Postgres(s) => s.list_all_proxies().await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.list_all_proxies().await,
+            Self::Memory(s) => s.list_all_proxies().await,
+        }
+    }
+
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.query_proxies(query).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.query_proxies(query).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.query_proxies(query).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.query_proxies(query).await,
+            Self::Memory(s) => s.query_proxies(query).await,
         }
     }
 
@@ -130,17 +242,155 @@ impl ProxyStorage for StorageBackend {
             Self::MySql(s) => s.random_proxy().await,
             #[cfg(feature = "postgres")]
             Self::Postgres(s) => s.random_proxy().await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.random_proxy().await,
+            Self::Memory(s) => s.random_proxy().await,
+        }
+    }
+
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool> {
+        let removed = match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.remove_proxy(ip, port).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.remove_proxy(ip, port).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.remove_proxy(ip, port).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.remove_proxy(ip, port).await,
+            Self::Memory(s) => s.remove_proxy(ip, port).await,
+        }?;
+        cache_remove_proxy(ip, port);
+        Ok(removed)
+    }
+
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.upsert_many(proxies).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.upsert_many(proxies).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.upsert_many(proxies).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.upsert_many(proxies).await,
+            Self::Memory(s) => s.upsert_many(proxies).await,
+        }?;
+        cache_upsert_proxies(proxies);
+        Ok(())
+    }
+
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64> {
+        let removed = match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.remove_many(proxies).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.remove_many(proxies).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.remove_many(proxies).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.remove_many(proxies).await,
+            Self::Memory(s) => s.remove_many(proxies).await,
+        }?;
+        cache_remove_proxies(proxies);
+        Ok(removed)
+    }
+
+    async fn count(&self) -> Result<u64> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.count().await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.count().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.count().await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.count().await,
+            Self::Memory(s) => s.count().await,
+        }
+    }
+
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.insert_checks(checks).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.insert_checks(checks).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.insert_checks(checks).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.insert_checks(checks).await,
+            Self::Memory(s) => s.insert_checks(checks).await,
+        }
+    }
+
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.list_checks(ip, port, limit).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.list_checks(ip, port, limit).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.list_checks(ip, port, limit).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.list_checks(ip, port, limit).await,
+            Self::Memory(s) => s.list_checks(ip, port, limit).await,
+        }
+    }
+
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.prune_checks(before).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.prune_checks(before).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.prune_checks(before).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.prune_checks(before).await,
+            Self::Memory(s) => s.prune_checks(before).await,
+        }
+    }
+
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.list_all_checks().await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.list_all_checks().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.list_all_checks().await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.list_all_checks().await,
+            Self::Memory(s) => s.list_all_checks().await,
+        }
+    }
+
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(s) => s.upsert_site_results(results).await,
+            #[cfg(feature = "mysql")]
+            Self::MySql(s) => s.upsert_site_results(results).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(s) => s.upsert_site_results(results).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.upsert_site_results(results).await,
+            Self::Memory(s) => s.upsert_site_results(results).await,
         }
     }
 
-    async fn remove_proxy(&self, ip: &str) -> Result<bool> {
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>> {
         match self {
             #[cfg(feature = "sqlite")]
-            Self::Sqlite(s) => s.remove_proxy(ip).await,
+            Self::Sqlite(s) => s.list_site_results(ip, port).await,
             #[cfg(feature = "mysql")]
-            Self::MySql(s) => s.remove_proxy(ip).await,
+            Self::MySql(s) => s.list_site_results(ip, port).await,
             #[cfg(feature = "postgres")]
-            Self::Postgres(s) => s.remove_proxy(ip).await,
+            Self::Postgres(s) => s.list_site_results(ip, port).await,
+            #[cfg(feature = "redis")]
+            Self::Redis(s) => s.list_site_results(ip, port).await,
+            Self::Memory(s) => s.list_site_results(ip, port).await,
         }
     }
 }
@@ -0,0 +1,199 @@
+//! 内存存储模块：基于进程内的并发集合实现 [`ProxyStorage`]。
+//!
+//! 数据只保存在当前进程中，退出即丢失；没有表结构，也无需迁移。
+//! 适用于单元测试（直接构造 [`MemoryStorage`]，不依赖配置文件与数据库文件）
+//! 以及无需持久化的临时部署（`[db] driver = "memory"`）。
+
+use crate::db::manager::ProxyStorage;
+use crate::db::query::{checked_since, matches_query, sort_proxies};
+use crate::model::{Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult, SortField};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rand::seq::{IteratorRandom, SliceRandom};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::RwLock;
+
+/// 代理的唯一键：`(ip, port)`。
+type Key = (String, String);
+
+fn key(ip: &str, port: &str) -> Key {
+    (ip.to_string(), port.to_string())
+}
+
+/// 内存存储实现，代理、检测记录与站点结果分别保存在读写锁保护的集合中。
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    proxies: RwLock<HashMap<Key, Proxy>>,
+    /// 按写入顺序保存的检测记录。
+    checks: RwLock<Vec<ProxyCheck>>,
+    /// 每个代理在各站点上的结果，按站点名排序。
+    sites: RwLock<HashMap<Key, BTreeMap<String, ProxySiteResult>>>,
+}
+
+impl MemoryStorage {
+    /// 创建一个空的内存存储。
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ProxyStorage for MemoryStorage {
+    async fn insert_basic_proxy(&self, proxy: &ProxyBasic) -> Result<()> {
+        self.proxies
+            .write()
+            .unwrap()
+            .entry(key(&proxy.ip, &proxy.port))
+            .or_insert_with(|| Proxy { protocol: proxy.protocol, ..Proxy::new(proxy.ip.clone(), proxy.port.clone()) });
+        Ok(())
+    }
+
+    async fn upsert_quality_proxy(&self, proxy: &Proxy) -> Result<()> {
+        self.upsert_many(std::slice::from_ref(proxy)).await
+    }
+
+    async fn find_proxy_by_ip_port(&self, ip: &str, port: &str) -> Result<Option<Proxy>> {
+        Ok(self.proxies.read().unwrap().get(&key(ip, port)).cloned())
+    }
+
+    async fn list_all_proxies(&self) -> Result<Vec<Proxy>> {
+        let mut proxies: Vec<Proxy> = self.proxies.read().unwrap().values().cloned().collect();
+        sort_proxies(&mut proxies, SortField::Score, true);
+        Ok(proxies)
+    }
+
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>> {
+        let since = checked_since(query);
+        let sites = self.sites.read().unwrap();
+        let site_ok = |proxy: &Proxy| {
+            query.site.as_ref().is_none_or(|site| {
+                sites
+                    .get(&key(&proxy.ip, &proxy.port))
+                    .and_then(|results| results.get(site))
+                    .is_some_and(|r| r.success_rate > 0.0)
+            })
+        };
+        let mut proxies: Vec<Proxy> = self
+            .proxies
+            .read()
+            .unwrap()
+            .values()
+            .filter(|p| matches_query(p, query, since) && site_ok(p))
+            .cloned()
+            .collect();
+        drop(sites);
+
+        match query.sort.field {
+            SortField::Random => proxies.shuffle(&mut rand::rng()),
+            field => sort_proxies(&mut proxies, field, query.sort.descending),
+        }
+
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.map_or(usize::MAX, |l| l as usize);
+        Ok(proxies.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn random_proxy(&self) -> Result<ProxyBasic> {
+        self.proxies
+            .read()
+            .unwrap()
+            .values()
+            .choose(&mut rand::rng())
+            .map(|p| p.basic())
+            .ok_or_else(|| anyhow::anyhow!("代理池为空"))
+    }
+
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool> {
+        Ok(self.remove_many(&[ProxyBasic::new(ip, port)]).await? > 0)
+    }
+
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()> {
+        let mut stored = self.proxies.write().unwrap();
+        for proxy in proxies {
+            stored.insert(key(&proxy.ip, &proxy.port), proxy.clone());
+        }
+        Ok(())
+    }
+
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64> {
+        let keys: HashSet<Key> = proxies.iter().map(|p| key(&p.ip, &p.port)).collect();
+        let mut stored = self.proxies.write().unwrap();
+        let mut sites = self.sites.write().unwrap();
+        let removed = keys.iter().filter(|k| stored.remove(k).is_some()).count();
+        for k in &keys {
+            sites.remove(k);
+        }
+        Ok(removed as u64)
+    }
+
+    async fn count(&self) -> Result<u64> {
+        Ok(self.proxies.read().unwrap().len() as u64)
+    }
+
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()> {
+        self.checks.write().unwrap().extend_from_slice(checks);
+        Ok(())
+    }
+
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>> {
+        // 先按写入顺序倒排，稳定排序后同一时间的记录仍是后写入的在前
+        let mut checks: Vec<ProxyCheck> =
+            self.checks.read().unwrap().iter().rev().filter(|c| c.ip == ip && c.port == port).cloned().collect();
+        checks.sort_by_key(|c| std::cmp::Reverse(c.checked_at));
+        checks.truncate(limit as usize);
+        Ok(checks)
+    }
+
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64> {
+        let mut checks = self.checks.write().unwrap();
+        let total = checks.len();
+        checks.retain(|c| c.checked_at >= before);
+        Ok((total - checks.len()) as u64)
+    }
+
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>> {
+        let mut checks = self.checks.read().unwrap().clone();
+        checks.sort_by_key(|c| c.checked_at);
+        Ok(checks)
+    }
+
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()> {
+        let mut sites = self.sites.write().unwrap();
+        for result in results {
+            sites.entry(key(&result.ip, &result.port)).or_default().insert(result.site.clone(), result.clone());
+        }
+        Ok(())
+    }
+
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>> {
+        let sites = self.sites.read().unwrap();
+        Ok(sites.get(&key(ip, port)).map(|results| results.values().cloned().collect()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_conformance() {
+        crate::db::conformance::run(&MemoryStorage::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_sort_nulls_last() {
+        let storage = MemoryStorage::new();
+        let proxy = |port: &str, speed: Option<f64>| Proxy { speed, ..Proxy::new("1.1.1.1".into(), port.into()) };
+        storage.upsert_many(&[proxy("1", Some(2.0)), proxy("2", None), proxy("3", Some(1.0))]).await.unwrap();
+
+        for (sort, expected) in [("speed", ["3", "1", "2"]), ("-speed", ["1", "3", "2"])] {
+            let query = ProxyQuery { sort: sort.parse().unwrap(), ..Default::default() };
+            let ports: Vec<String> = storage.query_proxies(&query).await.unwrap().into_iter().map(|p| p.port).collect();
+            assert_eq!(ports, expected, "sort = {}", sort);
+        }
+
+        let query = ProxyQuery { max_speed: Some(1.5), offset: Some(0), limit: Some(5), ..Default::default() };
+        assert_eq!(storage.query_proxies(&query).await.unwrap().len(), 1);
+    }
+}
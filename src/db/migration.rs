@@ -0,0 +1,342 @@
+//! 版本化的表结构迁移。
+//!
+//! 每个迁移有递增的版本号，按数据库方言生成各自的语句；已执行的版本记录在
+//! [`MIGRATIONS_TABLE`] 表中（按代理表名区分，同一数据库可存放多个代理表），
+//! 启动时（`db::init`）只执行尚未执行过的迁移。
+//!
+//! 迁移机制引入前的数据库可能由旧版 `CREATE TABLE` 建出任意新旧程度的表结构，
+//! 因此添加列的步骤会先检查列是否已存在，存在时跳过。
+//!
+//! 新增表结构变更时，在 [`MIGRATIONS`] 末尾追加一个版本号更大的迁移，不要修改已发布的迁移。
+
+use crate::db::query::{checks_table, sites_table};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::info;
+
+/// 记录已执行迁移版本的表名。
+pub const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+/// 数据库方言。
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    MySql,
+    Postgres,
+}
+
+/// 迁移中的一个步骤。
+enum Step {
+    /// 直接执行的语句。
+    Sql(String),
+    /// 为表添加一列，列已存在时跳过。
+    AddColumn { table: String, column: &'static str, definition: &'static str },
+}
+
+/// 一个编号的迁移，`steps` 根据方言与代理表名生成具体步骤。
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    steps: fn(Dialect, &str) -> Vec<Step>,
+}
+
+/// 全部迁移，按版本号递增排列。
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "创建代理表", steps: create_proxies },
+    Migration { version: 2, description: "代理表添加 protocol 列", steps: add_protocol },
+    Migration { version: 3, description: "代理表添加 anonymity 列", steps: add_anonymity },
+    Migration { version: 4, description: "创建检测历史表", steps: create_checks },
+    Migration { version: 5, description: "创建站点验证结果表", steps: create_sites },
+    Migration { version: 6, description: "代理表添加 tampered 列", steps: add_tampered },
+];
+
+fn create_proxies(dialect: Dialect, table: &str) -> Vec<Step> {
+    let sql = match dialect {
+        Dialect::Sqlite => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ip TEXT NOT NULL,
+                port TEXT NOT NULL,
+                speed REAL DEFAULT 0.0,
+                success_rate REAL DEFAULT 0.0,
+                stability REAL DEFAULT 0.0,
+                score REAL DEFAULT 0.0,
+                last_checked DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(ip, port)
+            )
+            "#,
+            table
+        ),
+        Dialect::MySql => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id INT PRIMARY KEY AUTO_INCREMENT,
+                ip VARCHAR(255) NOT NULL,
+                port VARCHAR(10) NOT NULL,
+                speed FLOAT DEFAULT 0.0,
+                success_rate FLOAT DEFAULT 0.0,
+                stability FLOAT DEFAULT 0.0,
+                score FLOAT DEFAULT 0.0,
+                last_checked DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(ip, port)
+            )
+            "#,
+            table
+        ),
+        Dialect::Postgres => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id SERIAL PRIMARY KEY,
+                ip TEXT NOT NULL,
+                port TEXT NOT NULL,
+                speed DOUBLE PRECISION DEFAULT 0.0,
+                success_rate DOUBLE PRECISION DEFAULT 0.0,
+                stability DOUBLE PRECISION DEFAULT 0.0,
+                score DOUBLE PRECISION DEFAULT 0.0,
+                last_checked TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(ip, port)
+            )
+            "#,
+            table
+        ),
+    };
+    vec![Step::Sql(sql)]
+}
+
+fn add_protocol(dialect: Dialect, table: &str) -> Vec<Step> {
+    let definition = match dialect {
+        Dialect::MySql => "VARCHAR(10) NOT NULL DEFAULT 'http'",
+        Dialect::Sqlite | Dialect::Postgres => "TEXT NOT NULL DEFAULT 'http'",
+    };
+    vec![Step::AddColumn { table: table.to_string(), column: "protocol", definition }]
+}
+
+fn add_anonymity(dialect: Dialect, table: &str) -> Vec<Step> {
+    let definition = match dialect {
+        Dialect::MySql => "VARCHAR(16) NULL",
+        Dialect::Sqlite | Dialect::Postgres => "TEXT",
+    };
+    vec![Step::AddColumn { table: table.to_string(), column: "anonymity", definition }]
+}
+
+fn create_checks(dialect: Dialect, table: &str) -> Vec<Step> {
+    let checks = checks_table(table);
+    match dialect {
+        Dialect::Sqlite => vec![
+            Step::Sql(format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    ip TEXT NOT NULL,
+                    port TEXT NOT NULL,
+                    checked_at DATETIME NOT NULL,
+                    source TEXT NOT NULL,
+                    target TEXT NOT NULL,
+                    success BOOLEAN NOT NULL,
+                    latency REAL,
+                    status_code INTEGER,
+                    error_kind TEXT
+                )
+                "#,
+                checks
+            )),
+            Step::Sql(format!("CREATE INDEX IF NOT EXISTS idx_{0}_proxy ON {0} (ip, port, checked_at)", checks)),
+        ],
+        Dialect::MySql => vec![Step::Sql(format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id BIGINT PRIMARY KEY AUTO_INCREMENT,
+                ip VARCHAR(255) NOT NULL,
+                port VARCHAR(10) NOT NULL,
+                checked_at DATETIME NOT NULL,
+                source VARCHAR(16) NOT NULL,
+                target TEXT NOT NULL,
+                success BOOLEAN NOT NULL,
+                latency DOUBLE NULL,
+                status_code INT NULL,
+                error_kind VARCHAR(32) NULL,
+                INDEX idx_proxy (ip, port, checked_at)
+            )
+            "#,
+            checks
+        ))],
+        Dialect::Postgres => vec![
+            Step::Sql(format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id BIGSERIAL PRIMARY KEY,
+                    ip TEXT NOT NULL,
+                    port TEXT NOT NULL,
+                    checked_at TIMESTAMP NOT NULL,
+                    source TEXT NOT NULL,
+                    target TEXT NOT NULL,
+                    success BOOLEAN NOT NULL,
+                    latency DOUBLE PRECISION,
+                    status_code INTEGER,
+                    error_kind TEXT
+                )
+                "#,
+                checks
+            )),
+            Step::Sql(format!("CREATE INDEX IF NOT EXISTS idx_{0}_proxy ON {0} (ip, port, checked_at)", checks)),
+        ],
+    }
+}
+
+fn create_sites(dialect: Dialect, table: &str) -> Vec<Step> {
+    let sites = sites_table(table);
+    let sql = match dialect {
+        Dialect::Sqlite => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ip TEXT NOT NULL,
+                port TEXT NOT NULL,
+                site TEXT NOT NULL,
+                speed REAL,
+                success_rate REAL NOT NULL DEFAULT 0.0,
+                score REAL NOT NULL DEFAULT 0.0,
+                last_checked DATETIME NOT NULL,
+                UNIQUE(ip, port, site)
+            )
+            "#,
+            sites
+        ),
+        Dialect::MySql => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id BIGINT PRIMARY KEY AUTO_INCREMENT,
+                ip VARCHAR(255) NOT NULL,
+                port VARCHAR(10) NOT NULL,
+                site VARCHAR(64) NOT NULL,
+                speed DOUBLE NULL,
+                success_rate DOUBLE NOT NULL DEFAULT 0.0,
+                score DOUBLE NOT NULL DEFAULT 0.0,
+                last_checked DATETIME NOT NULL,
+                UNIQUE(ip, port, site)
+            )
+            "#,
+            sites
+        ),
+        Dialect::Postgres => format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id BIGSERIAL PRIMARY KEY,
+                ip TEXT NOT NULL,
+                port TEXT NOT NULL,
+                site TEXT NOT NULL,
+                speed DOUBLE PRECISION,
+                success_rate DOUBLE PRECISION NOT NULL DEFAULT 0.0,
+                score DOUBLE PRECISION NOT NULL DEFAULT 0.0,
+                last_checked TIMESTAMP NOT NULL,
+                UNIQUE(ip, port, site)
+            )
+            "#,
+            sites
+        ),
+    };
+    vec![Step::Sql(sql)]
+}
+
+fn add_tampered(_dialect: Dialect, table: &str) -> Vec<Step> {
+    vec![Step::AddColumn { table: table.to_string(), column: "tampered", definition: "BOOLEAN NOT NULL DEFAULT FALSE" }]
+}
+
+/// 记录迁移版本的建表语句，版本按代理表名分别记录。
+pub fn migrations_table_sql(dialect: Dialect) -> String {
+    let timestamp = match dialect {
+        Dialect::Postgres => "TIMESTAMP",
+        Dialect::Sqlite | Dialect::MySql => "DATETIME",
+    };
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (proxy_table VARCHAR(64) NOT NULL, version BIGINT NOT NULL, description VARCHAR(255) NOT NULL, applied_at {} NOT NULL, PRIMARY KEY (proxy_table, version))",
+        MIGRATIONS_TABLE, timestamp
+    )
+}
+
+/// 一个待执行（或已执行）迁移的具体内容。
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedMigration {
+    pub version: i64,
+    pub description: &'static str,
+    /// 将要执行的语句。
+    pub statements: Vec<String>,
+    /// 因列已存在而跳过的步骤，格式为 `表名.列名`。
+    pub skipped: Vec<String>,
+}
+
+/// 执行迁移所需的数据库操作，由各存储后端实现。
+#[async_trait]
+pub trait SchemaStore: Send + Sync {
+    /// 数据库方言。
+    fn dialect(&self) -> Dialect;
+
+    /// 代理表名。
+    fn proxy_table(&self) -> &str;
+
+    /// 表是否存在。
+    async fn table_exists(&self, table: &str) -> Result<bool>;
+
+    /// 表中是否已有指定列，表不存在时返回 `false`。
+    async fn column_exists(&self, table: &str, column: &str) -> Result<bool>;
+
+    /// 代理表已执行的迁移版本，调用前需确认版本表存在。
+    async fn applied_versions(&self) -> Result<Vec<i64>>;
+
+    /// 在一个事务中执行迁移的全部语句并记录版本，版本表不存在时先创建。
+    async fn apply(&self, migration: &PlannedMigration) -> Result<()>;
+}
+
+/// 执行全部尚未执行的迁移，`dry_run` 为 `true` 时只生成计划、不修改数据库。
+///
+/// # 返回
+/// 按版本号排列的待执行迁移及其语句。
+pub async fn migrate(store: &impl SchemaStore, dry_run: bool) -> Result<Vec<PlannedMigration>> {
+    let applied = if store.table_exists(MIGRATIONS_TABLE).await? {
+        store.applied_versions().await?
+    } else {
+        Vec::new()
+    };
+
+    let mut plan = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        let mut planned = PlannedMigration {
+            version: migration.version,
+            description: migration.description,
+            statements: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for step in (migration.steps)(store.dialect(), store.proxy_table()) {
+            match step {
+                Step::Sql(sql) => planned.statements.push(sql),
+                Step::AddColumn { table, column, definition } => {
+                    if store.column_exists(&table, column).await? {
+                        planned.skipped.push(format!("{}.{}", table, column));
+                    } else {
+                        planned.statements.push(format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition));
+                    }
+                }
+            }
+        }
+
+        if !dry_run {
+            store.apply(&planned).await?;
+            info!("🧱 已执行迁移 #{}：{}", planned.version, planned.description);
+        }
+        plan.push(planned);
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_increasing() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+    }
+}
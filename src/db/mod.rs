@@ -1,7 +1,14 @@
 pub mod manager;
+pub mod memory;
+pub mod migration;
 pub mod sqlite;
 pub mod mysql;
 pub mod postgres;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub(crate) mod query;
+#[cfg(test)]
+mod conformance;
 mod global;
 
 pub use global::*;
\ No newline at end of file
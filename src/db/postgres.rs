@@ -6,15 +6,23 @@
 //! 支持表结构初始化、基础代理插入、代理质量信息更新、按 IP/端口查找和列表排序查询。
 //! 使用 `sqlx` 异步驱动，需启用 `postgres` 编译特性。
 use anyhow::Result;
+use chrono::NaiveDateTime;
 use async_trait::async_trait;
 
 #[cfg(feature = "postgres")]
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use crate::model::{APP_CONFIG, Proxy, ProxyBasic};
+use sqlx::{PgPool, Postgres, postgres::PgPoolOptions};
+use crate::model::{APP_CONFIG, Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult};
+use crate::db::migration::{self, migrations_table_sql, Dialect, PlannedMigration, SchemaStore, MIGRATIONS_TABLE};
+use crate::db::query::{
+    checks_table, delete_proxies, insert_checks, insert_proxies, select_proxies, sites_table, unique_proxies, BATCH_ROWS,
+};
 use crate::db::manager::ProxyStorage;
 use tracing::info;
 use crate::common::utils::validate_table_name;
 
+/// 批量写入代理时追加的冲突处理子句：代理已存在时更新协议与质量信息。
+const UPSERT_CLAUSE: &str = " ON CONFLICT(ip, port) DO UPDATE SET protocol=EXCLUDED.protocol, speed=EXCLUDED.speed, success_rate=EXCLUDED.success_rate, stability=EXCLUDED.stability, anonymity=EXCLUDED.anonymity, tampered=EXCLUDED.tampered, score=EXCLUDED.score, last_checked=EXCLUDED.last_checked";
+
 /// PostgreSQL 数据库存储实现，持有一个连接池 [`PgPool`]。
 ///
 /// 实现了 [`ProxyStorage`] trait，用于插入、更新、查询代理数据。
@@ -22,53 +30,101 @@ use crate::common::utils::validate_table_name;
 #[derive(Debug)]
 pub struct PgStorage {
     pool: PgPool,
+    /// 代理表名。
+    table: String,
+    /// 检测历史表名，由代理表名派生。
+    checks_table: String,
+    /// 分站点验证结果表名，由代理表名派生。
+    sites_table: String,
 }
 #[cfg(feature = "postgres")]
 impl PgStorage {
-    /// 创建一个 PostgreSQL 存储实例并自动初始化数据表结构。
+    /// 创建一个 PostgreSQL 存储实例并执行表结构迁移。
     ///
     /// # 返回
-    /// 返回 [`PgStorage`] 实例，如果连接失败或建表失败则返回错误。
+    /// 返回 [`PgStorage`] 实例，如果连接失败或迁移失败则返回错误。
     pub async fn new() -> Result<Self> {
+        let storage =
+            Self::connect(&APP_CONFIG.db.connection_string, &APP_CONFIG.db.table_name, APP_CONFIG.db.max_connections)
+                .await?;
+        storage.migrate(false).await?;
+        Ok(storage)
+    }
+
+    /// 使用指定的连接地址与代理表名创建存储实例，不依赖配置文件，也不执行迁移。
+    pub async fn connect(url: &str, table: &str, max_connections: u32) -> Result<Self> {
+        if !validate_table_name(table) {
+            anyhow::bail!("❌ 表名不合法：{}，请使用字母数字下划线，且不能以数字开头", table);
+        }
+
         let pool = PgPoolOptions::new()
-            .max_connections(APP_CONFIG.db.max_connections)
-            .connect(&APP_CONFIG.db.connection_string)
+            .max_connections(max_connections)
+            .connect(url)
             .await?;
 
-        let storage = Self { pool };
-        storage.create_table().await?;
+        let storage = Self {
+            pool,
+            table: table.to_string(),
+            checks_table: checks_table(table),
+            sites_table: sites_table(table),
+        };
         info!("✅ PostgresSQL 数据库连接成功");
         Ok(storage)
     }
 
-    /// 创建用于存储代理信息的 PostgreSQL 表（如果不存在）。
-    ///
-    /// 表名从配置项 [`APP_CONFIG.db.table_name`] 读取，
-    /// 并设置 `(ip, port)` 组合唯一约束。
-    async fn create_table(&self) -> Result<()> {
-        if !validate_table_name(&APP_CONFIG.db.table_name) {
-            panic!("❌ 配置中的表名不合法：{}，请使用字母数字下划线，且不能以数字开头", APP_CONFIG.db.table_name);
+    /// 执行尚未执行的表结构迁移，`dry_run` 为 `true` 时只返回待执行的迁移而不修改数据库。
+    pub async fn migrate(&self, dry_run: bool) -> Result<Vec<PlannedMigration>> {
+        migration::migrate(self, dry_run).await
+    }
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl SchemaStore for PgStorage {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn proxy_table(&self) -> &str {
+        &self.table
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = lower($1)")
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    async fn column_exists(&self, table: &str, column: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = lower($1) AND column_name = $2")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<i64>> {
+        let sql = format!("SELECT version FROM {} WHERE proxy_table = $1 ORDER BY version", MIGRATIONS_TABLE);
+        Ok(sqlx::query_scalar(&sql).bind(&self.table).fetch_all(&self.pool).await?)
+    }
+
+    async fn apply(&self, migration: &PlannedMigration) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&migrations_table_sql(Dialect::Postgres)).execute(&mut *tx).await?;
+        for statement in &migration.statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
         }
-        
-        let table = &APP_CONFIG.db.table_name;
-        sqlx::query(&format!(
-            r#"
-            CREATE TABLE IF NOT EXISTS {} (
-                id SERIAL PRIMARY KEY,
-                ip TEXT NOT NULL,
-                port TEXT NOT NULL,
-                speed DOUBLE PRECISION DEFAULT 0.0,
-                success_rate DOUBLE PRECISION DEFAULT 0.0,
-                stability DOUBLE PRECISION DEFAULT 0.0,
-                score DOUBLE PRECISION DEFAULT 0.0,
-                last_checked TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                UNIQUE(ip, port)
-            );
-            "#,
-            table
-        ))
-            .execute(&self.pool)
+        sqlx::query(&format!("INSERT INTO {} (proxy_table, version, description, applied_at) VALUES ($1, $2, $3, $4)", MIGRATIONS_TABLE))
+            .bind(&self.table)
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(chrono::Utc::now().naive_utc())
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 }
@@ -77,46 +133,23 @@ impl PgStorage {
 #[async_trait]
 impl ProxyStorage for PgStorage {
     async fn insert_basic_proxy(&self, proxy: &ProxyBasic) -> Result<()> {
-        let table = &APP_CONFIG.db.table_name;
-        let sql = format!("INSERT INTO {} (ip, port) VALUES ($1, $2) ON CONFLICT DO NOTHING", table);
+        let table = &self.table;
+        let sql = format!("INSERT INTO {} (ip, port, protocol) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING", table);
         sqlx::query(&sql)
             .bind(&proxy.ip)
             .bind(&proxy.port)
+            .bind(proxy.protocol.as_str())
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
     async fn upsert_quality_proxy(&self, proxy: &Proxy) -> Result<()> {
-        let table = &APP_CONFIG.db.table_name;
-        let sql = format!(
-            r#"
-            INSERT INTO {} (ip, port, speed, success_rate, stability, score, last_checked)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            ON CONFLICT(ip, port) DO UPDATE SET
-                speed=EXCLUDED.speed,
-                success_rate=EXCLUDED.success_rate,
-                stability=EXCLUDED.stability,
-                score=EXCLUDED.score,
-                last_checked=EXCLUDED.last_checked
-            "#,
-            table
-        );
-        sqlx::query(&sql)
-            .bind(&proxy.ip)
-            .bind(&proxy.port)
-            .bind(&proxy.speed)
-            .bind(&proxy.success_rate)
-            .bind(&proxy.stability)
-            .bind(&proxy.score)
-            .bind(&proxy.last_checked)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+        self.upsert_many(std::slice::from_ref(proxy)).await
     }
 
     async fn find_proxy_by_ip_port(&self, ip: &str, port: &str) -> Result<Option<Proxy>> {
-        let table = &APP_CONFIG.db.table_name;
+        let table = &self.table;
         let sql = format!("SELECT * FROM {} WHERE ip = $1 AND port = $2", table);
         let result = sqlx::query_as::<_, Proxy>(&sql)
             .bind(ip)
@@ -127,7 +160,7 @@ impl ProxyStorage for PgStorage {
     }
 
     async fn list_all_proxies(&self) -> Result<Vec<Proxy>> {
-        let table = &APP_CONFIG.db.table_name;
+        let table = &self.table;
         let sql = format!("SELECT * FROM {} ORDER BY score DESC", table);
         let proxies = sqlx::query_as::<_, Proxy>(&sql)
             .fetch_all(&self.pool)
@@ -135,12 +168,137 @@ impl ProxyStorage for PgStorage {
         Ok(proxies)
     }
 
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>> {
+        let table = &self.table;
+        let mut builder = select_proxies::<Postgres>(table, query, "RANDOM()");
+        let proxies = builder.build_query_as::<Proxy>().fetch_all(&self.pool).await?;
+        Ok(proxies)
+    }
+
     async fn random_proxy(&self) -> Result<ProxyBasic> {
-        todo!()
+        let sql = format!("SELECT ip, port, protocol FROM {} ORDER BY RANDOM() LIMIT 1", self.table);
+        sqlx::query_as::<_, ProxyBasic>(&sql)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("代理池为空"))
     }
 
-    async fn remove_proxy(&self, ip: &str) -> Result<bool> {
-        todo!()
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        // 站点结果随代理一并删除，避免重新收录后沿用旧的验证结果；检测记录保留，由保留期清理
+        let sql = format!("DELETE FROM {} WHERE ip = $1 AND port = $2", self.sites_table);
+        sqlx::query(&sql).bind(ip).bind(port).execute(&mut *tx).await?;
+        let sql = format!("DELETE FROM {} WHERE ip = $1 AND port = $2", self.table);
+        let result = sqlx::query(&sql).bind(ip).bind(port).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()> {
+        let proxies = unique_proxies(proxies);
+        let mut tx = self.pool.begin().await?;
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            let mut builder = insert_proxies::<Postgres>(&self.table, chunk);
+            builder.push(UPSERT_CLAUSE);
+            builder.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = 0;
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            delete_proxies::<Postgres>(&self.sites_table, chunk).build().execute(&mut *tx).await?;
+            removed += delete_proxies::<Postgres>(&self.table, chunk).build().execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn count(&self) -> Result<u64> {
+        let sql = format!("SELECT COUNT(*) FROM {}", self.table);
+        let count: i64 = sqlx::query_scalar(&sql).fetch_one(&self.pool).await?;
+        Ok(count as u64)
+    }
+
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()> {
+        if checks.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        for chunk in checks.chunks(BATCH_ROWS) {
+            insert_checks::<Postgres>(&self.checks_table, chunk).build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>> {
+        let sql = format!(
+            "SELECT * FROM {} WHERE ip = $1 AND port = $2 ORDER BY checked_at DESC, id DESC LIMIT $3",
+            self.checks_table
+        );
+        let checks = sqlx::query_as::<_, ProxyCheck>(&sql)
+            .bind(ip)
+            .bind(port)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(checks)
+    }
+
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64> {
+        let sql = format!("DELETE FROM {} WHERE checked_at < $1", self.checks_table);
+        let result = sqlx::query(&sql).bind(before).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>> {
+        let sql = format!("SELECT * FROM {} ORDER BY checked_at, id", self.checks_table);
+        let checks = sqlx::query_as::<_, ProxyCheck>(&sql).fetch_all(&self.pool).await?;
+        Ok(checks)
+    }
+
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()> {
+        let sql = format!(
+            r#"
+            INSERT INTO {} (ip, port, site, speed, success_rate, score, last_checked)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT(ip, port, site) DO UPDATE SET
+                speed=EXCLUDED.speed,
+                success_rate=EXCLUDED.success_rate,
+                score=EXCLUDED.score,
+                last_checked=EXCLUDED.last_checked
+            "#,
+            self.sites_table
+        );
+        let mut tx = self.pool.begin().await?;
+        for result in results {
+            sqlx::query(&sql)
+                .bind(&result.ip)
+                .bind(&result.port)
+                .bind(&result.site)
+                .bind(result.speed)
+                .bind(result.success_rate)
+                .bind(result.score)
+                .bind(result.last_checked)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>> {
+        let sql = format!("SELECT * FROM {} WHERE ip = $1 AND port = $2 ORDER BY site", self.sites_table);
+        let results = sqlx::query_as::<_, ProxySiteResult>(&sql)
+            .bind(ip)
+            .bind(port)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(results)
     }
 }
 
@@ -151,13 +309,13 @@ mod tests {
     use chrono::{NaiveDateTime, Utc};
     use sqlx::{Encode, Postgres, Type};
     use super::*;
-    use crate::model::{Proxy, ProxyBasic};
+    use crate::model::{Anonymity, Protocol, Proxy, ProxyBasic};
     use crate::db::manager::ProxyStorage;
 
     #[tokio::test]
-    async fn test_create_table() {
+    async fn test_migrate() {
         let storage = PgStorage::new().await.unwrap();
-        let result = storage.create_table().await;
+        let result = storage.migrate(false).await;
         assert!(result.is_ok());
     }
 
@@ -175,9 +333,12 @@ mod tests {
         let proxy = Proxy {
             ip: "127.0.0.1".into(),
             port: "1001".into(),
+            protocol: Protocol::Http,
             speed: Some(100.5),
             success_rate: Some(0.9),
             stability: Some(0.95),
+            anonymity: Some(Anonymity::Elite),
+            tampered: false,
             score: Some(85.0),
             last_checked: Some(Utc::now().naive_utc()),
         };
@@ -206,4 +367,20 @@ mod tests {
         let proxies = result.unwrap();
         assert!(!proxies.is_empty());
     }
+
+    #[tokio::test]
+    #[ignore = "需要 PostgreSQL：设置 POSTGRES_TEST_URL 后使用 --ignored 运行"]
+    async fn test_conformance() {
+        let url = std::env::var("POSTGRES_TEST_URL").expect("未设置 POSTGRES_TEST_URL");
+        let table = format!("conformance_{}", rand::random::<u32>());
+        let storage = PgStorage::connect(&url, &table, 2).await.unwrap();
+        storage.migrate(false).await.unwrap();
+        crate::db::conformance::run(&storage).await;
+        sqlx::query(&format!("DROP TABLE {}", table)).execute(&storage.pool).await.unwrap();
+        sqlx::query(&format!("DELETE FROM {} WHERE proxy_table = $1", MIGRATIONS_TABLE))
+            .bind(&table)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+    }
 }
@@ -0,0 +1,355 @@
+//! 将 [`ProxyQuery`] 转换为 SQL 的公共逻辑，供各数据库后端复用。
+//!
+//! 参数占位符由 [`QueryBuilder`] 按方言生成，各后端只需提供表名与随机函数名。
+//!
+//! 不支持 SQL 的后端（内存、Redis）使用 [`matches_query`] 与 [`sort_proxies`] 在内存中执行相同的条件，
+//! `/proxy` 接口则通过 [`filter_proxies`] 直接在代理池缓存的快照上查询。
+
+use crate::model::{Proxy, ProxyBasic, ProxyCheck, ProxyQuery, SortField};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use rand::seq::SliceRandom;
+use sqlx::{Database, Encode, QueryBuilder, Type};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// 构建 `SELECT * FROM {table} WHERE ... ORDER BY ... LIMIT ... OFFSET ...` 查询。
+///
+/// # 参数
+/// - `table`: 已校验的表名
+/// - `query`: 查询条件
+/// - `random_fn`: 随机排序函数，SQLite/PostgreSQL 为 `RANDOM()`，MySQL 为 `RAND()`
+pub fn select_proxies<'a, DB>(table: &str, query: &ProxyQuery, random_fn: &str) -> QueryBuilder<'a, DB>
+where
+    DB: Database,
+    DB::Arguments<'a>: Default,
+    f64: Encode<'a, DB> + Type<DB>,
+    &'a str: Encode<'a, DB> + Type<DB>,
+    String: Encode<'a, DB> + Type<DB>,
+    NaiveDateTime: Encode<'a, DB> + Type<DB>,
+{
+    let mut builder = QueryBuilder::new(format!("SELECT * FROM {} WHERE 1 = 1", table));
+
+    if let Some(min_score) = query.min_score {
+        builder.push(" AND score >= ").push_bind(min_score);
+    }
+    if let Some(max_speed) = query.max_speed {
+        builder.push(" AND speed <= ").push_bind(max_speed);
+    }
+    if let Some(min_success_rate) = query.min_success_rate {
+        builder.push(" AND success_rate >= ").push_bind(min_success_rate);
+    }
+    if let Some(since) = checked_since(query) {
+        builder.push(" AND last_checked >= ").push_bind(since);
+    }
+    if let Some(protocol) = query.protocol {
+        builder.push(" AND protocol = ").push_bind(protocol.as_str());
+    }
+    if let Some(site) = &query.site {
+        builder
+            .push(format!(
+                " AND EXISTS (SELECT 1 FROM {sites} s WHERE s.ip = {table}.ip AND s.port = {table}.port AND s.site = ",
+                sites = sites_table(table)
+            ))
+            .push_bind(site.clone())
+            .push(" AND s.success_rate > 0)");
+    }
+    if !query.include_tampered {
+        builder.push(" AND NOT tampered");
+    }
+
+    match query.sort.field.column() {
+        // 空值统一排在最后，避免各数据库对 NULL 排序规则不一致
+        Some(column) => {
+            let direction = if query.sort.descending { "DESC" } else { "ASC" };
+            builder.push(format!(" ORDER BY {column} IS NULL, {column} {direction}"));
+        }
+        None => {
+            builder.push(format!(" ORDER BY {}", random_fn));
+        }
+    }
+
+    // 只有 offset 时也需要 LIMIT（MySQL、SQLite 不支持单独的 OFFSET）
+    if query.limit.is_some() || query.offset.is_some() {
+        builder.push(format!(" LIMIT {}", query.limit.map_or(i64::MAX, i64::from)));
+        if let Some(offset) = query.offset {
+            builder.push(format!(" OFFSET {}", offset));
+        }
+    }
+
+    builder
+}
+
+/// `checked_within` 对应的最早检测时间，时间窗口超出可表示范围时取 Unix 纪元。
+pub fn checked_since(query: &ProxyQuery) -> Option<NaiveDateTime> {
+    query.checked_within.map(|secs| {
+        i64::try_from(secs)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .and_then(|window| Utc::now().naive_utc().checked_sub_signed(window))
+            .unwrap_or(DateTime::UNIX_EPOCH.naive_utc())
+    })
+}
+
+/// 代理是否满足查询条件中除 `site` 以外的过滤项，语义与 SQL 一致（空值不满足比较条件）。
+///
+/// `since` 为 [`checked_since`] 的结果，批量过滤时只需计算一次。
+pub fn matches_query(proxy: &Proxy, query: &ProxyQuery, since: Option<NaiveDateTime>) -> bool {
+    let at_least = |value: Option<f64>, min: Option<f64>| min.is_none_or(|min| value.is_some_and(|v| v >= min));
+    at_least(proxy.score, query.min_score)
+        && query.max_speed.is_none_or(|max| proxy.speed.is_some_and(|s| s <= max))
+        && at_least(proxy.success_rate, query.min_success_rate)
+        && since.is_none_or(|since| proxy.last_checked.is_some_and(|t| t >= since))
+        && query.protocol.is_none_or(|p| proxy.protocol == p)
+        && (query.include_tampered || !proxy.tampered)
+}
+
+/// 排序字段的取值，随机排序时为 `None`。
+fn sort_value(proxy: &Proxy, field: SortField) -> Option<f64> {
+    match field {
+        SortField::Score => proxy.score,
+        SortField::Speed => proxy.speed,
+        SortField::SuccessRate => proxy.success_rate,
+        SortField::LastChecked => proxy.last_checked.map(|t| t.and_utc().timestamp_micros() as f64),
+        SortField::Random => None,
+    }
+}
+
+/// 按字段排序，空值统一排在最后，与 [`select_proxies`] 的 `ORDER BY column IS NULL` 一致。
+pub fn sort_proxies<P: Borrow<Proxy>>(proxies: &mut [P], field: SortField, descending: bool) {
+    proxies.sort_by(|a, b| match (sort_value(a.borrow(), field), sort_value(b.borrow(), field)) {
+        (Some(x), Some(y)) => {
+            let ordering = x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+            if descending { ordering.reverse() } else { ordering }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+/// 在内存中按查询条件过滤、排序并分页，返回代理的引用，不复制代理数据。
+///
+/// 不处理 `site` 条件，按站点过滤需要查询存储中的分站点结果。
+pub fn filter_proxies<'a>(proxies: &'a [Proxy], query: &ProxyQuery) -> Vec<&'a Proxy> {
+    let since = checked_since(query);
+    let mut matched: Vec<&Proxy> = proxies.iter().filter(|p| matches_query(p, query, since)).collect();
+    match query.sort.field {
+        SortField::Random => matched.shuffle(&mut rand::rng()),
+        field => sort_proxies(&mut matched, field, query.sort.descending),
+    }
+
+    let offset = query.offset.unwrap_or(0) as usize;
+    let limit = query.limit.map_or(usize::MAX, |l| l as usize);
+    matched.into_iter().skip(offset).take(limit).collect()
+}
+
+/// 代理表对应的检测历史表名：`{table}_checks`，同一数据库中的多个代理表各自记录。
+pub fn checks_table(table: &str) -> String {
+    format!("{}_checks", table)
+}
+
+/// 代理表对应的分站点验证结果表名：`{table}_sites`。
+pub fn sites_table(table: &str) -> String {
+    format!("{}_sites", table)
+}
+
+/// 构建批量写入检测记录的 `INSERT INTO {table} (...) VALUES (...), (...)` 语句。
+///
+/// `table` 为检测历史表名（见 [`checks_table`]），`checks` 不能为空。
+pub fn insert_checks<'a, DB>(table: &str, checks: &'a [ProxyCheck]) -> QueryBuilder<'a, DB>
+where
+    DB: Database,
+    DB::Arguments<'a>: Default,
+    bool: Encode<'a, DB> + Type<DB>,
+    &'a str: Encode<'a, DB> + Type<DB>,
+    NaiveDateTime: Encode<'a, DB> + Type<DB>,
+    Option<f64>: Encode<'a, DB> + Type<DB>,
+    Option<i32>: Encode<'a, DB> + Type<DB>,
+    Option<&'a str>: Encode<'a, DB> + Type<DB>,
+{
+    let mut builder = QueryBuilder::new(format!(
+        "INSERT INTO {} (ip, port, checked_at, source, target, success, latency, status_code, error_kind) ",
+        table
+    ));
+    builder.push_values(checks, |mut row, check| {
+        row.push_bind(check.ip.as_str())
+            .push_bind(check.port.as_str())
+            .push_bind(check.checked_at)
+            .push_bind(check.source.as_str())
+            .push_bind(check.target.as_str())
+            .push_bind(check.success)
+            .push_bind(check.latency)
+            .push_bind(check.status_code)
+            .push_bind(check.error_kind.map(|k| k.as_str()));
+    });
+    builder
+}
+
+/// 批量写入或删除代理时单条语句包含的最大行数，避免超出数据库的绑定参数上限。
+pub const BATCH_ROWS: usize = 500;
+
+/// 按 IP 与端口去重，同一代理保留最后一条，顺序与首次出现的位置一致。
+///
+/// PostgreSQL 不允许同一条 `INSERT ... ON CONFLICT` 语句多次更新同一行，批量写入前需先去重。
+pub fn unique_proxies(proxies: &[Proxy]) -> Vec<&Proxy> {
+    let mut index = HashMap::new();
+    let mut unique: Vec<&Proxy> = Vec::with_capacity(proxies.len());
+    for proxy in proxies {
+        match index.get(&(proxy.ip.as_str(), proxy.port.as_str())) {
+            Some(&i) => unique[i] = proxy,
+            None => {
+                index.insert((proxy.ip.as_str(), proxy.port.as_str()), unique.len());
+                unique.push(proxy);
+            }
+        }
+    }
+    unique
+}
+
+/// 构建批量写入代理的 `INSERT INTO {table} (...) VALUES (...), (...)` 语句，冲突处理子句由各后端追加。
+///
+/// `proxies` 不能为空，且不能包含重复的代理（见 [`unique_proxies`]）。
+pub fn insert_proxies<'a, DB>(table: &str, proxies: &[&'a Proxy]) -> QueryBuilder<'a, DB>
+where
+    DB: Database,
+    DB::Arguments<'a>: Default,
+    bool: Encode<'a, DB> + Type<DB>,
+    &'a str: Encode<'a, DB> + Type<DB>,
+    Option<f64>: Encode<'a, DB> + Type<DB>,
+    Option<&'a str>: Encode<'a, DB> + Type<DB>,
+    Option<NaiveDateTime>: Encode<'a, DB> + Type<DB>,
+{
+    let mut builder = QueryBuilder::new(format!(
+        "INSERT INTO {} (ip, port, protocol, speed, success_rate, stability, anonymity, tampered, score, last_checked) ",
+        table
+    ));
+    builder.push_values(proxies.iter().copied(), |mut row, proxy| {
+        row.push_bind(proxy.ip.as_str())
+            .push_bind(proxy.port.as_str())
+            .push_bind(proxy.protocol.as_str())
+            .push_bind(proxy.speed)
+            .push_bind(proxy.success_rate)
+            .push_bind(proxy.stability)
+            .push_bind(proxy.anonymity.map(|a| a.as_str()))
+            .push_bind(proxy.tampered)
+            .push_bind(proxy.score)
+            .push_bind(proxy.last_checked);
+    });
+    builder
+}
+
+/// 构建按 IP 与端口批量删除代理的 `DELETE FROM {table} WHERE (ip = ? AND port = ?) OR ...` 语句。
+///
+/// `proxies` 不能为空。
+pub fn delete_proxies<'a, DB>(table: &str, proxies: &'a [ProxyBasic]) -> QueryBuilder<'a, DB>
+where
+    DB: Database,
+    DB::Arguments<'a>: Default,
+    &'a str: Encode<'a, DB> + Type<DB>,
+{
+    let mut builder = QueryBuilder::new(format!("DELETE FROM {} WHERE ", table));
+    for (i, proxy) in proxies.iter().enumerate() {
+        if i > 0 {
+            builder.push(" OR ");
+        }
+        builder
+            .push("(ip = ")
+            .push_bind(proxy.ip.as_str())
+            .push(" AND port = ")
+            .push_bind(proxy.port.as_str())
+            .push(")");
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Protocol;
+    use sqlx::Sqlite;
+
+    #[test]
+    fn test_select_proxies_sql() {
+        let query = ProxyQuery::default();
+        let builder = select_proxies::<Sqlite>("proxies", &query, "RANDOM()");
+        assert_eq!(builder.sql(), "SELECT * FROM proxies WHERE 1 = 1 AND NOT tampered ORDER BY score IS NULL, score DESC");
+
+        let query = ProxyQuery {
+            min_score: Some(0.5),
+            max_speed: Some(2.0),
+            protocol: Some(Protocol::Socks5),
+            offset: Some(10),
+            sort: "random".parse().unwrap(),
+            ..Default::default()
+        };
+        let builder = select_proxies::<Sqlite>("proxies", &query, "RANDOM()");
+        assert_eq!(
+            builder.sql(),
+            format!(
+                "SELECT * FROM proxies WHERE 1 = 1 AND score >= ? AND speed <= ? AND protocol = ? AND NOT tampered ORDER BY RANDOM() LIMIT {} OFFSET 10",
+                i64::MAX
+            )
+        );
+
+        let query = ProxyQuery { site: Some("baidu".into()), include_tampered: true, ..Default::default() };
+        let builder = select_proxies::<Sqlite>("proxies", &query, "RANDOM()");
+        assert!(builder.sql().contains(
+            "AND EXISTS (SELECT 1 FROM proxies_sites s WHERE s.ip = proxies.ip AND s.port = proxies.port AND s.site = ? AND s.success_rate > 0)"
+        ));
+        assert!(!builder.sql().contains("tampered"));
+    }
+
+    #[test]
+    fn test_filter_proxies() {
+        let proxy = |port: &str, score: Option<f64>, tampered: bool| {
+            let mut proxy = Proxy::new("1.1.1.1".into(), port.into());
+            proxy.score = score;
+            proxy.tampered = tampered;
+            proxy
+        };
+        let proxies = vec![
+            proxy("1", Some(0.3), false),
+            proxy("2", None, false),
+            proxy("3", Some(0.9), false),
+            proxy("4", Some(1.0), true),
+            proxy("5", Some(0.6), false),
+        ];
+        let ports = |query: &ProxyQuery| filter_proxies(&proxies, query).iter().map(|p| p.port.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(ports(&ProxyQuery::default()), vec!["3", "5", "1", "2"]);
+        assert_eq!(ports(&ProxyQuery { min_score: Some(0.5), ..Default::default() }), vec!["3", "5"]);
+        let page = ProxyQuery { offset: Some(1), limit: Some(2), sort: "score".parse().unwrap(), ..Default::default() };
+        assert_eq!(ports(&page), vec!["5", "3"]);
+        let random = ProxyQuery { include_tampered: true, sort: "random".parse().unwrap(), ..Default::default() };
+        assert_eq!(ports(&random).len(), 5);
+    }
+
+    #[test]
+    fn test_checked_since_overflow() {
+        let query = ProxyQuery { checked_within: Some(u64::MAX), ..Default::default() };
+        assert_eq!(checked_since(&query), Some(DateTime::UNIX_EPOCH.naive_utc()));
+        let query = ProxyQuery { checked_within: Some(60), ..Default::default() };
+        assert!(checked_since(&query).unwrap() > Utc::now().naive_utc() - TimeDelta::minutes(2));
+    }
+
+    #[test]
+    fn test_batch_sql() {
+        let mut updated = Proxy::new("1.1.1.1".into(), "80".into());
+        updated.score = Some(0.9);
+        let proxies = vec![
+            Proxy::new("1.1.1.1".into(), "80".into()),
+            Proxy::new("1.1.1.1".into(), "81".into()),
+            updated,
+        ];
+        let unique = unique_proxies(&proxies);
+        assert_eq!(unique.iter().map(|p| (p.port.as_str(), p.score)).collect::<Vec<_>>(), vec![("80", Some(0.9)), ("81", None)]);
+        assert!(insert_proxies::<Sqlite>("proxies", &unique).sql().ends_with("VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?), (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"));
+
+        let basics = vec![ProxyBasic::new("1.1.1.1", "80"), ProxyBasic::new("2.2.2.2", "81")];
+        assert_eq!(
+            delete_proxies::<Sqlite>("proxies", &basics).sql(),
+            "DELETE FROM proxies WHERE (ip = ? AND port = ?) OR (ip = ? AND port = ?)"
+        );
+    }
+}
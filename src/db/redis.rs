@@ -0,0 +1,514 @@
+//! Redis 存储模块：实现 [`ProxyStorage`] trait，便于多个实例共享同一个代理池。
+//!
+//! 以 `[db] table_name` 作为键前缀，数据布局如下：
+//!
+//! - `{prefix}:proxy:{ip}:{port}`：代理哈希，字段与代理表的列一致，空值字段不写入；
+//! - `{prefix}:scores`：按评分排序的有序集合，成员为 `ip:port`，未评分的代理分值为 `-inf`，
+//!   随机取代理与按评分取前 N 条均为 O(log n)；
+//! - `{prefix}:sites:{ip}:{port}`：各目标站点的验证结果（JSON），字段为站点名；
+//! - `{prefix}:checks` / `{prefix}:checks:{ip}:{port}`：全部与单个代理的检测记录，按检测时间排序；
+//! - `{prefix}:checked`：有检测记录的代理集合。
+//!
+//! 删除代理时其站点结果一并删除，检测记录保留到超出保留期后由 `prune_checks` 清理。
+//!
+//! 各操作的开销（n 为代理总数，k 为实际读取的代理数）：
+//!
+//! - `find_proxy_by_ip_port`、`random_proxy`、`count`：O(1) 或 O(log n)；
+//! - 按评分降序且不带 `site` 条件的查询：沿评分有序集合按页读取，取够 `offset + limit` 条即停止，O(log n + k)；
+//! - 其他排序方式或带 `site` 条件的查询：需要读取全部（满足 `min_score` 的）代理后在内存中过滤排序，O(n)。
+//!
+//! 需启用 `redis` 编译特性，要求 Redis 6.2 及以上（`ZRANDMEMBER`）。
+
+use crate::common::utils::validate_table_name;
+use crate::db::manager::ProxyStorage;
+use crate::db::query::{checked_since, matches_query, sort_proxies, BATCH_ROWS};
+use crate::model::{Anonymity, Protocol, Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult, SortField, APP_CONFIG};
+use ::redis::aio::MultiplexedConnection;
+use ::redis::{AsyncCommands, Client, SortedSetAddOptions};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+/// 代理哈希中时间字段的格式。
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// 按评分分页读取代理时每次读取的最大条数。
+const PAGE_SIZE: usize = 1000;
+
+/// Redis 存储实现，持有一个可并发复用的多路复用连接。
+#[derive(Clone)]
+pub struct RedisStorage {
+    conn: MultiplexedConnection,
+    /// 键前缀。
+    prefix: String,
+}
+
+impl std::fmt::Debug for RedisStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisStorage").field("prefix", &self.prefix).finish()
+    }
+}
+
+impl RedisStorage {
+    /// 按 `[db]` 配置连接 Redis，`table_name` 作为键前缀。
+    pub async fn new() -> Result<Self> {
+        let db = &APP_CONFIG.db;
+        Self::connect(&db.connection_string, &db.table_name).await
+    }
+
+    /// 连接指定的 Redis，`prefix` 为全部键的前缀。
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self> {
+        if !validate_table_name(prefix) {
+            anyhow::bail!("Invalid key prefix: {}", prefix);
+        }
+        let conn = Client::open(url)?.get_multiplexed_async_connection().await?;
+        info!("✅ Redis 连接成功");
+        Ok(Self { conn, prefix: prefix.to_string() })
+    }
+
+    fn proxy_key(&self, member: &str) -> String {
+        format!("{}:proxy:{}", self.prefix, member)
+    }
+
+    fn scores_key(&self) -> String {
+        format!("{}:scores", self.prefix)
+    }
+
+    fn sites_key(&self, member: &str) -> String {
+        format!("{}:sites:{}", self.prefix, member)
+    }
+
+    fn checks_key(&self) -> String {
+        format!("{}:checks", self.prefix)
+    }
+
+    fn proxy_checks_key(&self, member: &str) -> String {
+        format!("{}:checks:{}", self.prefix, member)
+    }
+
+    /// 有检测记录的代理集合，清理过期记录时使用。
+    fn checked_key(&self) -> String {
+        format!("{}:checked", self.prefix)
+    }
+
+    /// 检测记录序号，保证同一时间的记录按写入顺序排列。
+    fn check_seq_key(&self) -> String {
+        format!("{}:check_seq", self.prefix)
+    }
+
+    /// 批量读取代理哈希，已不存在的代理被跳过。
+    async fn load(&self, members: &[String]) -> Result<Vec<Proxy>> {
+        if members.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = ::redis::pipe();
+        for member in members {
+            pipe.hgetall(self.proxy_key(member));
+        }
+        let hashes: Vec<HashMap<String, String>> = pipe.query_async(&mut self.conn.clone()).await?;
+        hashes.into_iter().filter(|h| !h.is_empty()).map(parse_proxy).collect()
+    }
+
+    /// 批量读取代理并按查询条件过滤，包括 `site` 条件。
+    async fn load_matching(&self, members: &[String], query: &ProxyQuery, since: Option<NaiveDateTime>) -> Result<Vec<Proxy>> {
+        let page: Vec<Proxy> = self.load(members).await?.into_iter().filter(|p| matches_query(p, query, since)).collect();
+        let Some(site) = &query.site else { return Ok(page) };
+        let passed = self.passed_sites(&page, site).await?;
+        Ok(page.into_iter().zip(passed).filter(|(_, ok)| *ok).map(|(p, _)| p).collect())
+    }
+
+    /// 沿评分有序集合从高到低按页读取，直到满足条件的代理达到 `wanted` 条。
+    ///
+    /// 以上一页最低分作为下一页的上界（并跳过该分值上已读取的成员）分页，
+    /// 读取期间其他实例写入或删除代理不会导致漏读或重复。
+    async fn scan_by_score(&self, query: &ProxyQuery, since: Option<NaiveDateTime>, min: &str, wanted: usize) -> Result<Vec<Proxy>> {
+        let page_size = wanted.clamp(16, PAGE_SIZE);
+        let mut conn = self.conn.clone();
+        let mut max = "+inf".to_string();
+        let mut skip = 0;
+        let mut seen = HashSet::new();
+        let mut proxies = Vec::new();
+        loop {
+            let page: Vec<(String, f64)> = conn
+                .zrevrangebyscore_limit_withscores(self.scores_key(), &max, min, skip as isize, page_size as isize)
+                .await?;
+            let Some(&(_, lowest)) = page.last() else { break };
+            let ties = page.iter().rev().take_while(|(_, score)| *score == lowest).count();
+            let bound = score_bound(lowest);
+            skip = if bound == max { skip + ties } else { ties };
+            max = bound;
+
+            let full = page.len() == page_size;
+            let members: Vec<String> = page.into_iter().map(|(m, _)| m).filter(|m| seen.insert(m.clone())).collect();
+            proxies.extend(self.load_matching(&members, query, since).await?);
+            if !full || proxies.len() >= wanted {
+                break;
+            }
+        }
+        Ok(proxies)
+    }
+
+    /// 代理是否在指定站点上验证通过。
+    async fn passed_sites(&self, proxies: &[Proxy], site: &str) -> Result<Vec<bool>> {
+        if proxies.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = ::redis::pipe();
+        for proxy in proxies {
+            pipe.hget(self.sites_key(&member(&proxy.ip, &proxy.port)), site);
+        }
+        let results: Vec<Option<String>> = pipe.query_async(&mut self.conn.clone()).await?;
+        results
+            .into_iter()
+            .map(|r| match r {
+                Some(json) => Ok(serde_json::from_str::<ProxySiteResult>(&json)?.success_rate > 0.0),
+                None => Ok(false),
+            })
+            .collect()
+    }
+}
+
+/// 有序集合与哈希键中代理的标识。
+fn member(ip: &str, port: &str) -> String {
+    format!("{}:{}", ip, port)
+}
+
+/// 代理在评分有序集合中的分值，未评分时排在最后。
+fn score_of(proxy: &Proxy) -> f64 {
+    proxy.score.unwrap_or(f64::NEG_INFINITY)
+}
+
+/// 分值作为有序集合范围查询的边界（闭区间）。
+fn score_bound(score: f64) -> String {
+    match score {
+        f64::INFINITY => "+inf".to_string(),
+        f64::NEG_INFINITY => "-inf".to_string(),
+        score => score.to_string(),
+    }
+}
+
+/// 检测记录在有序集合中的分值：检测时间的微秒时间戳。
+fn check_score(time: NaiveDateTime) -> f64 {
+    time.and_utc().timestamp_micros() as f64
+}
+
+/// 代理写入哈希的字段，空值字段不写入。
+fn proxy_fields(proxy: &Proxy) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("ip", proxy.ip.clone()),
+        ("port", proxy.port.clone()),
+        ("protocol", proxy.protocol.as_str().to_string()),
+        ("tampered", u8::from(proxy.tampered).to_string()),
+    ];
+    let optional = [
+        ("speed", proxy.speed.map(|v| v.to_string())),
+        ("success_rate", proxy.success_rate.map(|v| v.to_string())),
+        ("stability", proxy.stability.map(|v| v.to_string())),
+        ("anonymity", proxy.anonymity.map(|a| a.as_str().to_string())),
+        ("score", proxy.score.map(|v| v.to_string())),
+        ("last_checked", proxy.last_checked.map(|t| t.format(TIME_FORMAT).to_string())),
+    ];
+    fields.extend(optional.into_iter().filter_map(|(name, value)| value.map(|v| (name, v))));
+    fields
+}
+
+/// 从代理哈希解析代理。
+fn parse_proxy(mut fields: HashMap<String, String>) -> Result<Proxy> {
+    let float = |fields: &HashMap<String, String>, name: &str| -> Result<Option<f64>> {
+        fields.get(name).map(|v| v.parse::<f64>()).transpose().map_err(|e| anyhow!("代理字段 {} 无效：{}", name, e))
+    };
+    let ip = fields.remove("ip").ok_or_else(|| anyhow!("代理缺少 ip 字段"))?;
+    let port = fields.remove("port").ok_or_else(|| anyhow!("代理缺少 port 字段"))?;
+    Ok(Proxy {
+        protocol: fields.get("protocol").map(|p| p.parse::<Protocol>()).transpose().map_err(|e| anyhow!(e))?.unwrap_or_default(),
+        speed: float(&fields, "speed")?,
+        success_rate: float(&fields, "success_rate")?,
+        stability: float(&fields, "stability")?,
+        anonymity: fields.get("anonymity").map(|a| a.parse::<Anonymity>()).transpose().map_err(|e| anyhow!(e))?,
+        tampered: fields.get("tampered").is_some_and(|t| t == "1"),
+        score: float(&fields, "score")?,
+        last_checked: fields.get("last_checked").map(|t| NaiveDateTime::parse_from_str(t, TIME_FORMAT)).transpose()?,
+        ..Proxy::new(ip, port)
+    })
+}
+
+/// 解析检测记录有序集合的成员：`{序号}|{JSON}`。
+fn parse_check(entry: &str) -> Result<ProxyCheck> {
+    let (_, json) = entry.split_once('|').ok_or_else(|| anyhow!("检测记录格式无效"))?;
+    Ok(serde_json::from_str(json)?)
+}
+
+#[async_trait]
+impl ProxyStorage for RedisStorage {
+    /// 在一个事务中逐个字段 `HSETNX` 并 `ZADD NX`：已存在的代理保持不变，缺失的字段被补齐。
+    async fn insert_basic_proxy(&self, proxy: &ProxyBasic) -> Result<()> {
+        let member = member(&proxy.ip, &proxy.port);
+        let key = self.proxy_key(&member);
+        let fields = [("ip", proxy.ip.as_str()), ("port", proxy.port.as_str()), ("protocol", proxy.protocol.as_str()), ("tampered", "0")];
+
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        for (field, value) in fields {
+            pipe.hset_nx(&key, field, value).ignore();
+        }
+        pipe.zadd_options(self.scores_key(), &member, f64::NEG_INFINITY, &SortedSetAddOptions::add_only()).ignore();
+        pipe.query_async::<()>(&mut self.conn.clone()).await?;
+        Ok(())
+    }
+
+    async fn upsert_quality_proxy(&self, proxy: &Proxy) -> Result<()> {
+        self.upsert_many(std::slice::from_ref(proxy)).await
+    }
+
+    async fn find_proxy_by_ip_port(&self, ip: &str, port: &str) -> Result<Option<Proxy>> {
+        let fields: HashMap<String, String> = self.conn.clone().hgetall(self.proxy_key(&member(ip, port))).await?;
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        parse_proxy(fields).map(Some)
+    }
+
+    async fn list_all_proxies(&self) -> Result<Vec<Proxy>> {
+        let members: Vec<String> = self.conn.clone().zrevrange(self.scores_key(), 0, -1).await?;
+        self.load(&members).await
+    }
+
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>> {
+        let since = checked_since(query);
+        let offset = query.offset.unwrap_or(0) as usize;
+        let min = query.min_score.map_or("-inf".to_string(), score_bound);
+
+        // 按评分降序时有序集合的顺序即结果顺序，取够 offset + limit 条即可停止
+        let mut proxies = if query.sort.field == SortField::Score && query.sort.descending && query.site.is_none() {
+            let wanted = query.limit.map_or(usize::MAX, |l| offset.saturating_add(l as usize));
+            self.scan_by_score(query, since, &min, wanted).await?
+        } else {
+            // 一次取出全部成员，分批读取时成员列表保持一致
+            let members: Vec<String> = self.conn.clone().zrevrangebyscore(self.scores_key(), "+inf", &min).await?;
+            let mut proxies = Vec::new();
+            for chunk in members.chunks(PAGE_SIZE) {
+                proxies.extend(self.load_matching(chunk, query, since).await?);
+            }
+            proxies
+        };
+
+        match query.sort.field {
+            SortField::Random => proxies.shuffle(&mut rand::rng()),
+            field => sort_proxies(&mut proxies, field, query.sort.descending),
+        }
+        let limit = query.limit.map_or(usize::MAX, |l| l as usize);
+        Ok(proxies.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn random_proxy(&self) -> Result<ProxyBasic> {
+        let member: Option<String> = self.conn.clone().zrandmember(self.scores_key(), None).await?;
+        let member = member.ok_or_else(|| anyhow!("代理池为空"))?;
+        self.load(&[member])
+            .await?
+            .pop()
+            .map(|p| p.basic())
+            .ok_or_else(|| anyhow!("代理池为空"))
+    }
+
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool> {
+        Ok(self.remove_many(&[ProxyBasic::new(ip, port)]).await? > 0)
+    }
+
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()> {
+        let mut conn = self.conn.clone();
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            let mut pipe = ::redis::pipe();
+            pipe.atomic();
+            for proxy in chunk {
+                let member = member(&proxy.ip, &proxy.port);
+                let key = self.proxy_key(&member);
+                pipe.del(&key).ignore();
+                pipe.hset_multiple(&key, &proxy_fields(proxy)).ignore();
+                pipe.zadd(self.scores_key(), &member, score_of(proxy)).ignore();
+            }
+            pipe.query_async::<()>(&mut conn).await?;
+        }
+        Ok(())
+    }
+
+    /// 站点结果随代理在同一个事务中删除，检测记录保留，由 `prune_checks` 按保留期清理。
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64> {
+        let mut conn = self.conn.clone();
+        let mut removed = 0;
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            let mut pipe = ::redis::pipe();
+            pipe.atomic();
+            for proxy in chunk {
+                let member = member(&proxy.ip, &proxy.port);
+                pipe.del(self.proxy_key(&member)).ignore();
+                pipe.zrem(self.scores_key(), &member);
+                pipe.del(self.sites_key(&member)).ignore();
+            }
+            let counts: Vec<u64> = pipe.query_async(&mut conn).await?;
+            removed += counts.iter().sum::<u64>();
+        }
+        Ok(removed)
+    }
+
+    async fn count(&self) -> Result<u64> {
+        Ok(self.conn.clone().zcard(self.scores_key()).await?)
+    }
+
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()> {
+        if checks.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.conn.clone();
+        let last: u64 = conn.incr(self.check_seq_key(), checks.len()).await?;
+        let first = last + 1 - checks.len() as u64;
+
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        for (seq, check) in (first..).zip(checks) {
+            let member = member(&check.ip, &check.port);
+            let entry = format!("{:020}|{}", seq, serde_json::to_string(check)?);
+            let score = check_score(check.checked_at);
+            pipe.zadd(self.checks_key(), &entry, score).ignore();
+            pipe.zadd(self.proxy_checks_key(&member), &entry, score).ignore();
+            pipe.sadd(self.checked_key(), &member).ignore();
+        }
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let key = self.proxy_checks_key(&member(ip, port));
+        let entries: Vec<String> = self.conn.clone().zrevrange(key, 0, limit as isize - 1).await?;
+        entries.iter().map(|e| parse_check(e)).collect()
+    }
+
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64> {
+        let max = format!("({}", check_score(before));
+        let mut conn = self.conn.clone();
+        let removed: u64 = conn.zrembyscore(self.checks_key(), "-inf", &max).await?;
+
+        // 代理删除时已移出集合，这里只遍历仍有检测记录的代理，记录清空的代理同时移出集合
+        let members: Vec<String> = conn.smembers(self.checked_key()).await?;
+        for chunk in members.chunks(BATCH_ROWS) {
+            let mut pipe = ::redis::pipe();
+            for member in chunk {
+                pipe.zrembyscore(self.proxy_checks_key(member), "-inf", &max).ignore();
+                pipe.zcard(self.proxy_checks_key(member));
+            }
+            let remaining: Vec<u64> = pipe.query_async(&mut conn).await?;
+            let emptied: Vec<&String> = chunk.iter().zip(remaining).filter(|(_, n)| *n == 0).map(|(m, _)| m).collect();
+            if !emptied.is_empty() {
+                let _: () = conn.srem(self.checked_key(), emptied).await?;
+            }
+        }
+        Ok(removed)
+    }
+
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>> {
+        let entries: Vec<String> = self.conn.clone().zrange(self.checks_key(), 0, -1).await?;
+        entries.iter().map(|e| parse_check(e)).collect()
+    }
+
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()> {
+        if results.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        for result in results {
+            let key = self.sites_key(&member(&result.ip, &result.port));
+            pipe.hset(key, &result.site, serde_json::to_string(result)?).ignore();
+        }
+        pipe.query_async::<()>(&mut self.conn.clone()).await?;
+        Ok(())
+    }
+
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>> {
+        let fields: HashMap<String, String> = self.conn.clone().hgetall(self.sites_key(&member(ip, port))).await?;
+        let mut results = fields
+            .values()
+            .map(|json| serde_json::from_str::<ProxySiteResult>(json))
+            .collect::<Result<Vec<_>, _>>()?;
+        results.sort_by(|a, b| a.site.cmp(&b.site));
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_fields_roundtrip() {
+        let proxy = Proxy {
+            protocol: Protocol::Socks5,
+            speed: Some(0.5),
+            anonymity: Some(Anonymity::Elite),
+            tampered: true,
+            last_checked: Some(chrono::Utc::now().naive_utc()),
+            ..Proxy::new("::1".into(), "1080".into())
+        };
+        let fields = proxy_fields(&proxy).into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        let parsed = parse_proxy(fields).unwrap();
+        assert_eq!((parsed.ip.as_str(), parsed.port.as_str()), ("::1", "1080"));
+        assert_eq!(parsed.protocol, Protocol::Socks5);
+        assert_eq!((parsed.speed, parsed.score, parsed.success_rate), (Some(0.5), None, None));
+        assert_eq!(parsed.anonymity, Some(Anonymity::Elite));
+        assert!(parsed.tampered);
+        assert_eq!(parsed.last_checked, proxy.last_checked);
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 Redis：设置 REDIS_TEST_URL 后使用 --ignored 运行"]
+    async fn test_conformance() {
+        let url = std::env::var("REDIS_TEST_URL").expect("未设置 REDIS_TEST_URL");
+        let prefix = format!("conformance_{}", rand::random::<u32>());
+        let storage = RedisStorage::connect(&url, &prefix).await.unwrap();
+        crate::db::conformance::run(&storage).await;
+
+        cleanup(&storage, &prefix).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 Redis：设置 REDIS_TEST_URL 后使用 --ignored 运行"]
+    async fn test_score_paging_with_ties() {
+        let url = std::env::var("REDIS_TEST_URL").expect("未设置 REDIS_TEST_URL");
+        let prefix = format!("paging_{}", rand::random::<u32>());
+        let storage = RedisStorage::connect(&url, &prefix).await.unwrap();
+        // 同分成员跨越分页边界，且只有四分之一满足协议条件，需要读取多页
+        let proxies: Vec<Proxy> = (0..60)
+            .map(|i| Proxy {
+                protocol: if i % 4 == 0 { Protocol::Socks5 } else { Protocol::Http },
+                score: Some([0.9, 0.5, 0.1][i / 20]),
+                ..Proxy::new("10.0.0.1".into(), (1000 + i).to_string())
+            })
+            .collect();
+        storage.upsert_many(&proxies).await.unwrap();
+
+        let all = storage.query_proxies(&ProxyQuery::default()).await.unwrap();
+        let ports: HashSet<&str> = all.iter().map(|p| p.port.as_str()).collect();
+        assert_eq!((all.len(), ports.len()), (60, 60));
+        assert!(all.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let socks: Vec<&str> = all.iter().filter(|p| p.protocol == Protocol::Socks5).map(|p| p.port.as_str()).collect();
+        let query = ProxyQuery { protocol: Some(Protocol::Socks5), limit: Some(4), offset: Some(7), ..Default::default() };
+        let page = storage.query_proxies(&query).await.unwrap();
+        assert_eq!(page.iter().map(|p| p.port.as_str()).collect::<Vec<_>>(), socks[7..11]);
+
+        cleanup(&storage, &prefix).await;
+    }
+
+    async fn cleanup(storage: &RedisStorage, prefix: &str) {
+        let mut conn = storage.conn.clone();
+        let keys: Vec<String> = conn.keys(format!("{}:*", prefix)).await.unwrap();
+        if !keys.is_empty() {
+            let _: () = conn.del(keys).await.unwrap();
+        }
+    }
+}
@@ -15,56 +15,114 @@
 //! 通过 SQLite 实现高效的代理数据存储与管理。
 
 use crate::db::manager::ProxyStorage;
-use crate::model::{Proxy, ProxyBasic, APP_CONFIG};
+use crate::db::migration::{self, migrations_table_sql, Dialect, PlannedMigration, SchemaStore, MIGRATIONS_TABLE};
+use crate::db::query::{
+    checks_table, delete_proxies, insert_checks, insert_proxies, select_proxies, sites_table, unique_proxies, BATCH_ROWS,
+};
+use crate::model::{Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult, APP_CONFIG};
 use anyhow::Result;
+use chrono::NaiveDateTime;
 use async_trait::async_trait;
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
 use tracing::info;
 use crate::common::utils::validate_table_name;
 
+/// 批量写入代理时追加的冲突处理子句：代理已存在时更新协议与质量信息。
+const UPSERT_CLAUSE: &str = " ON CONFLICT(ip, port) DO UPDATE SET protocol=excluded.protocol, speed=excluded.speed, success_rate=excluded.success_rate, stability=excluded.stability, anonymity=excluded.anonymity, tampered=excluded.tampered, score=excluded.score, last_checked=excluded.last_checked";
+
 #[derive(Debug)]
 pub struct SqliteStorage {
     pool: Pool<Sqlite>,
+    /// 代理表名。
+    table: String,
+    /// 检测历史表名，由代理表名派生。
+    checks_table: String,
+    /// 分站点验证结果表名，由代理表名派生。
+    sites_table: String,
 }
 
 impl SqliteStorage {
     pub async fn new() -> Result<Self> {
+        let storage =
+            Self::connect(&APP_CONFIG.db.connection_string, &APP_CONFIG.db.table_name, APP_CONFIG.db.max_connections)
+                .await?;
+        storage.migrate(false).await?;
+        Ok(storage)
+    }
+
+    /// 使用指定的连接地址与代理表名创建存储实例，不依赖配置文件，也不执行迁移。
+    pub async fn connect(url: &str, table: &str, max_connections: u32) -> Result<Self> {
+        if !validate_table_name(table) {
+            anyhow::bail!("❌ 表名不合法：{}，请使用字母数字下划线，且不能以数字开头", table);
+        }
 
         let pool = SqlitePoolOptions::new()
-            .max_connections(APP_CONFIG.db.max_connections)
-            .connect(&APP_CONFIG.db.connection_string)
+            .max_connections(max_connections)
+            .connect(url)
             .await?;
 
-        let storage = Self { pool };
-        storage.create_table().await?;
+        let storage = Self {
+            pool,
+            table: table.to_string(),
+            checks_table: checks_table(table),
+            sites_table: sites_table(table),
+        };
         info!("✅ SQLite 数据库连接成功");
         Ok(storage)
     }
 
-    async fn create_table(&self) -> Result<()> {
-        if !validate_table_name(&APP_CONFIG.db.table_name) {
-            panic!("❌ 配置中的表名不合法：{}，请使用字母数字下划线，且不能以数字开头", APP_CONFIG.db.table_name);
-        }
+    /// 执行尚未执行的表结构迁移，`dry_run` 为 `true` 时只返回待执行的迁移而不修改数据库。
+    pub async fn migrate(&self, dry_run: bool) -> Result<Vec<PlannedMigration>> {
+        migration::migrate(self, dry_run).await
+    }
+}
 
-        let table = &APP_CONFIG.db.table_name;
-        sqlx::query(&format!(
-            r#"
-            CREATE TABLE IF NOT EXISTS {} (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                ip TEXT NOT NULL,
-                port TEXT NOT NULL,
-                speed REAL DEFAULT 0.0,
-                success_rate REAL DEFAULT 0.0,
-                stability REAL DEFAULT 0.0,
-                score REAL DEFAULT 0.0,
-                last_checked DATETIME DEFAULT CURRENT_TIMESTAMP,
-                UNIQUE(ip, port)
-            );
-            "#,
-            table
-        ))
-            .execute(&self.pool)
+#[async_trait]
+impl SchemaStore for SqliteStorage {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    fn proxy_table(&self) -> &str {
+        &self.table
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    async fn column_exists(&self, table: &str, column: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&self.pool)
             .await?;
+        Ok(count > 0)
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<i64>> {
+        let sql = format!("SELECT version FROM {} WHERE proxy_table = ? ORDER BY version", MIGRATIONS_TABLE);
+        Ok(sqlx::query_scalar(&sql).bind(&self.table).fetch_all(&self.pool).await?)
+    }
+
+    async fn apply(&self, migration: &PlannedMigration) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&migrations_table_sql(Dialect::Sqlite)).execute(&mut *tx).await?;
+        for statement in &migration.statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        sqlx::query(&format!("INSERT INTO {} (proxy_table, version, description, applied_at) VALUES (?, ?, ?, ?)", MIGRATIONS_TABLE))
+            .bind(&self.table)
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(chrono::Utc::now().naive_utc())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
         Ok(())
     }
 }
@@ -74,11 +132,12 @@ impl ProxyStorage for SqliteStorage {
 
     async fn insert_basic_proxy(&self, proxy: &ProxyBasic) -> Result<()> {
         sqlx::query(&format!(
-            "INSERT OR IGNORE INTO {} (ip, port) VALUES (?, ?)",
-            APP_CONFIG.db.table_name
+            "INSERT OR IGNORE INTO {} (ip, port, protocol) VALUES (?, ?, ?)",
+            self.table
         ))
             .bind(&proxy.ip)
             .bind(&proxy.port)
+            .bind(proxy.protocol.as_str())
             .execute(&self.pool)
             .await?;
         info!("插入基础代理：{}:{}", proxy.ip, proxy.port);
@@ -86,35 +145,13 @@ impl ProxyStorage for SqliteStorage {
     }
 
     async fn upsert_quality_proxy(&self, proxy: &Proxy) -> Result<()> {
-        sqlx::query(&format!(
-            r#"
-            INSERT INTO {} (ip, port, speed, success_rate, stability, score, last_checked)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(ip, port) DO UPDATE SET
-                speed=excluded.speed,
-                success_rate=excluded.success_rate,
-                stability=excluded.stability,
-                score=excluded.score,
-                last_checked=excluded.last_checked
-            "#,
-            APP_CONFIG.db.table_name
-        ))
-            .bind(&proxy.ip)
-            .bind(&proxy.port)
-            .bind(proxy.speed)
-            .bind(proxy.success_rate)
-            .bind(proxy.stability)
-            .bind(proxy.score)
-            .bind(&proxy.last_checked)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+        self.upsert_many(std::slice::from_ref(proxy)).await
     }
 
     async fn find_proxy_by_ip_port(&self, ip: &str, port: &str) -> Result<Option<Proxy>> {
         let proxy = sqlx::query_as::<_, Proxy>(&format!(
             "SELECT * FROM {} WHERE ip = ? AND port = ?",
-            APP_CONFIG.db.table_name
+            self.table
         ))
             .bind(ip)
             .bind(port)
@@ -126,27 +163,143 @@ impl ProxyStorage for SqliteStorage {
     async fn list_all_proxies(&self) -> Result<Vec<Proxy>> {
         let proxies = sqlx::query_as::<_, Proxy>(&format!(
             "SELECT * FROM {} ORDER BY score DESC",
-            APP_CONFIG.db.table_name
+            self.table
         ))
             .fetch_all(&self.pool)
             .await?;
         Ok(proxies)
     }
 
+    async fn query_proxies(&self, query: &ProxyQuery) -> Result<Vec<Proxy>> {
+        let mut builder = select_proxies::<Sqlite>(&self.table, query, "RANDOM()");
+        let proxies = builder.build_query_as::<Proxy>().fetch_all(&self.pool).await?;
+        Ok(proxies)
+    }
+
     async fn random_proxy(&self) -> Result<ProxyBasic> {
-        let proxy= sqlx::query_as::<_, ProxyBasic>("SELECT * FROM proxies ORDER BY RANDOM() LIMIT 1")
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(proxy)
+        let sql = format!("SELECT ip, port, protocol FROM {} ORDER BY RANDOM() LIMIT 1", self.table);
+        sqlx::query_as::<_, ProxyBasic>(&sql)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("代理池为空"))
+    }
+
+    async fn remove_proxy(&self, ip: &str, port: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        // 站点结果随代理一并删除，避免重新收录后沿用旧的验证结果；检测记录保留，由保留期清理
+        let sql = format!("DELETE FROM {} WHERE ip = ? AND port = ?", self.sites_table);
+        sqlx::query(&sql).bind(ip).bind(port).execute(&mut *tx).await?;
+        let sql = format!("DELETE FROM {} WHERE ip = ? AND port = ?", self.table);
+        let result = sqlx::query(&sql).bind(ip).bind(port).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn remove_proxy(&self, ip: &str) -> Result<bool> {
-        sqlx::query("DELETE FROM proxies WHERE ip = ?")
+    async fn upsert_many(&self, proxies: &[Proxy]) -> Result<()> {
+        let proxies = unique_proxies(proxies);
+        let mut tx = self.pool.begin().await?;
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            let mut builder = insert_proxies::<Sqlite>(&self.table, chunk);
+            builder.push(UPSERT_CLAUSE);
+            builder.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_many(&self, proxies: &[ProxyBasic]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = 0;
+        for chunk in proxies.chunks(BATCH_ROWS) {
+            delete_proxies::<Sqlite>(&self.sites_table, chunk).build().execute(&mut *tx).await?;
+            removed += delete_proxies::<Sqlite>(&self.table, chunk).build().execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn count(&self) -> Result<u64> {
+        let sql = format!("SELECT COUNT(*) FROM {}", self.table);
+        let count: i64 = sqlx::query_scalar(&sql).fetch_one(&self.pool).await?;
+        Ok(count as u64)
+    }
+
+    async fn insert_checks(&self, checks: &[ProxyCheck]) -> Result<()> {
+        if checks.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        for chunk in checks.chunks(BATCH_ROWS) {
+            insert_checks::<Sqlite>(&self.checks_table, chunk).build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_checks(&self, ip: &str, port: &str, limit: u32) -> Result<Vec<ProxyCheck>> {
+        let sql = format!(
+            "SELECT * FROM {} WHERE ip = ? AND port = ? ORDER BY checked_at DESC, id DESC LIMIT ?",
+            self.checks_table
+        );
+        let checks = sqlx::query_as::<_, ProxyCheck>(&sql)
             .bind(ip)
-            .execute(&self.pool)
+            .bind(port)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
             .await?;
+        Ok(checks)
+    }
 
-        Ok(true)
+    async fn prune_checks(&self, before: NaiveDateTime) -> Result<u64> {
+        let sql = format!("DELETE FROM {} WHERE checked_at < ?", self.checks_table);
+        let result = sqlx::query(&sql).bind(before).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn list_all_checks(&self) -> Result<Vec<ProxyCheck>> {
+        let sql = format!("SELECT * FROM {} ORDER BY checked_at, id", self.checks_table);
+        let checks = sqlx::query_as::<_, ProxyCheck>(&sql).fetch_all(&self.pool).await?;
+        Ok(checks)
+    }
+
+    async fn upsert_site_results(&self, results: &[ProxySiteResult]) -> Result<()> {
+        let sql = format!(
+            r#"
+            INSERT INTO {} (ip, port, site, speed, success_rate, score, last_checked)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(ip, port, site) DO UPDATE SET
+                speed=excluded.speed,
+                success_rate=excluded.success_rate,
+                score=excluded.score,
+                last_checked=excluded.last_checked
+            "#,
+            self.sites_table
+        );
+        let mut tx = self.pool.begin().await?;
+        for result in results {
+            sqlx::query(&sql)
+                .bind(&result.ip)
+                .bind(&result.port)
+                .bind(&result.site)
+                .bind(result.speed)
+                .bind(result.success_rate)
+                .bind(result.score)
+                .bind(result.last_checked)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_site_results(&self, ip: &str, port: &str) -> Result<Vec<ProxySiteResult>> {
+        let sql = format!("SELECT * FROM {} WHERE ip = ? AND port = ? ORDER BY site", self.sites_table);
+        let results = sqlx::query_as::<_, ProxySiteResult>(&sql)
+            .bind(ip)
+            .bind(port)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(results)
     }
 }
 
@@ -155,19 +308,29 @@ impl ProxyStorage for SqliteStorage {
 mod tests {
     use super::*;
     use crate::db::manager::ProxyStorage;
-    use crate::model::{Proxy, ProxyBasic};
+    use crate::model::{Anonymity, CheckErrorKind, CheckSource, Protocol, Proxy, ProxyBasic};
     use chrono::Utc;
 
+    /// 已执行迁移的内存数据库，测试之间互不影响，也不依赖配置文件。
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::connect("sqlite::memory:", "proxies", 1).await.unwrap();
+        storage.migrate(false).await.unwrap();
+        storage
+    }
+
     #[tokio::test]
-    async fn test_create_table() {
-        let storage = SqliteStorage::new().await.unwrap();
-        let result = storage.create_table().await;
+    async fn test_migrate() {
+        let storage = SqliteStorage::connect("sqlite::memory:", "proxies", 1).await.unwrap();
+        let result = storage.migrate(false).await;
         assert!(result.is_ok());
+        // 检测历史与站点结果表按代理表名派生
+        assert!(storage.table_exists("proxies_checks").await.unwrap());
+        assert!(storage.table_exists("proxies_sites").await.unwrap());
     }
 
     #[tokio::test]
     async fn test_insert_basic_proxy() {
-        let storage = SqliteStorage::new().await.unwrap();
+        let storage = storage().await;
         let proxy = ProxyBasic::new("127.0.0.1", "1000");
         let result = storage.insert_basic_proxy(&proxy).await;
         assert!(result.is_ok());
@@ -175,13 +338,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_upsert_quality_proxy() {
-        let storage = SqliteStorage::new().await.unwrap();
+        let storage = storage().await;
         let proxy = Proxy {
             ip: "127.0.0.1".into(),
             port: "1001".into(),
+            protocol: Protocol::Http,
             speed: Some(100.5),
             success_rate: Some(0.9),
             stability: Some(0.95),
+            anonymity: Some(Anonymity::Elite),
+            tampered: false,
             score: Some(85.0),
             last_checked: Some(Utc::now().naive_utc()),
         };
@@ -192,7 +358,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_find_proxy_by_ip_port() {
-        let storage = SqliteStorage::new().await.unwrap();
+        let storage = storage().await;
         let proxy = ProxyBasic::new("127.0.0.1", "1002");
 
         storage.insert_basic_proxy(&proxy).await.unwrap();
@@ -203,11 +369,160 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::len_zero)]
     async fn test_list_all_proxies() {
-        let storage = SqliteStorage::new().await.unwrap();
+        let storage = storage().await;
+        storage.insert_basic_proxy(&ProxyBasic::new("127.0.0.1", "1000")).await.unwrap();
         let result = storage.list_all_proxies().await;
         assert!(result.is_ok());
         let proxies = result.unwrap();
         assert!(proxies.len() > 0);
     }
+
+    #[tokio::test]
+    async fn test_query_proxies() {
+        let storage = storage().await;
+        for (port, score, protocol) in [("1101", 0.9, Protocol::Socks5), ("1102", 0.2, Protocol::Socks5), ("1103", 0.8, Protocol::Http)] {
+            let proxy = Proxy {
+                ip: "127.0.0.2".into(),
+                port: port.into(),
+                protocol,
+                speed: Some(0.5),
+                success_rate: Some(1.0),
+                stability: Some(1.0),
+                anonymity: None,
+                tampered: false,
+                score: Some(score),
+                last_checked: Some(Utc::now().naive_utc()),
+            };
+            storage.upsert_quality_proxy(&proxy).await.unwrap();
+        }
+
+        let query = ProxyQuery {
+            min_score: Some(0.5),
+            protocol: Some(Protocol::Socks5),
+            checked_within: Some(60),
+            ..Default::default()
+        };
+        let proxies = storage.query_proxies(&query).await.unwrap();
+        assert!(proxies.iter().any(|p| p.port == "1101"));
+        assert!(proxies.iter().all(|p| p.score.unwrap() >= 0.5 && p.protocol == Protocol::Socks5));
+
+        let query = ProxyQuery { limit: Some(1), sort: "score".parse().unwrap(), ..Default::default() };
+        let proxies = storage.query_proxies(&query).await.unwrap();
+        assert_eq!(proxies.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_checks_history() {
+        let storage = storage().await;
+        let now = Utc::now().naive_utc();
+        let check = |minutes: i64, success: bool| ProxyCheck {
+            ip: "127.0.0.3".into(),
+            port: "1201".into(),
+            checked_at: now - chrono::Duration::minutes(minutes),
+            source: CheckSource::Verify,
+            target: "https://example.com".into(),
+            success,
+            latency: success.then_some(0.3),
+            status_code: success.then_some(200),
+            error_kind: (!success).then_some(CheckErrorKind::Timeout),
+        };
+        storage.insert_checks(&[check(120, true), check(1, false)]).await.unwrap();
+
+        let checks = storage.list_checks("127.0.0.3", "1201", 10).await.unwrap();
+        assert!(checks.len() >= 2);
+        assert_eq!(checks[0].error_kind, Some(CheckErrorKind::Timeout));
+        assert!(checks.windows(2).all(|w| w[0].checked_at >= w[1].checked_at));
+
+        storage.prune_checks(now - chrono::Duration::minutes(60)).await.unwrap();
+        let checks = storage.list_checks("127.0.0.3", "1201", 10).await.unwrap();
+        assert!(checks.iter().all(|c| c.checked_at >= now - chrono::Duration::minutes(60)));
+    }
+
+    #[tokio::test]
+    async fn test_site_results() {
+        let storage = storage().await;
+        storage.insert_basic_proxy(&ProxyBasic::new("127.0.0.4", "1301")).await.unwrap();
+        let site = |name: &str, success_rate: f64| ProxySiteResult {
+            ip: "127.0.0.4".into(),
+            port: "1301".into(),
+            site: name.into(),
+            speed: (success_rate > 0.0).then_some(0.4),
+            success_rate,
+            score: success_rate,
+            last_checked: Utc::now().naive_utc(),
+        };
+        storage.upsert_site_results(&[site("site_a", 0.0), site("site_b", 1.0)]).await.unwrap();
+        storage.upsert_site_results(&[site("site_a", 0.5)]).await.unwrap();
+
+        let sites = storage.list_site_results("127.0.0.4", "1301").await.unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].success_rate, 0.5);
+
+        let query = ProxyQuery { site: Some("site_b".into()), ..Default::default() };
+        let proxies = storage.query_proxies(&query).await.unwrap();
+        assert!(proxies.iter().any(|p| p.ip == "127.0.0.4"));
+
+        let query = ProxyQuery { site: Some("site_c".into()), ..Default::default() };
+        assert!(storage.query_proxies(&query).await.unwrap().iter().all(|p| p.ip != "127.0.0.4"));
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        crate::db::conformance::run(&storage().await).await;
+    }
+
+    /// 迁移机制引入前发布的 `proxy.db` 表结构。
+    const LEGACY_SCHEMA: &str = r#"
+        CREATE TABLE proxies (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ip TEXT NOT NULL,
+            port TEXT NOT NULL,
+            speed REAL DEFAULT 0.0,
+            success_rate REAL DEFAULT 0.0,
+            stability REAL DEFAULT 0.0,
+            score REAL DEFAULT 0.0,
+            last_checked DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(ip, port)
+        )
+    "#;
+
+    #[tokio::test]
+    async fn test_migrate_legacy_schema() {
+        let storage = SqliteStorage::connect("sqlite::memory:", "proxies", 1).await.unwrap();
+        sqlx::query(LEGACY_SCHEMA).execute(&storage.pool).await.unwrap();
+        sqlx::query("INSERT INTO proxies (ip, port, score) VALUES ('1.1.1.1', '80', 0.5)")
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+
+        let plan = storage.migrate(true).await.unwrap();
+        assert_eq!(plan.len(), migration::MIGRATIONS.len());
+        assert!(plan[1].statements[0].starts_with("ALTER TABLE proxies ADD COLUMN protocol"));
+        assert!(!storage.table_exists(MIGRATIONS_TABLE).await.unwrap());
+
+        storage.migrate(false).await.unwrap();
+        let proxy = storage.find_proxy_by_ip_port("1.1.1.1", "80").await.unwrap().unwrap();
+        assert_eq!(proxy.protocol, Protocol::Http);
+        assert_eq!(proxy.score, Some(0.5));
+        assert!(!proxy.tampered);
+        assert_eq!(storage.applied_versions().await.unwrap().len(), migration::MIGRATIONS.len());
+        assert!(storage.migrate(true).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_skips_existing_columns() {
+        let storage = SqliteStorage::connect("sqlite::memory:", "proxies", 1).await.unwrap();
+        sqlx::query("CREATE TABLE proxies (id INTEGER PRIMARY KEY, ip TEXT, port TEXT, protocol TEXT, anonymity TEXT)")
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+
+        let plan = storage.migrate(true).await.unwrap();
+        assert_eq!(plan[1].skipped, vec!["proxies.protocol"]);
+        assert!(plan[1].statements.is_empty());
+        assert_eq!(plan[2].skipped, vec!["proxies.anonymity"]);
+        assert_eq!(plan[5].statements.len(), 1);
+    }
 }
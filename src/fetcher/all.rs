@@ -1,25 +1,234 @@
-use crate::fetcher::{bfbke, kuai, lumiproxy};
-use crate::model::ProxyBasic;
-use anyhow::Result;
+use crate::fetcher::bfbke::BfbkeSource;
+use crate::fetcher::generic::GenericSource;
+use crate::fetcher::kuai::KuaiSource;
+use crate::fetcher::lumiproxy::LumiProxySource;
+use crate::fetcher::source::{ProxySource, SourceReport};
+use crate::model::{ProxyBasic, SourceConfig, APP_CONFIG};
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug, info, warn};
 
-/// 汇总所有代理来源的抓取结果，统一返回为 `ProxyBasic` 列表。
+/// 全局代理源注册表，包含内置代理源与配置文件声明的通用代理源。
+static SOURCE_REGISTRY: OnceCell<SourceRegistry> = OnceCell::new();
+
+/// 根据配置初始化全局代理源注册表，程序启动时调用一次即可。
 ///
-/// 该函数依次调用各个代理源模块的 `fetch()` 函数（如 `bfbke` 和 `kuai`），
-/// 并将它们返回的代理合并为一个统一列表。
+/// 配置中任一 `[[sources]]` 条目不合法都会返回错误，避免带着错误配置启动。
+pub fn init() -> Result<()> {
+    let registry = SourceRegistry::from_config(&APP_CONFIG.sources)?;
+    info!("✅ 代理源初始化完成：{}", registry.names().join(", "));
+    SOURCE_REGISTRY.set(registry).map_err(|_| anyhow::anyhow!("代理源注册表已初始化"))?;
+    Ok(())
+}
+
+/// 获取全局代理源注册表。
+pub fn registry() -> &'static SourceRegistry {
+    SOURCE_REGISTRY.get().expect("Source registry not initialized")
+}
+
+/// 一次完整抓取的汇总结果。
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchReport {
+    /// 所有代理源合并后的代理列表（未去重）。
+    pub proxies: Vec<ProxyBasic>,
+    /// 每个代理源的抓取报告，顺序与注册顺序一致。
+    pub sources: Vec<SourceReport>,
+}
+
+/// 代理源注册表。
+///
+/// 负责保存所有已注册的 [`ProxySource`]，并发执行抓取，
+/// 单个代理源失败只会记录在其报告中，不会影响其他代理源。
+#[derive(Default)]
+pub struct SourceRegistry {
+    sources: Vec<Arc<dyn ProxySource>>,
+}
+
+impl SourceRegistry {
+    /// 创建一个空的注册表。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建包含所有内置代理源的注册表。
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(BfbkeSource);
+        registry.register(KuaiSource);
+        registry.register(LumiProxySource);
+        registry
+    }
+
+    /// 创建包含内置代理源与配置声明代理源的注册表。
+    ///
+    /// # 错误
+    /// 任一配置条目无法构建为 [`GenericSource`] 时返回错误。
+    pub fn from_config(sources: &[SourceConfig]) -> Result<Self> {
+        let mut registry = Self::with_builtin();
+        for config in sources {
+            let name = config.name.clone();
+            let source = GenericSource::new(config.clone())
+                .with_context(|| format!("代理源 {} 配置不合法", name))?;
+            registry.register(source);
+        }
+        Ok(registry)
+    }
+
+    /// 注册一个代理源。
+    pub fn register<S: ProxySource + 'static>(&mut self, source: S) {
+        self.sources.push(Arc::new(source));
+    }
+
+    /// 已注册代理源的名称列表。
+    pub fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|s| s.name()).collect()
+    }
+
+    /// 并发执行所有已启用代理源的抓取，并汇总结果。
+    ///
+    /// 每个代理源独立计时，失败的代理源只会在报告中记录错误信息，
+    /// 其余代理源的结果照常合并返回。
+    pub async fn fetch_all(&self) -> FetchReport {
+        let tasks = self
+            .sources
+            .iter()
+            .filter(|source| {
+                if !source.enabled() {
+                    debug!("代理源 {} 未启用，跳过", source.name());
+                }
+                source.enabled()
+            })
+            .map(|source| async move {
+                let start = Instant::now();
+                let result = source.fetch().await;
+                (source.name().to_string(), result, start.elapsed().as_millis() as u64)
+            });
+
+        let mut proxies = Vec::new();
+        let mut sources = Vec::new();
+
+        for (name, result, duration_ms) in join_all(tasks).await {
+            match result {
+                Ok(list) => {
+                    info!("🟢 代理源 {} 抓取完成：{} 条，耗时 {}ms", name, list.len(), duration_ms);
+                    sources.push(SourceReport { name, count: list.len(), error: None, duration_ms });
+                    proxies.extend(list);
+                }
+                Err(e) => {
+                    warn!("🔴 代理源 {} 抓取失败，耗时 {}ms，错误：{}", name, duration_ms, e);
+                    sources.push(SourceReport { name, count: 0, error: Some(e.to_string()), duration_ms });
+                }
+            }
+        }
+
+        FetchReport { proxies, sources }
+    }
+}
+
+/// 汇总所有代理来源的抓取结果，统一返回为 [`FetchReport`]。
+///
+/// 该函数通过全局代理源注册表并发调用各个代理源，
+/// 合并它们返回的代理，并附带每个代理源的抓取报告。
 ///
 /// # 返回
-/// 返回所有代理源合并后的 `Vec<ProxyBasic>`，其中每个元素表示一个原始代理条目。
+/// 返回 [`FetchReport`]，其中 `proxies` 为所有代理源合并后的原始代理条目，
+/// `sources` 为每个代理源的数量、错误与耗时。
 ///
 /// # 错误
-/// 如果任何一个源的抓取函数返回错误（如网络失败、格式异常），
-/// 此函数也将立即返回相应的错误。
+/// 单个代理源的失败（如网络失败、格式异常）只会记录在对应报告中，不会中断整体抓取。
 ///
 /// # 用例
 /// 可作为统一的代理抓取入口，用于后续批量验证与质量评估流程。
-pub async fn fetch_all_sources() -> Result<Vec<ProxyBasic>> {
-    let mut list = Vec::new();
-    list.extend(bfbke::fetch().await?);
-    list.extend(kuai::fetch().await?);
-    list.extend(lumiproxy::fetch().await?);
-    Ok(list)
+pub async fn fetch_all_sources() -> FetchReport {
+    registry().fetch_all().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+
+    struct StaticSource {
+        name: &'static str,
+        enabled: bool,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl ProxySource for StaticSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn enabled(&self) -> bool {
+            self.enabled
+        }
+
+        async fn fetch(&self) -> Result<Vec<ProxyBasic>> {
+            if self.fail {
+                return Err(anyhow!("模拟失败"));
+            }
+            Ok(vec![ProxyBasic::new("127.0.0.1", "8080"), ProxyBasic::new("127.0.0.1", "8081")])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_isolates_failures() {
+        let mut registry = SourceRegistry::new();
+        registry.register(StaticSource { name: "ok", enabled: true, fail: false });
+        registry.register(StaticSource { name: "broken", enabled: true, fail: true });
+        registry.register(StaticSource { name: "off", enabled: false, fail: false });
+
+        let report = registry.fetch_all().await;
+        assert_eq!(report.proxies.len(), 2);
+        assert_eq!(report.sources.len(), 2);
+        assert!(report.sources[0].is_ok());
+        assert_eq!(report.sources[0].count, 2);
+        assert!(!report.sources[1].is_ok());
+        assert_eq!(report.sources[1].count, 0);
+    }
+
+    #[test]
+    fn test_builtin_sources() {
+        let registry = SourceRegistry::with_builtin();
+        assert_eq!(registry.names(), vec!["bfbke", "kuai", "lumiproxy"]);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let toml = r#"
+            [[sources]]
+            name = "plain"
+            url = "https://example.com/proxy.txt"
+            [sources.parser]
+            kind = "text"
+
+            [[sources]]
+            name = "api"
+            url = "https://example.com/api?page={page}"
+            pages = [1, 2]
+            enabled = false
+            [sources.parser]
+            kind = "json"
+            list = "/data"
+        "#;
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            sources: Vec<SourceConfig>,
+        }
+        let wrapper: Wrapper = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        let registry = SourceRegistry::from_config(&wrapper.sources).unwrap();
+        assert_eq!(registry.names(), vec!["bfbke", "kuai", "lumiproxy", "plain", "api"]);
+    }
 }
@@ -1,7 +1,23 @@
+use crate::fetcher::source::ProxySource;
 use crate::model::ProxyBasic;
 use anyhow::Result;
+use async_trait::async_trait;
 use tracing::info;
 
+/// BFBKE 代理源。
+pub struct BfbkeSource;
+
+#[async_trait]
+impl ProxySource for BfbkeSource {
+    fn name(&self) -> &str {
+        "bfbke"
+    }
+
+    async fn fetch(&self) -> Result<Vec<ProxyBasic>> {
+        fetch().await
+    }
+}
+
 /// 从 BFBKE 网站抓取代理列表，并解析为 `ProxyBasic` 向量。
 ///
 /// 该函数会向 `https://www.bfbke.com/proxy.txt` 发起 GET 请求，
@@ -41,6 +57,7 @@ pub async fn fetch() -> Result<Vec<ProxyBasic>> {
 mod tests {
 
     #[tokio::test]
+    #[allow(clippy::len_zero)]
     async fn test_fetch() {
         let list = super::fetch().await.unwrap();
         assert!(list.len() > 0);
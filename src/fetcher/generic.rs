@@ -0,0 +1,254 @@
+//! 通用代理源模块
+//!
+//! 根据配置文件中的 `[[sources]]` 条目构建代理源，无需为每个网站单独编写模块。
+//! 支持三种常见的数据形态：
+//!
+//! - `text`：纯文本列表，每行一个 `ip:port`，可带 `socks5://` 等协议前缀；
+//! - `json`：JSON 数据，通过 JSON Pointer 定位代理数组与字段；
+//! - `regex`：任意文本，通过带 `ip`、`port` 命名捕获组的正则提取。
+//!
+//! 条目本身未携带协议信息时，使用配置中的 `protocol`（默认 `http`）。
+
+use crate::fetcher::source::ProxySource;
+use crate::model::{Protocol, ProxyBasic, SourceConfig, SourceParserConfig};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+use tracing::{debug, info};
+
+/// 编译后的解析器，正则只在构建代理源时编译一次。
+#[derive(Debug)]
+enum Parser {
+    Text { separator: String },
+    Json { list: String, ip: String, port: String, protocol: Option<String> },
+    Regex(Regex),
+}
+
+/// 由配置声明的通用代理源。
+#[derive(Debug)]
+pub struct GenericSource {
+    config: SourceConfig,
+    parser: Parser,
+}
+
+impl GenericSource {
+    /// 根据配置构建代理源，并校验解析规则是否合法。
+    ///
+    /// # 错误
+    /// - 页码范围起始页大于结束页；
+    /// - 正则表达式无法编译，或缺少 `ip`、`port` 命名捕获组。
+    pub fn new(config: SourceConfig) -> Result<Self> {
+        if let Some([start, end]) = config.pages
+            && start > end
+        {
+            bail!("代理源 {} 的页码范围不合法：[{}, {}]", config.name, start, end);
+        }
+
+        let parser = match &config.parser {
+            SourceParserConfig::Text { separator } => Parser::Text { separator: separator.clone() },
+            SourceParserConfig::Json { list, ip, port, protocol } => Parser::Json {
+                list: list.clone(),
+                ip: ip.clone(),
+                port: port.clone(),
+                protocol: protocol.clone(),
+            },
+            SourceParserConfig::Regex { pattern } => {
+                let re = Regex::new(pattern)?;
+                let names: Vec<&str> = re.capture_names().flatten().collect();
+                if !names.contains(&"ip") || !names.contains(&"port") {
+                    bail!("代理源 {} 的正则缺少 ip 或 port 命名捕获组", config.name);
+                }
+                Parser::Regex(re)
+            }
+        };
+
+        Ok(Self { config, parser })
+    }
+
+    /// 展开页码范围，生成需要请求的全部地址。
+    fn urls(&self) -> Vec<String> {
+        match self.config.pages {
+            Some([start, end]) => (start..=end)
+                .map(|page| self.config.url.replace("{page}", &page.to_string()))
+                .collect(),
+            None => vec![self.config.url.clone()],
+        }
+    }
+
+    /// 解析条目自带的协议字符串，缺失或无法识别时使用配置的默认协议。
+    fn protocol_of(&self, raw: Option<&str>) -> Protocol {
+        raw.and_then(|s| s.parse().ok()).unwrap_or(self.config.protocol)
+    }
+
+    /// 按配置的解析方式，从单页响应内容中解析出代理列表。
+    fn parse(&self, body: &str) -> Result<Vec<ProxyBasic>> {
+        let mut list = Vec::new();
+
+        match &self.parser {
+            Parser::Text { separator } => {
+                for line in body.lines() {
+                    let (scheme, addr) = match line.trim().split_once("://") {
+                        Some((scheme, addr)) => (Some(scheme), addr),
+                        None => (None, line.trim()),
+                    };
+                    let mut parts = addr.splitn(2, separator.as_str());
+                    if let (Some(ip), Some(port)) = (parts.next(), parts.next())
+                        && !ip.is_empty()
+                        && !port.is_empty()
+                    {
+                        let protocol = self.protocol_of(scheme);
+                        list.push(ProxyBasic::new(ip.trim(), port.trim()).with_protocol(protocol));
+                    }
+                }
+            }
+            Parser::Json { list: pointer, ip, port, protocol } => {
+                let data: Value = serde_json::from_str(body)?;
+                let items = data
+                    .pointer(pointer)
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| anyhow!("JSON 中未找到代理数组：{}", pointer))?;
+
+                for item in items {
+                    match (item.pointer(ip).and_then(json_to_string), item.pointer(port).and_then(json_to_string)) {
+                        (Some(ip), Some(port)) => {
+                            let raw = protocol.as_deref().and_then(|p| item.pointer(p)).and_then(json_to_string);
+                            let protocol = self.protocol_of(raw.as_deref());
+                            list.push(ProxyBasic::new(&ip, &port).with_protocol(protocol));
+                        }
+                        _ => debug!("代理源 {} 跳过无法解析的条目：{}", self.config.name, item),
+                    }
+                }
+            }
+            Parser::Regex(re) => {
+                for cap in re.captures_iter(body) {
+                    if let (Some(ip), Some(port)) = (cap.name("ip"), cap.name("port")) {
+                        let protocol = self.protocol_of(cap.name("protocol").map(|m| m.as_str()));
+                        list.push(ProxyBasic::new(ip.as_str(), port.as_str()).with_protocol(protocol));
+                    }
+                }
+            }
+        }
+
+        Ok(list)
+    }
+}
+
+/// 将 JSON 中的字符串或数字统一转换为字符串。
+fn json_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl ProxySource for GenericSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    async fn fetch(&self) -> Result<Vec<ProxyBasic>> {
+        info!("========== [{}] ==========", self.config.name);
+        let mut list = Vec::new();
+
+        for url in self.urls() {
+            info!("正在请求 {}", url);
+            let body = reqwest::get(&url).await?.text().await?;
+            list.extend(self.parse(&body)?);
+        }
+
+        info!("{} 抓取了 {} 条代理", self.config.name, list.len());
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(pages: Option<[u32; 2]>, parser: SourceParserConfig) -> GenericSource {
+        GenericSource::new(SourceConfig {
+            name: "test".into(),
+            url: "https://example.com/list/{page}".into(),
+            pages,
+            enabled: true,
+            protocol: Protocol::Http,
+            parser,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_urls() {
+        let src = source(Some([1, 3]), SourceParserConfig::Text { separator: ":".into() });
+        assert_eq!(
+            src.urls(),
+            vec!["https://example.com/list/1", "https://example.com/list/2", "https://example.com/list/3"]
+        );
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let src = source(None, SourceParserConfig::Text { separator: ":".into() });
+        let list = src.parse("1.1.1.1:8080\n\nsocks5://2.2.2.2:3128\r\ninvalid\n").unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].protocol, Protocol::Http);
+        assert_eq!(list[1].ip, "2.2.2.2");
+        assert_eq!(list[1].port, "3128");
+        assert_eq!(list[1].protocol, Protocol::Socks5);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let src = source(
+            None,
+            SourceParserConfig::Json {
+                list: "/data/list".into(),
+                ip: "/ip".into(),
+                port: "/port".into(),
+                protocol: Some("/type".into()),
+            },
+        );
+        let body = r#"{"data":{"list":[{"ip":"1.1.1.1","port":8080,"type":"SOCKS4"},{"ip":"2.2.2.2","port":"3128"},{"ip":"3.3.3.3"}]}}"#;
+        let list = src.parse(body).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].port, "8080");
+        assert_eq!(list[0].protocol, Protocol::Socks4);
+        assert_eq!(list[1].port, "3128");
+        assert_eq!(list[1].protocol, Protocol::Http);
+    }
+
+    #[test]
+    fn test_parse_regex() {
+        let src = source(
+            None,
+            SourceParserConfig::Regex { pattern: r"<td>(?P<ip>[\d.]+)</td><td>(?P<port>\d+)</td>".into() },
+        );
+        let body = "<tr><td>1.1.1.1</td><td>80</td></tr><tr><td>2.2.2.2</td><td>8080</td></tr>";
+        let list = src.parse(body).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].ip, "1.1.1.1");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = SourceConfig {
+            name: "bad".into(),
+            url: "https://example.com".into(),
+            pages: None,
+            enabled: true,
+            protocol: Protocol::Http,
+            parser: SourceParserConfig::Regex { pattern: r"(\d+):(\d+)".into() },
+        };
+        assert!(GenericSource::new(config.clone()).is_err());
+
+        let config = SourceConfig { pages: Some([3, 1]), parser: SourceParserConfig::Text { separator: ":".into() }, ..config };
+        assert!(GenericSource::new(config).is_err());
+    }
+}
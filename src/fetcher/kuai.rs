@@ -1,8 +1,24 @@
+use crate::fetcher::source::ProxySource;
 use crate::model::ProxyBasic;
 use anyhow::Result;
+use async_trait::async_trait;
 use regex::Regex;
 use tracing::info;
 
+/// 「快代理」代理源。
+pub struct KuaiSource;
+
+#[async_trait]
+impl ProxySource for KuaiSource {
+    fn name(&self) -> &str {
+        "kuai"
+    }
+
+    async fn fetch(&self) -> Result<Vec<ProxyBasic>> {
+        fetch().await
+    }
+}
+
 /// 从「快代理」网站分页抓取代理列表并解析为 `ProxyBasic` 实例集合。
 ///
 /// 函数将遍历页码 `1..=3`，从每一页的 HTML 中提取嵌入的 `fpsList` JSON 数据，
@@ -53,6 +69,7 @@ pub async fn fetch() -> Result<Vec<ProxyBasic>> {
 mod tests {
 
     #[tokio::test]
+    #[allow(clippy::len_zero)]
     async fn test_fetch() {
         let list = super::fetch().await.unwrap();
         assert!(list.len() > 0);
@@ -1,8 +1,24 @@
 use anyhow::anyhow;
+use async_trait::async_trait;
 use serde_json::Value;
 use tracing::info;
+use crate::fetcher::source::ProxySource;
 use crate::model::ProxyBasic;
 
+/// LumiProxy 代理源。
+pub struct LumiProxySource;
+
+#[async_trait]
+impl ProxySource for LumiProxySource {
+    fn name(&self) -> &str {
+        "lumiproxy"
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<ProxyBasic>> {
+        fetch().await
+    }
+}
+
 pub async fn fetch() -> anyhow::Result<Vec<ProxyBasic>> {
     info!("========== [LumiProxy] ==========");
     let mut proxies = Vec::new();
@@ -30,6 +46,7 @@ pub async fn fetch() -> anyhow::Result<Vec<ProxyBasic>> {
 mod tests {
 
     #[tokio::test]
+    #[allow(clippy::len_zero)]
     async fn test_fetch() {
         let vec = super::fetch().await.unwrap();
         assert!(vec.len() > 0)
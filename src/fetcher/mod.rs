@@ -1,6 +1,8 @@
 mod all;
 mod bfbke;
+mod generic;
 mod kuai;
 mod lumiproxy;
+pub mod source;
 
-pub use all::fetch_all_sources;
\ No newline at end of file
+pub use all::{fetch_all_sources, init};
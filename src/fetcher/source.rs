@@ -0,0 +1,48 @@
+//! 代理源抽象模块
+//!
+//! 定义统一的 [`ProxySource`] trait，每个代理网站（或配置声明的通用源）
+//! 都以一个实现该 trait 的结构体接入抓取流程，由 [`SourceRegistry`](crate::fetcher::all::SourceRegistry)
+//! 统一调度，并生成对应的 [`SourceReport`] 抓取报告。
+
+use crate::model::ProxyBasic;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+
+/// 代理源的通用异步接口。
+///
+/// 实现者只需关心「如何从某个站点拿到代理列表」，
+/// 并发调度、失败隔离与统计均由注册表负责。
+#[async_trait]
+pub trait ProxySource: Send + Sync {
+    /// 代理源名称，用于日志输出与抓取报告。
+    fn name(&self) -> &str;
+
+    /// 是否启用该代理源，未启用的源不会参与抓取。默认启用。
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// 抓取并解析代理列表。
+    async fn fetch(&self) -> Result<Vec<ProxyBasic>>;
+}
+
+/// 单个代理源的抓取报告。
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceReport {
+    /// 代理源名称。
+    pub name: String,
+    /// 本次抓取到的代理数量，失败时为 0。
+    pub count: usize,
+    /// 抓取失败时的错误信息，成功时为 `None`。
+    pub error: Option<String>,
+    /// 抓取耗时（单位：毫秒）。
+    pub duration_ms: u64,
+}
+
+impl SourceReport {
+    /// 该代理源本次抓取是否成功。
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
@@ -0,0 +1,213 @@
+//! HTTP/HTTPS 正向代理网关。
+//!
+//! - `CONNECT host:port`：经支持隧道的上游代理（HTTPS、SOCKS）建立隧道后双向转发，适用于 HTTPS 等任意 TCP 流量；
+//! - 普通请求（`GET http://host/path` 等绝对地址形式）：HTTP 上游以绝对地址形式转发，
+//!   SOCKS 上游先建立到目标站点的隧道，再以相对路径形式转发。
+//!
+//! 转发的普通请求会去掉逐跳请求头并改为 `Connection: close`：每个客户端连接只转发一个请求，
+//! 后续请求由客户端重新建立连接，从而重新选择上游并同样去掉代理认证等请求头。
+
+use crate::gateway::pool::UpstreamPool;
+use crate::gateway::upstream;
+use crate::model::Protocol;
+use anyhow::{anyhow, bail, Result};
+use std::sync::Arc;
+use tokio::io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// 客户端请求头的最大长度。
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// 仅对上一跳有意义、转发前需要去掉的请求头，`Connection` 统一改写为 `close`。
+const HOP_HEADERS: [&str; 4] = ["proxy-connection", "proxy-authorization", "connection", "keep-alive"];
+
+/// 请求的目标。
+#[derive(Debug, PartialEq)]
+enum Target {
+    /// `CONNECT` 隧道请求。
+    Connect { host: String, port: u16 },
+    /// 绝对地址形式的普通请求，`path` 为相对路径形式（含查询串）。
+    Forward { host: String, port: u16, path: String },
+}
+
+/// 解析后的请求行。
+#[derive(Debug)]
+struct RequestLine {
+    method: String,
+    /// 原始的请求目标（绝对地址或 `host:port`）。
+    uri: String,
+    target: Target,
+    version: String,
+}
+
+/// 解析 `host[:port]`，未带端口时使用 `default_port`。
+fn split_host_port(authority: &str, default_port: u16) -> Result<(String, u16)> {
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse()?),
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        bail!("目标地址为空");
+    }
+    Ok((host.to_string(), port))
+}
+
+impl RequestLine {
+    fn parse(line: &str) -> Result<Self> {
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(uri), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("请求行格式不正确：{}", line);
+        };
+
+        let target = if method.eq_ignore_ascii_case("CONNECT") {
+            let (host, port) = split_host_port(uri, 443)?;
+            Target::Connect { host, port }
+        } else {
+            let rest = uri
+                .strip_prefix("http://")
+                .ok_or_else(|| anyhow!("仅支持 http:// 绝对地址或 CONNECT 请求：{}", uri))?;
+            let (authority, path) = match rest.find('/') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, "/"),
+            };
+            let (host, port) = split_host_port(authority, 80)?;
+            Target::Forward { host, port, path: path.to_string() }
+        };
+
+        Ok(Self { method: method.to_string(), uri: uri.to_string(), target, version: version.to_string() })
+    }
+}
+
+/// 读取客户端请求头，返回请求头与已读入的多余数据（请求体开头）。
+async fn read_head(stream: &mut TcpStream) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut buf = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            bail!("客户端在请求头结束前关闭了连接");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(pos + 4);
+            return Ok((buf, rest));
+        }
+        if buf.len() > MAX_HEAD_SIZE {
+            bail!("请求头过长");
+        }
+    }
+}
+
+/// 以 `uri` 作为请求目标重写请求头，去掉逐跳请求头并加上 `Connection: close`。
+///
+/// HTTP 上游使用原始的绝对地址，SOCKS 隧道使用相对路径。
+fn forward_head(head: &str, request: &RequestLine, uri: &str) -> Vec<u8> {
+    let mut out = format!("{} {} {}\r\n", request.method, uri, request.version);
+    for line in head.split("\r\n").skip(1).filter(|l| !l.is_empty()) {
+        let name = line.split(':').next().unwrap_or_default().trim().to_ascii_lowercase();
+        if !HOP_HEADERS.contains(&name.as_str()) {
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+    }
+    out.push_str("Connection: close\r\n\r\n");
+    out.into_bytes()
+}
+
+/// 处理单个 HTTP 代理客户端连接。
+pub async fn handle(mut client: TcpStream, pool: Arc<UpstreamPool>) -> Result<()> {
+    let (head, rest) = read_head(&mut client).await?;
+    let head = String::from_utf8_lossy(&head).into_owned();
+    let request = RequestLine::parse(head.lines().next().unwrap_or_default())?;
+
+    let result = match &request.target {
+        Target::Connect { host, port } => {
+            pool.establish_tunnel(&upstream::authority(host, *port), |proxy| async move {
+                upstream::tunnel(&proxy, host, *port).await
+            })
+            .await
+        }
+        Target::Forward { host, port, path } => {
+            let absolute = forward_head(&head, &request, &request.uri);
+            let origin = forward_head(&head, &request, path);
+            pool.establish(&upstream::authority(host, *port), |proxy| {
+                let (absolute, origin) = (&absolute, &origin);
+                async move {
+                    let mut stream = match proxy.protocol {
+                        Protocol::Http | Protocol::Https => {
+                            let mut stream = upstream::connect(&proxy).await?;
+                            stream.write_all(absolute).await?;
+                            stream
+                        }
+                        Protocol::Socks4 | Protocol::Socks5 => {
+                            let mut stream = upstream::tunnel(&proxy, host, *port).await?;
+                            stream.write_all(origin).await?;
+                            stream
+                        }
+                    };
+                    stream.flush().await?;
+                    Ok(stream)
+                }
+            })
+            .await
+        }
+    };
+
+    let (mut upstream, proxy) = match result {
+        Ok(ok) => ok,
+        Err(e) => {
+            client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await?;
+            return Err(e);
+        }
+    };
+
+    if let Target::Connect { .. } = request.target {
+        client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?;
+    }
+    if !rest.is_empty() {
+        upstream.write_all(&rest).await?;
+    }
+
+    let (sent, received) = copy_bidirectional(&mut client, &mut upstream).await?;
+    debug!("连接经上游 {}:{} 结束，发送 {} 字节，接收 {} 字节", proxy.ip, proxy.port, sent, received);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line() {
+        let connect = RequestLine::parse("CONNECT example.com:443 HTTP/1.1").unwrap();
+        assert_eq!(connect.target, Target::Connect { host: "example.com".into(), port: 443 });
+
+        let forward = RequestLine::parse("GET http://example.com/a?b=1 HTTP/1.1").unwrap();
+        assert_eq!(
+            forward.target,
+            Target::Forward { host: "example.com".into(), port: 80, path: "/a?b=1".into() }
+        );
+
+        let ipv6 = RequestLine::parse("GET http://[::1]:8080 HTTP/1.1").unwrap();
+        assert_eq!(ipv6.target, Target::Forward { host: "::1".into(), port: 8080, path: "/".into() });
+
+        assert!(RequestLine::parse("GET /relative HTTP/1.1").is_err());
+        assert!(RequestLine::parse("GET").is_err());
+    }
+
+    #[test]
+    fn test_forward_head() {
+        let head = "GET http://example.com/a HTTP/1.1\r\nHost: example.com\r\nProxy-Connection: keep-alive\r\n\
+                    Proxy-Authorization: Basic dXNlcjpwYXNz\r\nConnection: keep-alive\r\n\r\n";
+        let request = RequestLine::parse(head.lines().next().unwrap()).unwrap();
+        let out = forward_head(head, &request, "/a");
+        assert_eq!(String::from_utf8(out).unwrap(), "GET /a HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n");
+        let out = forward_head(head, &request, &request.uri);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "GET http://example.com/a HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n"
+        );
+    }
+}
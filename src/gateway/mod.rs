@@ -0,0 +1,74 @@
+//! # gateway 模块
+//!
+//! 内置的正向代理网关：客户端把 ProxyHydra 当作普通代理使用，
+//! 网关为每个连接从已验证的代理池中挑选上游代理转发流量。
+//!
+//! 支持 HTTP/HTTPS（CONNECT）与 SOCKS5 两种入口协议，二者共享同一个上游代理池。
+//! 上游的选择策略、最大尝试次数与连接超时由 `[gateway]` 配置决定，
+//! 每次尝试的成败都会回写到对应代理的成功率与评分中。
+
+mod http;
+mod pool;
+mod socks5;
+mod upstream;
+
+use crate::model::APP_CONFIG;
+use crate::service::selector::SELECTOR;
+use anyhow::Result;
+use pool::UpstreamPool;
+use socks5::Credentials;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// 根据配置启动网关，未配置监听地址的入口不启动。
+///
+/// # 错误
+/// 监听地址无法绑定时返回错误。
+pub async fn start() -> Result<()> {
+    let config = &APP_CONFIG.gateway;
+    let pool = Arc::new(UpstreamPool::new(config, &SELECTOR));
+
+    if let Some(addr) = &config.http_addr {
+        serve("HTTP", addr, Arc::clone(&pool), http::handle).await?;
+    }
+    if let Some(addr) = &config.socks5_addr {
+        let credentials = Credentials::from_config(config).map(Arc::new);
+        if credentials.is_none() {
+            warn!("SOCKS5 网关未配置用户名密码，任何能访问 {} 的客户端都可以使用", addr);
+        }
+        serve("SOCKS5", addr, Arc::clone(&pool), move |client, pool| {
+            socks5::handle(client, pool, credentials.clone())
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+/// 在指定地址监听，监听成功后在后台持续接受连接，每个连接交给 `handle` 在独立任务中处理。
+async fn serve<F, Fut>(name: &'static str, addr: &str, pool: Arc<UpstreamPool>, handle: F) -> Result<()>
+where
+    F: Fn(TcpStream, Arc<UpstreamPool>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).await?;
+    info!("🌐 {} 代理网关已启动：{}", name, addr);
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((client, peer)) => {
+                    let task = handle(client, Arc::clone(&pool));
+                    tokio::spawn(async move {
+                        if let Err(e) = task.await {
+                            warn!("{} 网关处理 {} 的连接失败：{}", name, peer, e);
+                        }
+                    });
+                }
+                Err(e) => warn!("{} 网关接受连接失败：{}", name, e),
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! 网关的上游代理池：按策略挑选上游代理，失败时换用其他上游重试，
+//! 并将每次尝试的结果回写到代理统计数据中。
+
+use crate::common::cache::cached_proxies;
+use crate::model::{GatewayConfig, Proxy, ProxyBasic, SelectStrategy};
+use crate::service::feedback::FEEDBACK;
+use crate::service::lease::LEASES;
+use crate::service::selector::Selector;
+use crate::service::stats;
+use anyhow::{anyhow, Result};
+use std::ptr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+/// 单次尝试的结果：上游代理、是否成功、建立连接耗时（秒）。
+pub type Outcome = (ProxyBasic, bool, Option<f64>);
+
+/// 上游代理池。
+pub struct UpstreamPool {
+    strategy: SelectStrategy,
+    selector: &'static Selector,
+    max_attempts: usize,
+    connect_timeout: Duration,
+}
+
+impl UpstreamPool {
+    /// 根据网关配置创建代理池，上游由 `selector` 按配置的策略挑选。
+    pub fn new(config: &GatewayConfig, selector: &'static Selector) -> Self {
+        Self {
+            strategy: config.strategy,
+            selector,
+            max_attempts: config.max_attempts.max(1),
+            connect_timeout: Duration::from_secs(config.connect_timeout),
+        }
+    }
+
+    /// 按策略从候选代理中挑选一个未尝试过的上游，`tried` 为已尝试的候选（按引用比较，尝试次数很少）。
+    fn pick<'a>(&self, candidates: &[&'a Proxy], tried: &[&Proxy]) -> Option<&'a Proxy> {
+        let available: Vec<&Proxy> =
+            candidates.iter().copied().filter(|p| !tried.iter().any(|t| ptr::eq(*t, *p))).collect();
+        self.selector.select(self.strategy, &available)
+    }
+
+    /// 依次尝试候选上游，直到 `connect` 成功或达到最大尝试次数。
+    ///
+    /// 返回建立结果以及每次尝试的结果，由调用方决定如何回写统计。
+    pub async fn try_candidates<F, Fut>(
+        &self,
+        candidates: &[&Proxy],
+        target: &str,
+        connect: F,
+    ) -> (Result<(TcpStream, Proxy)>, Vec<Outcome>)
+    where
+        F: Fn(ProxyBasic) -> Fut,
+        Fut: Future<Output = Result<TcpStream>>,
+    {
+        let mut tried = Vec::new();
+        let mut outcomes = Vec::new();
+        let mut last_error = None;
+
+        for attempt in 1..=self.max_attempts {
+            let Some(proxy) = self.pick(candidates, &tried) else { break };
+            let basic = proxy.basic();
+            tried.push(proxy);
+
+            let start = Instant::now();
+            let result = timeout(self.connect_timeout, connect(basic.clone()))
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("连接超时")));
+
+            match result {
+                Ok(stream) => {
+                    let latency = start.elapsed().as_secs_f64();
+                    debug!("🔗 经上游 {}:{} 连接 {} 成功，耗时 {:.2}s", basic.ip, basic.port, target, latency);
+                    outcomes.push((basic, true, Some(latency)));
+                    return (Ok((stream, proxy.clone())), outcomes);
+                }
+                Err(e) => {
+                    warn!("🔁 第 {} 次尝试经上游 {}:{} 连接 {} 失败：{}", attempt, basic.ip, basic.port, target, e);
+                    outcomes.push((basic, false, None));
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let error = last_error.unwrap_or_else(|| anyhow!("代理池中没有可用的上游代理"));
+        (Err(error.context(format!("无法通过上游代理连接 {}", target))), outcomes)
+    }
+
+    /// 从代理池中挑选上游并建立连接，失败时自动换用其他上游，
+    /// 每次尝试的结果都会在后台回写到对应代理的统计数据。
+    ///
+    /// 处于隔离期、租约数已满或被标记为内容篡改的代理不会被选作上游。
+    pub async fn establish<F, Fut>(&self, target: &str, connect: F) -> Result<(TcpStream, Proxy)>
+    where
+        F: Fn(ProxyBasic) -> Fut,
+        Fut: Future<Output = Result<TcpStream>>,
+    {
+        self.establish_with(target, |_| true, connect).await
+    }
+
+    /// 同 [`establish`](Self::establish)，但只从支持隧道的上游（HTTPS、SOCKS4、SOCKS5）中挑选，
+    /// 用于 CONNECT 与 SOCKS5 入站连接。
+    pub async fn establish_tunnel<F, Fut>(&self, target: &str, connect: F) -> Result<(TcpStream, Proxy)>
+    where
+        F: Fn(ProxyBasic) -> Fut,
+        Fut: Future<Output = Result<TcpStream>>,
+    {
+        self.establish_with(target, |p| p.protocol.supports_tunnel(), connect).await
+    }
+
+    async fn establish_with<F, Fut>(
+        &self,
+        target: &str,
+        accept: impl Fn(&Proxy) -> bool,
+        connect: F,
+    ) -> Result<(TcpStream, Proxy)>
+    where
+        F: Fn(ProxyBasic) -> Fut,
+        Fut: Future<Output = Result<TcpStream>>,
+    {
+        let proxies = cached_proxies().await?;
+        let usable: Vec<&Proxy> = proxies
+            .iter()
+            .filter(|p| accept(p) && !p.tampered && !FEEDBACK.is_quarantined(p))
+            .collect();
+        let candidates = LEASES.available(&usable);
+        let (result, outcomes) = self.try_candidates(&candidates, target, connect).await;
+
+        tokio::spawn(async move {
+            for (basic, ok, latency) in outcomes {
+                if let Err(e) = stats::record_outcome(&basic, ok, latency).await {
+                    warn!("更新代理 {}:{} 统计数据失败：{}", basic.ip, basic.port, e);
+                }
+            }
+        });
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn pool(strategy: SelectStrategy, max_attempts: usize) -> UpstreamPool {
+        let selector = Box::leak(Box::new(Selector::default()));
+        UpstreamPool::new(&GatewayConfig { strategy, max_attempts, ..Default::default() }, selector)
+    }
+
+    fn proxy(port: &str, score: f64) -> Proxy {
+        let mut proxy = Proxy::new("127.0.0.1".into(), port.into());
+        proxy.score = Some(score);
+        proxy
+    }
+
+    #[test]
+    fn test_pick() {
+        let proxies = [proxy("1", 0.0), proxy("2", 1.0), proxy("3", 0.5)];
+        let candidates: Vec<&Proxy> = proxies.iter().collect();
+        let mut tried = Vec::new();
+
+        let round_robin = pool(SelectStrategy::RoundRobin, 3);
+        let ports: Vec<_> = (0..4).map(|_| round_robin.pick(&candidates, &tried).unwrap().port.clone()).collect();
+        assert_eq!(ports, vec!["1", "2", "3", "1"]);
+
+        tried.extend([&proxies[1], &proxies[2]]);
+        let weighted = pool(SelectStrategy::WeightedByScore, 3);
+        assert_eq!(weighted.pick(&candidates, &tried).unwrap().port, "1");
+
+        tried.push(&proxies[0]);
+        assert!(pool(SelectStrategy::Random, 3).pick(&candidates, &tried).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_try_candidates_failover() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let good = listener.local_addr().unwrap().port().to_string();
+        let proxies = [proxy("1", 1.0), proxy(&good, 1.0)];
+        let candidates: Vec<&Proxy> = proxies.iter().collect();
+
+        let pool = pool(SelectStrategy::RoundRobin, 3);
+        let (result, outcomes) = pool
+            .try_candidates(&candidates, "example.com:80", |basic| async move {
+                if basic.port == "1" {
+                    return Err(anyhow!("模拟失败"));
+                }
+                Ok(TcpStream::connect(format!("{}:{}", basic.ip, basic.port)).await?)
+            })
+            .await;
+
+        let (_, upstream) = result.unwrap();
+        assert_eq!(upstream.port, good);
+        assert_eq!(outcomes.len(), 2);
+        assert!(!outcomes[0].1);
+        assert!(outcomes[1].1 && outcomes[1].2.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_try_candidates_exhausted() {
+        let proxies = [proxy("1", 1.0), proxy("2", 1.0), proxy("3", 1.0)];
+        let candidates: Vec<&Proxy> = proxies.iter().collect();
+        let pool = pool(SelectStrategy::Random, 2);
+        let (result, outcomes) = pool
+            .try_candidates(&candidates, "example.com:80", |_| async { Err(anyhow!("模拟失败")) })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(outcomes.len(), 2);
+        assert_ne!(outcomes[0].0.port, outcomes[1].0.port);
+    }
+}
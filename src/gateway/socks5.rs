@@ -0,0 +1,242 @@
+//! SOCKS5 代理网关（RFC 1928），支持可选的用户名密码认证（RFC 1929）。
+//!
+//! 仅支持 `CONNECT` 命令：握手完成后经上游代理建立到目标地址的隧道并双向转发，
+//! 域名目标直接交给上游代理解析。
+
+use crate::gateway::pool::UpstreamPool;
+use crate::gateway::upstream;
+use crate::model::GatewayConfig;
+use anyhow::{bail, Result};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use tokio::io::{copy_bidirectional, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+const VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+
+const METHOD_NONE: u8 = 0x00;
+const METHOD_PASSWORD: u8 = 0x02;
+const METHOD_UNACCEPTABLE: u8 = 0xFF;
+
+const CMD_CONNECT: u8 = 0x01;
+
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_FAILURE: u8 = 0x01;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// SOCKS5 用户名密码。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    /// 用户名与密码都配置时返回认证信息，否则不启用认证。
+    pub fn from_config(config: &GatewayConfig) -> Option<Self> {
+        match (&config.username, &config.password) {
+            (Some(username), Some(password)) => Some(Self { username: username.clone(), password: password.clone() }),
+            _ => None,
+        }
+    }
+}
+
+/// 协商认证方式，配置了认证信息时要求客户端使用用户名密码认证。
+async fn negotiate<S>(stream: &mut S, credentials: Option<&Credentials>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let version = stream.read_u8().await?;
+    if version != VERSION {
+        bail!("不支持的 SOCKS 版本：{}", version);
+    }
+    let mut methods = vec![0u8; stream.read_u8().await? as usize];
+    stream.read_exact(&mut methods).await?;
+
+    let method = if credentials.is_some() { METHOD_PASSWORD } else { METHOD_NONE };
+    if !methods.contains(&method) {
+        stream.write_all(&[VERSION, METHOD_UNACCEPTABLE]).await?;
+        bail!("客户端不支持所需的认证方式：{:#04x}", method);
+    }
+    stream.write_all(&[VERSION, method]).await?;
+
+    let Some(credentials) = credentials else { return Ok(()) };
+
+    let version = stream.read_u8().await?;
+    if version != AUTH_VERSION {
+        bail!("不支持的认证协议版本：{}", version);
+    }
+    let mut username = vec![0u8; stream.read_u8().await? as usize];
+    stream.read_exact(&mut username).await?;
+    let mut password = vec![0u8; stream.read_u8().await? as usize];
+    stream.read_exact(&mut password).await?;
+
+    if username != credentials.username.as_bytes() || password != credentials.password.as_bytes() {
+        stream.write_all(&[AUTH_VERSION, 0x01]).await?;
+        bail!("用户名或密码错误：{}", String::from_utf8_lossy(&username));
+    }
+    stream.write_all(&[AUTH_VERSION, 0x00]).await?;
+    Ok(())
+}
+
+/// 读取连接请求，返回目标主机与端口；不支持的命令或地址类型会先回复错误码。
+async fn read_request<S>(stream: &mut S) -> Result<(String, u16)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    let [version, command, _, atyp] = header;
+    if version != VERSION {
+        bail!("不支持的 SOCKS 版本：{}", version);
+    }
+
+    let host = match atyp {
+        ATYP_IPV4 => {
+            let mut ip = [0u8; 4];
+            stream.read_exact(&mut ip).await?;
+            Ipv4Addr::from(ip).to_string()
+        }
+        ATYP_IPV6 => {
+            let mut ip = [0u8; 16];
+            stream.read_exact(&mut ip).await?;
+            Ipv6Addr::from(ip).to_string()
+        }
+        ATYP_DOMAIN => {
+            let mut domain = vec![0u8; stream.read_u8().await? as usize];
+            stream.read_exact(&mut domain).await?;
+            String::from_utf8(domain)?
+        }
+        other => {
+            reply(stream, REPLY_ADDRESS_NOT_SUPPORTED).await?;
+            bail!("不支持的地址类型：{}", other);
+        }
+    };
+    let port = stream.read_u16().await?;
+
+    if command != CMD_CONNECT {
+        reply(stream, REPLY_COMMAND_NOT_SUPPORTED).await?;
+        bail!("不支持的 SOCKS 命令：{}", command);
+    }
+    Ok((host, port))
+}
+
+/// 回复连接请求结果，绑定地址统一填 `0.0.0.0:0`。
+async fn reply<S>(stream: &mut S, code: u8) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream.write_all(&[VERSION, code, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0]).await?;
+    Ok(())
+}
+
+/// 处理单个 SOCKS5 客户端连接。
+pub async fn handle(mut client: TcpStream, pool: Arc<UpstreamPool>, credentials: Option<Arc<Credentials>>) -> Result<()> {
+    negotiate(&mut client, credentials.as_deref()).await?;
+    let (host, port) = read_request(&mut client).await?;
+
+    let target = upstream::authority(&host, port);
+    let result = pool
+        .establish_tunnel(&target, |proxy| {
+            let host = &host;
+            async move { upstream::tunnel(&proxy, host, port).await }
+        })
+        .await;
+
+    let (mut upstream, proxy) = match result {
+        Ok(ok) => ok,
+        Err(e) => {
+            reply(&mut client, REPLY_FAILURE).await?;
+            return Err(e);
+        }
+    };
+    reply(&mut client, REPLY_SUCCEEDED).await?;
+
+    let (sent, received) = copy_bidirectional(&mut client, &mut upstream).await?;
+    debug!("SOCKS5 连接 {} 经上游 {}:{} 结束，发送 {} 字节，接收 {} 字节", target, proxy.ip, proxy.port, sent, received);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    fn credentials() -> Credentials {
+        Credentials { username: "hydra".into(), password: "secret".into() }
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_without_auth() {
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[VERSION, 1, METHOD_NONE]).await.unwrap();
+        negotiate(&mut server, None).await.unwrap();
+
+        let mut response = [0u8; 2];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [VERSION, METHOD_NONE]);
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_with_auth() {
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[VERSION, 2, METHOD_NONE, METHOD_PASSWORD]).await.unwrap();
+        client.write_all(&[AUTH_VERSION, 5]).await.unwrap();
+        client.write_all(b"hydra").await.unwrap();
+        client.write_all(&[6]).await.unwrap();
+        client.write_all(b"secret").await.unwrap();
+        negotiate(&mut server, Some(&credentials())).await.unwrap();
+
+        let mut response = [0u8; 4];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [VERSION, METHOD_PASSWORD, AUTH_VERSION, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_rejects() {
+        // 要求认证但客户端只支持无认证
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[VERSION, 1, METHOD_NONE]).await.unwrap();
+        assert!(negotiate(&mut server, Some(&credentials())).await.is_err());
+        let mut response = [0u8; 2];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [VERSION, METHOD_UNACCEPTABLE]);
+
+        // 密码错误
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[VERSION, 1, METHOD_PASSWORD, AUTH_VERSION, 5]).await.unwrap();
+        client.write_all(b"hydra").await.unwrap();
+        client.write_all(&[5]).await.unwrap();
+        client.write_all(b"wrong").await.unwrap();
+        assert!(negotiate(&mut server, Some(&credentials())).await.is_err());
+        let mut response = [0u8; 4];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [VERSION, METHOD_PASSWORD, AUTH_VERSION, 0x01]);
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let (mut client, mut server) = duplex(64);
+        client.write_all(&[VERSION, CMD_CONNECT, 0, ATYP_DOMAIN, 11]).await.unwrap();
+        client.write_all(b"example.com").await.unwrap();
+        client.write_all(&443u16.to_be_bytes()).await.unwrap();
+        assert_eq!(read_request(&mut server).await.unwrap(), ("example.com".to_string(), 443));
+
+        client.write_all(&[VERSION, CMD_CONNECT, 0, ATYP_IPV4, 1, 2, 3, 4, 0, 80]).await.unwrap();
+        assert_eq!(read_request(&mut server).await.unwrap(), ("1.2.3.4".to_string(), 80));
+
+        // BIND 命令不支持
+        client.write_all(&[VERSION, 0x02, 0, ATYP_IPV4, 1, 2, 3, 4, 0, 80]).await.unwrap();
+        assert!(read_request(&mut server).await.is_err());
+        let mut response = [0u8; 10];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response[1], REPLY_COMMAND_NOT_SUPPORTED);
+    }
+}
@@ -0,0 +1,125 @@
+//! 通过上游代理建立到目标地址的 TCP 连接。
+//!
+//! HTTP/HTTPS 上游使用 `CONNECT` 建立隧道，SOCKS4/SOCKS5 上游使用对应的握手协议，
+//! 域名均交由上游代理解析。
+
+use crate::model::{Protocol, ProxyBasic};
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::{Socks4Stream, Socks5Stream};
+
+/// 上游 `CONNECT` 响应头的最大长度。
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+/// 拼接 `host:port`，IPv6 地址会加上方括号。
+pub fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// 上游代理自身的地址，IPv6 地址会加上方括号。
+fn proxy_addr(proxy: &ProxyBasic) -> Result<String> {
+    let port = proxy.port.parse::<u16>().with_context(|| format!("上游代理端口不合法：{}", proxy.port))?;
+    Ok(authority(&proxy.ip, port))
+}
+
+/// 直接连接上游代理本身，用于向 HTTP 代理转发普通（非 CONNECT）请求。
+pub async fn connect(proxy: &ProxyBasic) -> Result<TcpStream> {
+    Ok(TcpStream::connect(proxy_addr(proxy)?).await?)
+}
+
+/// 通过上游代理建立到 `host:port` 的隧道，返回可直接读写目标数据的连接。
+pub async fn tunnel(proxy: &ProxyBasic, host: &str, port: u16) -> Result<TcpStream> {
+    let addr = proxy_addr(proxy)?;
+    let stream = match proxy.protocol {
+        Protocol::Http | Protocol::Https => http_connect(&addr, host, port).await?,
+        Protocol::Socks4 => Socks4Stream::connect(addr.as_str(), (host, port)).await?.into_inner(),
+        Protocol::Socks5 => Socks5Stream::connect(addr.as_str(), (host, port)).await?.into_inner(),
+    };
+    Ok(stream)
+}
+
+/// 向 HTTP 代理发送 `CONNECT` 请求，收到 2xx 响应后返回隧道连接。
+async fn http_connect(addr: &str, host: &str, port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let target = authority(host, port);
+    let request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // 逐字节读取响应头，避免把隧道建立后目标发来的数据读走
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD {
+            bail!("上游代理 CONNECT 响应头过长");
+        }
+        head.push(stream.read_u8().await?);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(stream),
+        _ => bail!("上游代理拒绝 CONNECT：{}", status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 模拟一个 HTTP 代理：按预设状态行响应 CONNECT，随后回显收到的数据。
+    async fn fake_http_proxy(status: &'static str) -> ProxyBasic {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            assert!(head.starts_with(b"CONNECT example.com:443 HTTP/1.1\r\n"));
+            stream.write_all(format!("{status}\r\n\r\n").as_bytes()).await.unwrap();
+            let mut buf = [0u8; 4];
+            if stream.read_exact(&mut buf).await.is_ok() {
+                stream.write_all(&buf).await.unwrap();
+            }
+        });
+        ProxyBasic::new("127.0.0.1", &port.to_string())
+    }
+
+    #[test]
+    fn test_authority() {
+        assert_eq!(authority("example.com", 443), "example.com:443");
+        assert_eq!(authority("::1", 80), "[::1]:80");
+        assert_eq!(proxy_addr(&ProxyBasic::new("::1", "8080")).unwrap(), "[::1]:8080");
+        assert!(proxy_addr(&ProxyBasic::new("127.0.0.1", "http")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_ipv6() {
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else { return };
+        let port = listener.local_addr().unwrap().port().to_string();
+        assert!(connect(&ProxyBasic::new("::1", &port)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_http_tunnel() {
+        let proxy = fake_http_proxy("HTTP/1.1 200 Connection established").await;
+        let mut stream = tunnel(&proxy, "example.com", 443).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_http_tunnel_rejected() {
+        let proxy = fake_http_proxy("HTTP/1.1 403 Forbidden").await;
+        assert!(tunnel(&proxy, "example.com", 443).await.is_err());
+    }
+}
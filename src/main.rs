@@ -1,13 +1,18 @@
+mod cli;
 mod model;
 mod service;
 mod fetcher;
 mod common;
 mod db;
+mod gateway;
 mod web;
 
 use crate::common::log::init_logging;
-use crate::db::manager::ProxyStorage;
+use crate::web::api::cache_api::cache_router;
+use crate::web::api::echo_api::echo_router;
+use crate::web::api::job_api::job_router;
 use crate::web::api::proxy_api::proxy_router;
+use crate::web::api::schedule_api::schedule_router;
 use salvo::prelude::TcpListener;
 use salvo::{Listener, Router, Server};
 use crate::model::APP_CONFIG;
@@ -15,11 +20,25 @@ use crate::model::APP_CONFIG;
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     init_logging().expect("Failed to initialize logging");
+    let command = cli::Command::parse(std::env::args().skip(1))?;
+    if let Some(command) = command {
+        return command.run().await; // 执行维护子命令后退出
+    }
     db::init().await?; // 初始化数据库
+    fetcher::init()?; // 初始化代理源
+    common::cache::start_refresh(); // 定期刷新代理池缓存
+    service::history::start_prune(); // 定期清理过期的检测记录
+    service::scheduler::start()?; // 启动定时任务
+    gateway::start().await?; // 启动代理网关
 
     let acceptor = TcpListener::new(format!("{}:{}", APP_CONFIG.server.addr, APP_CONFIG.server.port)).bind().await;
 
-    let router = Router::new().push(proxy_router());
+    let router = Router::new()
+        .push(proxy_router())
+        .push(echo_router())
+        .push(schedule_router())
+        .push(job_router())
+        .push(cache_router());
     Server::new(acceptor).serve(router).await;
 
     Ok(())
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use crate::model::{Protocol, SelectStrategy};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
@@ -12,8 +13,34 @@ pub struct AppConfig {
     pub verify: VerifyConfig,
     pub db: DbConfig,
     pub log: LoggingConfig,
-    pub server: ServerConfig
-
+    pub server: ServerConfig,
+    /// 配置文件中声明的通用代理源（`[[sources]]`），可为空。
+    #[serde(default)]
+    pub sources: Vec<SourceConfig>,
+    /// 定时任务配置，未配置时不启用定时任务。
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// 内置正向代理网关配置，未配置监听地址时不启用。
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    /// 代理选择配置。
+    #[serde(default)]
+    pub selector: SelectorConfig,
+    /// 代理池缓存配置。
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// 代理租约配置。
+    #[serde(default)]
+    pub lease: LeaseConfig,
+    /// 客户端反馈配置。
+    #[serde(default)]
+    pub feedback: FeedbackConfig,
+    /// 代理检测历史配置。
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// 综合评分的权重与速度评分曲线。
+    #[serde(default)]
+    pub scoring: ScoringConfig,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ServerConfig {
@@ -34,8 +61,379 @@ pub struct DbConfig {
 pub struct VerifyConfig {
     pub semaphore: usize,
     pub timeout: u64,
+    /// 测试地址列表（旧写法），未配置 `targets` 时按地址生成同名目标站点。
+    #[serde(default)]
     pub test_urls: Vec<String>,
-    pub verify_level: u32
+    /// 命名的目标站点（`[[verify.targets]]`），验证时分别测试并记录各站点的结果与评分。
+    #[serde(default)]
+    pub targets: Vec<TargetProfile>,
+    pub verify_level: u32,
+    /// 匿名检测使用的回显接口（兼容 httpbin `/get` 格式），未配置时不检测匿名等级。
+    #[serde(default)]
+    pub echo_url: Option<String>,
+    /// 本机真实出口 IP，未配置时通过直连回显接口自动获取。
+    #[serde(default)]
+    pub egress_ip: Option<String>,
+    /// 稳定性历史的半衰期（秒）：距上次检测经过该时长后，历史稳定性的权重衰减为一半。
+    #[serde(default = "default_stability_half_life")]
+    pub stability_half_life: u64,
+    /// 检测到目标站点返回被篡改的内容时对代理的处理方式。
+    #[serde(default)]
+    pub tamper_action: TamperAction,
+}
+
+/// 代理返回被篡改内容（劫持页、注入广告等）时的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperAction {
+    /// 保留代理但标记为篡改，默认不再分配给客户端。
+    #[default]
+    Flag,
+    /// 视为无效代理，直接从存储中删除。
+    Drop,
+}
+
+impl VerifyConfig {
+    /// 验证使用的目标站点：优先使用 `targets`，否则由 `test_urls` 生成，名称为地址本身。
+    pub fn profiles(&self) -> Vec<TargetProfile> {
+        if !self.targets.is_empty() {
+            return self.targets.clone();
+        }
+        self.test_urls.iter().map(|url| TargetProfile::new(url, url)).collect()
+    }
+
+    /// 校验目标站点配置。
+    ///
+    /// # 错误
+    /// 没有任何目标、名称重复或正则表达式无效时返回错误。
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let profiles = self.profiles();
+        if profiles.is_empty() {
+            anyhow::bail!("[verify] 至少需要配置一个 targets 或 test_urls");
+        }
+        let mut names = std::collections::HashSet::new();
+        for profile in &profiles {
+            if !names.insert(profile.name.as_str()) {
+                anyhow::bail!("[verify] 目标站点名称重复：{}", profile.name);
+            }
+            if let Some(pattern) = &profile.body_regex {
+                regex::Regex::new(pattern).map_err(|e| anyhow::anyhow!("[verify] 目标站点 {} 的 body_regex 无效：{}", profile.name, e))?;
+            }
+            if let Some(hash) = &profile.body_sha256
+                && (hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()))
+            {
+                anyhow::bail!("[verify] 目标站点 {} 的 body_sha256 应为 64 位十六进制字符串", profile.name);
+            }
+            if let Some(pointer) = &profile.json_field
+                && !pointer.is_empty()
+                && !pointer.starts_with('/')
+            {
+                anyhow::bail!("[verify] 目标站点 {} 的 json_field 应为以 / 开头的 JSON Pointer", profile.name);
+            }
+            if profile.json_equals.is_some() && profile.json_field.is_none() {
+                anyhow::bail!("[verify] 目标站点 {} 配置了 json_equals 但缺少 json_field", profile.name);
+            }
+            if profile.max_body_size == Some(0) {
+                anyhow::bail!("[verify] 目标站点 {} 的 max_body_size 必须大于 0", profile.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 验证目标站点，对应配置文件中的一个 `[[verify.targets]]` 条目。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TargetProfile {
+    /// 站点名称，用于 `/proxy?site=` 查询与结果记录。
+    pub name: String,
+    /// 测试地址。
+    pub url: String,
+    /// 期望的 HTTP 状态码，未配置时接受任意 2xx。
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+    /// 响应内容需要包含的子串。
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    /// 响应内容需要匹配的正则表达式。
+    #[serde(default)]
+    pub body_regex: Option<String>,
+    /// 响应内容的 SHA-256 摘要（十六进制），适用于内容固定的页面。
+    #[serde(default)]
+    pub body_sha256: Option<String>,
+    /// 响应须为 JSON 且包含该字段（JSON Pointer，如 `/origin`）。
+    #[serde(default)]
+    pub json_field: Option<String>,
+    /// `json_field` 字段的期望值，字符串字段按原文比较，其他类型按 JSON 解析后比较。
+    #[serde(default)]
+    pub json_equals: Option<String>,
+    /// 校验响应内容时最多读取的字节数，默认 1 MiB；超出时记为 body_too_large 失败。
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+}
+
+impl TargetProfile {
+    /// 只校验状态码为 2xx 的目标站点。
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            expected_status: None,
+            body_contains: None,
+            body_regex: None,
+            body_sha256: None,
+            json_field: None,
+            json_equals: None,
+            max_body_size: None,
+        }
+    }
+}
+
+/// 定时任务配置，cron 表达式包含秒字段（`秒 分 时 日 月 周`）。
+///
+/// 任一表达式为空时，对应任务不启用。
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ScheduleConfig {
+    /// 代理采集（抓取 + 验证）的 cron 表达式。
+    #[serde(default)]
+    pub collect_cron: Option<String>,
+    /// 数据库存活代理复检的 cron 表达式。
+    #[serde(default)]
+    pub verify_cron: Option<String>,
+}
+
+/// 正向代理网关配置。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GatewayConfig {
+    /// HTTP/HTTPS（CONNECT）代理监听地址，如 `0.0.0.0:9901`。
+    #[serde(default)]
+    pub http_addr: Option<String>,
+    /// SOCKS5 代理监听地址，如 `0.0.0.0:9902`。
+    #[serde(default)]
+    pub socks5_addr: Option<String>,
+    /// SOCKS5 用户名，与 `password` 同时配置时启用用户名密码认证。
+    #[serde(default)]
+    pub username: Option<String>,
+    /// SOCKS5 密码。
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 每个连接选择上游代理的策略。
+    #[serde(default = "default_gateway_strategy")]
+    pub strategy: SelectStrategy,
+    /// 单个连接最多尝试的上游代理数量。
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+    /// 连接上游代理并完成握手的超时时间（秒）。
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            http_addr: None,
+            socks5_addr: None,
+            username: None,
+            password: None,
+            strategy: default_gateway_strategy(),
+            max_attempts: default_max_attempts(),
+            connect_timeout: default_connect_timeout(),
+        }
+    }
+}
+
+/// 代理池缓存配置。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CacheConfig {
+    /// 后台整体刷新代理池缓存的间隔（秒），为 0 时不定期刷新。
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: u64,
+    /// 代理池缓存的有效期（秒），过期后下次读取时从存储重新加载，为 0 时永不过期。
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { refresh_interval: default_refresh_interval(), ttl: default_cache_ttl() }
+    }
+}
+
+/// 代理租约配置。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LeaseConfig {
+    /// 每个代理允许同时存在的租约数，1 表示完全独占。
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// 未指定时长时的默认租约时长（秒）。
+    #[serde(default = "default_lease_ttl")]
+    pub default_ttl: u64,
+    /// 允许申请的最长租约时长（秒）。
+    #[serde(default = "default_lease_max_ttl")]
+    pub max_ttl: u64,
+}
+
+impl Default for LeaseConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            default_ttl: default_lease_ttl(),
+            max_ttl: default_lease_max_ttl(),
+        }
+    }
+}
+
+/// 客户端反馈配置。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FeedbackConfig {
+    /// 连续反馈失败多少次后处理该代理，为 0 时不处理。
+    #[serde(default = "default_max_consecutive_failures")]
+    pub max_consecutive_failures: u32,
+    /// 连续失败达到上限后的处理方式。
+    #[serde(default)]
+    pub action: FailureAction,
+    /// 隔离时长（秒），仅 `action = "quarantine"` 时生效。
+    #[serde(default = "default_quarantine_secs")]
+    pub quarantine_secs: u64,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: default_max_consecutive_failures(),
+            action: FailureAction::default(),
+            quarantine_secs: default_quarantine_secs(),
+        }
+    }
+}
+
+/// 代理连续反馈失败后的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureAction {
+    /// 隔离一段时间，期间不再分配该代理。
+    #[default]
+    Quarantine,
+    /// 直接从存储中删除。
+    Remove,
+}
+
+/// 代理检测历史配置。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HistoryConfig {
+    /// 检测记录的保留天数，为 0 时永久保留。
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { retention_days: default_retention_days() }
+    }
+}
+
+/// 综合评分配置。
+///
+/// 速度、成功率、稳定性三项权重之和必须为 1.0；
+/// 匿名等级权重为附加项，与其余三项一起按权重之和归一化，未检测出匿名等级时该项记 0 分。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScoringConfig {
+    /// 速度评分权重。
+    #[serde(default = "default_speed_weight")]
+    pub speed_weight: f64,
+    /// 成功率评分权重。
+    #[serde(default = "default_success_weight")]
+    pub success_weight: f64,
+    /// 稳定性评分权重。
+    #[serde(default = "default_stability_weight")]
+    pub stability_weight: f64,
+    /// 匿名等级评分权重（0.0 - 1.0）。
+    #[serde(default = "default_anonymity_weight")]
+    pub anonymity_weight: f64,
+    /// 响应时间（毫秒）不超过该值时速度得满分 1.0。
+    #[serde(default = "default_speed_fast_ms")]
+    pub speed_fast_ms: f64,
+    /// 响应时间（毫秒）达到该值时速度得分降为 0.3，其间按平方根曲线递减。
+    #[serde(default = "default_speed_slow_ms")]
+    pub speed_slow_ms: f64,
+    /// 响应时间（毫秒）达到该值及以上时速度得分为 0，其间线性递减。
+    #[serde(default = "default_speed_max_ms")]
+    pub speed_max_ms: f64,
+    /// 使用的评分模型。
+    #[serde(default)]
+    pub model: ScoringModelKind,
+    /// 逻辑回归模型文件路径，由 `train` 子命令生成。
+    #[serde(default = "default_model_file")]
+    pub model_file: String,
+}
+
+/// 评分模型类型。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoringModelKind {
+    /// 按权重加权（默认）。
+    #[default]
+    Weighted,
+    /// 从检测历史训练的逻辑回归模型，从 `model_file` 加载。
+    Logistic,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            speed_weight: default_speed_weight(),
+            success_weight: default_success_weight(),
+            stability_weight: default_stability_weight(),
+            anonymity_weight: default_anonymity_weight(),
+            speed_fast_ms: default_speed_fast_ms(),
+            speed_slow_ms: default_speed_slow_ms(),
+            speed_max_ms: default_speed_max_ms(),
+            model: ScoringModelKind::default(),
+            model_file: default_model_file(),
+        }
+    }
+}
+
+impl ScoringConfig {
+    /// 评分是否用到稳定性：加权模型的稳定性权重大于 0，或使用以稳定性为特征的逻辑回归模型。
+    pub fn weighs_stability(&self) -> bool {
+        self.model == ScoringModelKind::Logistic || self.stability_weight > 0.0
+    }
+
+    /// 校验权重与速度分段。
+    ///
+    /// # 错误
+    /// 权重为负、三项基础权重之和不为 1.0、匿名权重超过 1.0，
+    /// 或速度分段不满足 `0 < fast < slow < max` 时返回错误。
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let weights = [self.speed_weight, self.success_weight, self.stability_weight, self.anonymity_weight];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            anyhow::bail!("[scoring] 权重不能为负数");
+        }
+        let sum = self.speed_weight + self.success_weight + self.stability_weight;
+        if (sum - 1.0).abs() > 1e-6 {
+            anyhow::bail!("[scoring] speed_weight + success_weight + stability_weight 应为 1.0，当前为 {}", sum);
+        }
+        if self.anonymity_weight > 1.0 {
+            anyhow::bail!("[scoring] anonymity_weight 应在 0.0 - 1.0 之间，当前为 {}", self.anonymity_weight);
+        }
+        if !(0.0 < self.speed_fast_ms && self.speed_fast_ms < self.speed_slow_ms && self.speed_slow_ms < self.speed_max_ms) {
+            anyhow::bail!(
+                "[scoring] 速度分段应满足 0 < speed_fast_ms < speed_slow_ms < speed_max_ms，当前为 {} / {} / {}",
+                self.speed_fast_ms,
+                self.speed_slow_ms,
+                self.speed_max_ms
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 代理选择配置。
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SelectorConfig {
+    /// `/proxy` 未指定 `strategy` 参数时使用的选择策略。
+    #[serde(default)]
+    pub strategy: SelectStrategy,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -43,13 +441,157 @@ pub struct LoggingConfig {
     pub console_levels: Vec<String>,
 }
 
+/// 通用代理源配置，对应配置文件中的一个 `[[sources]]` 条目。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SourceConfig {
+    /// 代理源名称，用于日志与抓取报告。
+    pub name: String,
+    /// 请求地址模板，可使用 `{page}` 占位符表示页码。
+    pub url: String,
+    /// 页码范围 `[起始页, 结束页]`（闭区间），未配置时只请求一次。
+    #[serde(default)]
+    pub pages: Option<[u32; 2]>,
+    /// 是否启用该代理源。
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 默认代理协议，条目自身带有协议信息时以条目为准。
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// 响应内容的解析方式。
+    pub parser: SourceParserConfig,
+}
+
+/// 通用代理源的解析方式。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceParserConfig {
+    /// 纯文本列表，每行一个 `ip{separator}port`，可带 `socks5://` 等协议前缀。
+    Text {
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+    /// JSON 数据，通过 JSON Pointer 定位代理数组及其中的 ip、port 字段。
+    Json {
+        /// 代理数组的位置，空字符串表示根节点本身就是数组。
+        #[serde(default)]
+        list: String,
+        #[serde(default = "default_ip_pointer")]
+        ip: String,
+        #[serde(default = "default_port_pointer")]
+        port: String,
+        /// 协议字段的位置，可选。
+        #[serde(default)]
+        protocol: Option<String>,
+    },
+    /// 正则表达式，需包含名为 `ip` 和 `port` 的命名捕获组，可选 `protocol` 捕获组。
+    Regex { pattern: String },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_stability_half_life() -> u64 {
+    86400
+}
+
+fn default_refresh_interval() -> u64 {
+    300
+}
+
+fn default_cache_ttl() -> u64 {
+    600
+}
+
+fn default_max_concurrent() -> usize {
+    1
+}
+
+fn default_lease_ttl() -> u64 {
+    60
+}
+
+fn default_lease_max_ttl() -> u64 {
+    3600
+}
+
+fn default_max_consecutive_failures() -> u32 {
+    5
+}
+
+fn default_quarantine_secs() -> u64 {
+    1800
+}
+
+fn default_retention_days() -> u64 {
+    7
+}
+
+fn default_speed_weight() -> f64 {
+    0.4
+}
+
+fn default_success_weight() -> f64 {
+    0.3
+}
+
+fn default_stability_weight() -> f64 {
+    0.3
+}
+
+fn default_anonymity_weight() -> f64 {
+    0.2
+}
+
+fn default_speed_fast_ms() -> f64 {
+    300.0
+}
+
+fn default_speed_slow_ms() -> f64 {
+    1000.0
+}
+
+fn default_speed_max_ms() -> f64 {
+    5000.0
+}
+
+fn default_model_file() -> String {
+    "scoring_model.json".to_string()
+}
+
+fn default_gateway_strategy() -> SelectStrategy {
+    SelectStrategy::WeightedByScore
+}
+
+fn default_max_attempts() -> usize {
+    3
+}
+
+fn default_connect_timeout() -> u64 {
+    5
+}
+
+fn default_separator() -> String {
+    ":".to_string()
+}
+
+fn default_ip_pointer() -> String {
+    "/ip".to_string()
+}
+
+fn default_port_pointer() -> String {
+    "/port".to_string()
+}
+
 
 impl AppConfig {
     fn load() -> anyhow::Result<Self> {
         let config = config::Config::builder()
             .add_source(config::File::with_name("Config"))
             .build()?;
-        let config = config.try_deserialize()?;
+        let config: Self = config.try_deserialize()?;
+        config.verify.validate()?;
+        config.scoring.validate()?;
         Ok(config)
     }
 }
@@ -62,4 +604,61 @@ mod tests {
     fn test_config() {
         println!("{:#?}", APP_CONFIG.verify.semaphore);
     }
+
+    #[test]
+    fn test_scoring_validate() {
+        assert!(ScoringConfig::default().validate().is_ok());
+
+        let unbalanced = ScoringConfig { speed_weight: 0.5, ..Default::default() };
+        assert!(unbalanced.validate().is_err());
+
+        let negative = ScoringConfig { speed_weight: 1.1, success_weight: -0.1, stability_weight: 0.0, ..Default::default() };
+        assert!(negative.validate().is_err());
+
+        let unordered = ScoringConfig { speed_slow_ms: 200.0, ..Default::default() };
+        assert!(unordered.validate().is_err());
+    }
+
+    #[test]
+    fn test_verify_profiles() {
+        let mut verify = VerifyConfig {
+            semaphore: 1,
+            timeout: 5,
+            test_urls: vec!["https://a.com".into(), "https://b.com".into()],
+            targets: Vec::new(),
+            verify_level: 1,
+            echo_url: None,
+            egress_ip: None,
+            stability_half_life: default_stability_half_life(),
+            tamper_action: TamperAction::default(),
+        };
+        let names: Vec<_> = verify.profiles().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["https://a.com", "https://b.com"]);
+        assert!(verify.validate().is_ok());
+
+        verify.targets = vec![TargetProfile::new("a", "https://a.com"), TargetProfile::new("a", "https://b.com")];
+        assert_eq!(verify.profiles().len(), 2);
+        assert!(verify.validate().is_err());
+
+        verify.targets[1] = TargetProfile { body_regex: Some("[".into()), ..TargetProfile::new("b", "https://b.com") };
+        assert!(verify.validate().is_err());
+
+        verify.targets[1] = TargetProfile { body_sha256: Some("abc".into()), ..TargetProfile::new("b", "https://b.com") };
+        assert!(verify.validate().is_err());
+
+        verify.targets[1] = TargetProfile { json_equals: Some("1".into()), ..TargetProfile::new("b", "https://b.com") };
+        assert!(verify.validate().is_err());
+
+        verify.targets[1] = TargetProfile {
+            body_sha256: Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into()),
+            json_field: Some("/origin".into()),
+            json_equals: Some("1.1.1.1".into()),
+            ..TargetProfile::new("b", "https://b.com")
+        };
+        assert!(verify.validate().is_ok());
+
+        verify.targets.clear();
+        verify.test_urls.clear();
+        assert!(verify.validate().is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 回显接口的响应格式，与 httpbin 的 `/get` 兼容。
+///
+/// 用于匿名等级检测：通过代理访问回显接口，
+/// 比较目标站点看到的来源 IP 与请求头。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EchoResponse {
+    /// 目标站点看到的来源 IP，可能是以逗号分隔的多个地址。
+    pub origin: String,
+    /// 目标站点收到的请求头。
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl EchoResponse {
+    /// 忽略大小写地读取请求头。
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
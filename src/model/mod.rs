@@ -1,5 +1,9 @@
 mod proxy;
 mod app_config;
+mod echo;
+mod query;
 
 pub use proxy::*;
-pub use app_config::APP_CONFIG;
\ No newline at end of file
+pub use echo::EchoResponse;
+pub use query::{ProxyQuery, SelectStrategy, SortField, MAX_CHECKED_WITHIN};
+pub use app_config::{FailureAction, FeedbackConfig, GatewayConfig, ScoringConfig, ScoringModelKind, SourceConfig, TargetProfile, SourceParserConfig, TamperAction, APP_CONFIG};
@@ -3,6 +3,165 @@
 
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 为以小写字符串存储的枚举实现 `sqlx` 解码。
+///
+/// 数据库中统一使用文本列存储，写入时绑定 `as_str()`，
+/// 读取时通过 [`FromStr`] 解析，可同时适配 SQLite、MySQL、PostgreSQL。
+macro_rules! impl_sqlx_text_enum {
+    ($ty:ty) => {
+        impl<DB: sqlx::Database> sqlx::Type<DB> for $ty
+        where
+            String: sqlx::Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <String as sqlx::Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <String as sqlx::Type<DB>>::compatible(ty)
+            }
+        }
+
+        impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for $ty
+        where
+            String: sqlx::Decode<'r, DB>,
+        {
+            fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+                let s = <String as sqlx::Decode<'r, DB>>::decode(value)?;
+                Ok(s.parse::<$ty>()?)
+            }
+        }
+    };
+}
+
+/// 代理协议类型。
+///
+/// 数据库中以小写字符串存储（如 `"socks5"`），
+/// 验证时根据协议选择对应的连接方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// 普通 HTTP 代理（转发明文 HTTP 请求）。
+    #[default]
+    Http,
+    /// 支持 CONNECT 隧道的 HTTP 代理，可用于访问 HTTPS 站点。
+    Https,
+    /// SOCKS4 代理。
+    Socks4,
+    /// SOCKS5 代理。
+    Socks5,
+}
+
+impl Protocol {
+    /// 协议的小写字符串表示，与数据库存储格式一致。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Http => "http",
+            Protocol::Https => "https",
+            Protocol::Socks4 => "socks4",
+            Protocol::Socks5 => "socks5",
+        }
+    }
+
+    /// 是否已验证可以建立到任意目标的隧道（CONNECT 或 SOCKS 握手）。
+    ///
+    /// 普通 HTTP 代理只验证过明文请求转发，不能确定其支持 CONNECT。
+    pub fn supports_tunnel(&self) -> bool {
+        !matches!(self, Protocol::Http)
+    }
+
+    /// 构建供 `reqwest::Proxy` 使用的代理地址。
+    ///
+    /// HTTP/HTTPS 代理均通过 `http://` 连接；
+    /// SOCKS 代理使用 `socks4a://`、`socks5h://`，由代理端解析域名。
+    pub fn proxy_url(&self, ip: &str, port: &str) -> String {
+        let scheme = match self {
+            Protocol::Http | Protocol::Https => "http",
+            Protocol::Socks4 => "socks4a",
+            Protocol::Socks5 => "socks5h",
+        };
+        format!("{}://{}:{}", scheme, ip, port)
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    /// 解析协议字符串，忽略大小写，兼容 `socks4a`、`socks5h` 等写法。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "http" => Ok(Protocol::Http),
+            "https" => Ok(Protocol::Https),
+            "socks4" | "socks4a" => Ok(Protocol::Socks4),
+            "socks5" | "socks5h" | "socks" => Ok(Protocol::Socks5),
+            other => Err(format!("不支持的代理协议：{}", other)),
+        }
+    }
+}
+
+impl_sqlx_text_enum!(Protocol);
+
+/// 代理匿名等级，通过回显接口观察目标站点看到的来源 IP 与转发头判定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anonymity {
+    /// 透明代理：目标站点可以看到真实出口 IP。
+    Transparent,
+    /// 普通匿名：隐藏了真实 IP，但带有 `Via`、`X-Forwarded-For` 等代理特征头。
+    Anonymous,
+    /// 高匿代理：既不泄露真实 IP，也没有代理特征头。
+    Elite,
+}
+
+impl Anonymity {
+    /// 匿名等级的小写字符串表示，与数据库存储格式一致。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Anonymity::Transparent => "transparent",
+            Anonymity::Anonymous => "anonymous",
+            Anonymity::Elite => "elite",
+        }
+    }
+
+    /// 匿名等级对应的评分，0.0~1.0。
+    pub fn score(&self) -> f64 {
+        match self {
+            Anonymity::Transparent => 0.1,
+            Anonymity::Anonymous => 0.6,
+            Anonymity::Elite => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Anonymity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Anonymity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "transparent" => Ok(Anonymity::Transparent),
+            "anonymous" => Ok(Anonymity::Anonymous),
+            "elite" => Ok(Anonymity::Elite),
+            other => Err(format!("未知的匿名等级：{}", other)),
+        }
+    }
+}
+
+impl_sqlx_text_enum!(Anonymity);
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Proxy {
@@ -12,6 +171,9 @@ pub struct Proxy {
     /// 代理的端口号（字符串形式，便于处理）。
     pub port: String,
 
+    /// 代理协议（HTTP、HTTPS、SOCKS4、SOCKS5）。
+    pub protocol: Protocol,
+
     /// 平均响应速度（单位：秒），从多个测试请求中得出。
     ///
     /// 若未进行测速，该字段为 `None`。
@@ -27,7 +189,18 @@ pub struct Proxy {
     /// 分值越高表示响应越稳定。若未测试，该字段为 `None`。
     pub stability: Option<f64>,
 
-    /// 综合评分，基于成功率、速度和稳定性计算得出。
+    /// 匿名等级，通过回显接口检测得出。
+    ///
+    /// 未配置回显接口或检测失败时为 `None`。
+    pub anonymity: Option<Anonymity>,
+
+    /// 最近一次验证时是否有目标站点返回了被篡改的内容（劫持页、注入广告等）。
+    ///
+    /// 被标记的代理默认不会被 `/proxy`、`/proxy/list` 与代理网关选用。
+    #[serde(default)]
+    pub tampered: bool,
+
+    /// 综合评分，基于成功率、速度、稳定性和匿名等级计算得出。
     ///
     /// 用于排序和筛选高质量代理。若尚未评分，则为 `None`。
     pub score: Option<f64>,
@@ -43,9 +216,12 @@ impl Proxy {
         Self {
             ip,
             port,
+            protocol: Protocol::default(),
             speed: None,
             success_rate: None,
             stability: None,
+            anonymity: None,
+            tampered: false,
             score: None,
             last_checked: None,
         }
@@ -55,6 +231,7 @@ impl Proxy {
         ProxyBasic {
             ip: self.ip.clone(),
             port: self.port.clone(),
+            protocol: self.protocol,
         }
     }
 
@@ -63,6 +240,8 @@ impl Proxy {
             speed: self.speed,
             success_rate: self.success_rate,
             stability: self.stability,
+            anonymity: self.anonymity,
+            tampered: self.tampered,
             score: self.score,
             last_checked: self.last_checked,
         }
@@ -72,9 +251,12 @@ impl Proxy {
         Self {
             ip: basic.ip,
             port: basic.port,
+            protocol: basic.protocol,
             speed: result.speed,
             success_rate: result.success_rate,
             stability: result.stability,
+            anonymity: result.anonymity,
+            tampered: result.tampered,
             score: result.score,
             last_checked: result.last_checked,
         }
@@ -85,11 +267,25 @@ impl Proxy {
 pub struct ProxyBasic {
     pub ip: String,
     pub port: String,
+    /// 代理协议，抓取源未提供时默认为 HTTP。
+    #[serde(default)]
+    pub protocol: Protocol,
 }
 
 impl ProxyBasic {
     pub fn new(ip: &str, port: &str) -> Self {
-        ProxyBasic { ip: ip.to_string(), port: port.to_string() }
+        ProxyBasic { ip: ip.to_string(), port: port.to_string(), protocol: Protocol::default() }
+    }
+
+    /// 指定代理协议。
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// 构建供 `reqwest::Proxy` 使用的代理地址。
+    pub fn proxy_url(&self) -> String {
+        self.protocol.proxy_url(&self.ip, &self.port)
     }
 
     pub fn is_none_empty(&self) -> bool {
@@ -102,6 +298,9 @@ pub struct ProxyCheckResult {
     pub speed: Option<f64>,
     pub success_rate: Option<f64>,
     pub stability: Option<f64>,
+    pub anonymity: Option<Anonymity>,
+    #[serde(default)]
+    pub tampered: bool,
     pub score: Option<f64>,
     pub last_checked: Option<NaiveDateTime>,
 }
@@ -116,8 +315,153 @@ impl Default for ProxyCheckResult {
             speed: None,
             success_rate: None,
             stability: None,
+            anonymity: None,
+            tampered: false,
             score: None,
             last_checked: None,
         }
     }
 }
+
+/// 代理检测记录的来源。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckSource {
+    /// 验证任务的测速请求。
+    Verify,
+    /// 客户端通过 `/proxy/feedback` 上报的使用结果。
+    Feedback,
+}
+
+impl CheckSource {
+    /// 小写字符串表示，与数据库存储格式一致。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckSource::Verify => "verify",
+            CheckSource::Feedback => "feedback",
+        }
+    }
+}
+
+impl FromStr for CheckSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "verify" => Ok(CheckSource::Verify),
+            "feedback" => Ok(CheckSource::Feedback),
+            other => Err(format!("未知的检测来源：{}", other)),
+        }
+    }
+}
+
+impl_sqlx_text_enum!(CheckSource);
+
+/// 检测失败的原因分类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckErrorKind {
+    /// 请求超时。
+    Timeout,
+    /// 无法连接代理或目标。
+    Connect,
+    /// 目标返回的状态码不符合预期（默认要求 2xx）。
+    HttpStatus,
+    /// 响应内容与目标站点声明的哈希、子串、正则或 JSON 字段不符，疑似被劫持或篡改。
+    Tampered,
+    /// 响应内容超过目标站点允许读取的大小，无法完成内容校验，属于目标配置问题而非篡改。
+    BodyTooLarge,
+    /// 其他请求错误（协议错误、响应读取失败等）。
+    Request,
+}
+
+impl CheckErrorKind {
+    /// 小写字符串表示，与数据库存储格式一致。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckErrorKind::Timeout => "timeout",
+            CheckErrorKind::Connect => "connect",
+            CheckErrorKind::HttpStatus => "http_status",
+            CheckErrorKind::Tampered => "tampered",
+            CheckErrorKind::BodyTooLarge => "body_too_large",
+            CheckErrorKind::Request => "request",
+        }
+    }
+}
+
+impl FromStr for CheckErrorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "timeout" => Ok(CheckErrorKind::Timeout),
+            "connect" => Ok(CheckErrorKind::Connect),
+            "http_status" => Ok(CheckErrorKind::HttpStatus),
+            "tampered" => Ok(CheckErrorKind::Tampered),
+            "body_too_large" => Ok(CheckErrorKind::BodyTooLarge),
+            "request" => Ok(CheckErrorKind::Request),
+            other => Err(format!("未知的失败类型：{}", other)),
+        }
+    }
+}
+
+impl_sqlx_text_enum!(CheckErrorKind);
+
+/// 一次代理检测的历史记录，对应检测历史表（`{代理表名}_checks`）中的一行。
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProxyCheck {
+    pub ip: String,
+    pub port: String,
+    /// 检测时间（UTC）。
+    pub checked_at: NaiveDateTime,
+    /// 记录来源。
+    pub source: CheckSource,
+    /// 检测的目标地址（反馈记录为客户端上报的目标域名，可能为空）。
+    pub target: String,
+    /// 本次检测是否成功。
+    pub success: bool,
+    /// 请求耗时（单位：秒），失败时通常为 `None`。
+    pub latency: Option<f64>,
+    /// 目标返回的 HTTP 状态码，未收到响应时为 `None`。
+    pub status_code: Option<i32>,
+    /// 失败原因分类，成功时为 `None`。
+    pub error_kind: Option<CheckErrorKind>,
+}
+
+/// 代理在单个目标站点上最近一次验证的结果，对应分站点结果表（`{代理表名}_sites`）中的一行。
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProxySiteResult {
+    pub ip: String,
+    pub port: String,
+    /// 目标站点名称，见 `[[verify.targets]]`。
+    pub site: String,
+    /// 该站点上成功请求的平均耗时（单位：秒），全部失败时为 `None`。
+    pub speed: Option<f64>,
+    /// 该站点上的成功率（0.0 - 1.0），大于 0 即视为可用。
+    pub success_rate: f64,
+    /// 该站点上的综合评分。
+    pub score: f64,
+    /// 验证时间（UTC）。
+    pub last_checked: NaiveDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_parse() {
+        assert_eq!("HTTP".parse::<Protocol>().unwrap(), Protocol::Http);
+        assert_eq!("socks5h".parse::<Protocol>().unwrap(), Protocol::Socks5);
+        assert!(!Protocol::Http.supports_tunnel() && Protocol::Https.supports_tunnel());
+        assert_eq!("socks4a".parse::<Protocol>().unwrap(), Protocol::Socks4);
+        assert!("ftp".parse::<Protocol>().is_err());
+    }
+
+    #[test]
+    fn test_proxy_url() {
+        let basic = ProxyBasic::new("1.2.3.4", "1080").with_protocol(Protocol::Socks5);
+        assert_eq!(basic.proxy_url(), "socks5h://1.2.3.4:1080");
+        assert_eq!(ProxyBasic::new("1.2.3.4", "80").proxy_url(), "http://1.2.3.4:80");
+    }
+}
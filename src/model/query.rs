@@ -0,0 +1,148 @@
+//! 代理查询条件：过滤、排序与分页，对应 `/proxy`、`/proxy/list` 的查询参数。
+//!
+//! 查询条件由各存储后端转换为 SQL 执行，而不是取出全部代理后在内存中过滤；
+//! `/proxy` 接口优先在代理池缓存的快照上过滤，只有按站点过滤时才查询存储。
+
+use crate::model::Protocol;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// `checked_within` 的上限（秒），约 100 年，更大的值按上限处理。
+pub const MAX_CHECKED_WITHIN: u64 = 100 * 365 * 24 * 3600;
+
+/// 代理查询条件，所有条件均可选，未设置的条件不参与过滤。
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProxyQuery {
+    /// 最低综合评分。
+    pub min_score: Option<f64>,
+    /// 最大平均响应时间（秒）。
+    pub max_speed: Option<f64>,
+    /// 最低成功率（0.0 - 1.0）。
+    pub min_success_rate: Option<f64>,
+    /// 只返回最近 N 秒内检测过的代理，最大为 [`MAX_CHECKED_WITHIN`]。
+    pub checked_within: Option<u64>,
+    /// 代理协议。
+    pub protocol: Option<Protocol>,
+    /// 只返回在指定目标站点（`[[verify.targets]]` 的名称）上验证通过的代理。
+    pub site: Option<String>,
+    /// 是否包含被标记为内容篡改的代理，默认不包含。
+    #[serde(default)]
+    pub include_tampered: bool,
+    /// 最多返回的条数。
+    pub limit: Option<u32>,
+    /// 跳过的条数。
+    pub offset: Option<u32>,
+    /// 排序方式，默认按评分从高到低。
+    #[serde(default)]
+    pub sort: ProxySort,
+    /// 取单个代理（`/proxy`）时的选择策略，未指定时使用配置的默认策略。
+    pub strategy: Option<SelectStrategy>,
+}
+
+/// 从候选代理中挑选一个代理的策略。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectStrategy {
+    /// 均匀随机。
+    #[default]
+    Random,
+    /// 按综合评分加权随机，评分越高越容易被选中。
+    #[serde(alias = "score_weighted")]
+    WeightedByScore,
+    /// 依次轮询。
+    RoundRobin,
+    /// 选择最久未被使用的代理。
+    LeastRecentlyUsed,
+    /// 总是选择评分最高的代理。
+    Best,
+}
+
+/// 可排序的字段。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Score,
+    Speed,
+    SuccessRate,
+    LastChecked,
+    /// 随机顺序。
+    Random,
+}
+
+impl SortField {
+    /// 对应的数据库列名，随机排序时为 `None`。
+    pub fn column(&self) -> Option<&'static str> {
+        match self {
+            SortField::Score => Some("score"),
+            SortField::Speed => Some("speed"),
+            SortField::SuccessRate => Some("success_rate"),
+            SortField::LastChecked => Some("last_checked"),
+            SortField::Random => None,
+        }
+    }
+}
+
+/// 排序方式，字符串形式为字段名，前缀 `-` 表示降序，如 `-score`、`speed`、`random`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ProxySort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for ProxySort {
+    fn default() -> Self {
+        Self { field: SortField::Score, descending: true }
+    }
+}
+
+impl FromStr for ProxySort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, s),
+        };
+        let field = match name.to_ascii_lowercase().as_str() {
+            "score" => SortField::Score,
+            "speed" => SortField::Speed,
+            "success_rate" => SortField::SuccessRate,
+            "last_checked" => SortField::LastChecked,
+            "random" => SortField::Random,
+            _ => return Err(format!("不支持的排序字段：{}", s)),
+        };
+        Ok(Self { field, descending })
+    }
+}
+
+impl TryFrom<String> for ProxySort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ProxySort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.field.column().unwrap_or("random");
+        if self.descending { write!(f, "-{}", name) } else { write!(f, "{}", name) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sort() {
+        assert_eq!("-score".parse::<ProxySort>().unwrap(), ProxySort::default());
+        let speed: ProxySort = "speed".parse().unwrap();
+        assert_eq!((speed.field, speed.descending), (SortField::Speed, false));
+        assert_eq!("random".parse::<ProxySort>().unwrap().field, SortField::Random);
+        assert!("ip".parse::<ProxySort>().is_err());
+        assert_eq!("-success_rate".parse::<ProxySort>().unwrap().to_string(), "-success_rate");
+    }
+}
@@ -0,0 +1,263 @@
+//! # feedback 模块
+//!
+//! 客户端使用结果反馈：爬虫等客户端通过 `POST /proxy/feedback` 上报代理在目标站点上的实际表现，
+//! 据此更新代理的滑动成功率、速度与综合评分。
+//!
+//! 同一代理连续失败达到 `[feedback] max_consecutive_failures` 次后，
+//! 按配置将其从存储中删除，或在 `quarantine_secs` 时间内隔离（不再被分配）。
+//!
+//! 只跟踪存储中存在的代理：未知代理的反馈直接拒绝，代理被删除或隔离期结束后即清除其状态。
+
+use crate::db::get_storage;
+use crate::db::manager::ProxyStorage;
+use crate::model::{CheckSource, FailureAction, FeedbackConfig, Proxy, ProxyBasic, ProxyCheck, APP_CONFIG};
+use crate::service::{history, selector, stats};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// 全局反馈跟踪器。
+pub static FEEDBACK: Lazy<FeedbackTracker> = Lazy::new(|| FeedbackTracker::new(&APP_CONFIG.feedback));
+
+/// 客户端上报的一次代理使用结果。
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedbackReport {
+    pub ip: String,
+    pub port: String,
+    /// 本次使用是否成功。
+    pub ok: bool,
+    /// 本次请求耗时（毫秒），可选。
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// 访问的目标域名，记录到检测历史中，可选。
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+impl FeedbackReport {
+    /// 转换为检测历史记录。
+    fn check(&self, latency: Option<f64>) -> ProxyCheck {
+        ProxyCheck {
+            ip: self.ip.clone(),
+            port: self.port.clone(),
+            checked_at: chrono::Utc::now().naive_utc(),
+            source: CheckSource::Feedback,
+            target: self.target.clone().unwrap_or_default(),
+            success: self.ok,
+            latency,
+            status_code: None,
+            error_kind: None,
+        }
+    }
+}
+
+/// 处理反馈后的结果。
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackResult {
+    /// 更新后的代理；本次反馈触发删除时为 `None`。
+    pub proxy: Option<Proxy>,
+    /// 当前连续失败次数。
+    pub consecutive_failures: u32,
+    /// 本次反馈触发的处理动作。
+    pub action: Option<FailureAction>,
+}
+
+/// 单个代理的反馈状态。
+#[derive(Debug, Default)]
+struct FeedbackState {
+    consecutive_failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+impl FeedbackState {
+    /// 没有连续失败且不在隔离期的状态无需保留。
+    fn is_idle(&self, now: Instant) -> bool {
+        self.consecutive_failures == 0 && self.quarantined_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// 代理从存储中删除后清除其反馈状态，跟踪器尚未初始化时无需处理。
+pub fn forget_removed(removed: &[ProxyBasic]) {
+    if let Some(tracker) = Lazy::get(&FEEDBACK) {
+        tracker.forget(removed);
+    }
+}
+
+/// 反馈跟踪器：记录各代理的连续失败次数与隔离状态。
+pub struct FeedbackTracker {
+    max_failures: u32,
+    action: FailureAction,
+    quarantine: Duration,
+    states: Mutex<HashMap<(String, String), FeedbackState>>,
+}
+
+impl FeedbackTracker {
+    pub fn new(config: &FeedbackConfig) -> Self {
+        Self {
+            max_failures: config.max_consecutive_failures,
+            action: config.action,
+            quarantine: Duration::from_secs(config.quarantine_secs),
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), FeedbackState>> {
+        self.states.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录一次结果，返回当前连续失败次数以及需要触发的处理动作。
+    ///
+    /// 成功时清零计数；失败次数达到上限时触发动作并清零计数，`max_failures` 为 0 时从不触发。
+    fn track(&self, ip: &str, port: &str, ok: bool) -> (u32, Option<FailureAction>) {
+        let mut states = self.lock();
+        let key = (ip.to_string(), port.to_string());
+        let state = states.entry(key.clone()).or_default();
+        if ok {
+            state.consecutive_failures = 0;
+            if state.is_idle(Instant::now()) {
+                states.remove(&key);
+            }
+            return (0, None);
+        }
+
+        state.consecutive_failures += 1;
+        let failures = state.consecutive_failures;
+        if self.max_failures == 0 || failures < self.max_failures {
+            return (failures, None);
+        }
+
+        state.consecutive_failures = 0;
+        match self.action {
+            FailureAction::Quarantine => state.quarantined_until = Some(Instant::now() + self.quarantine),
+            FailureAction::Remove => {
+                states.remove(&key);
+            }
+        }
+        (failures, Some(self.action))
+    }
+
+    /// 代理是否处于隔离期，隔离期已结束的状态顺便清除。
+    pub fn is_quarantined(&self, proxy: &Proxy) -> bool {
+        let mut states = self.lock();
+        let key = (proxy.ip.clone(), proxy.port.clone());
+        let Some(state) = states.get_mut(&key) else { return false };
+        let now = Instant::now();
+        if state.quarantined_until.is_some_and(|until| until > now) {
+            return true;
+        }
+        state.quarantined_until = None;
+        if state.is_idle(now) {
+            states.remove(&key);
+        }
+        false
+    }
+
+    /// 清除已删除代理的反馈状态。
+    pub fn forget(&self, removed: &[ProxyBasic]) {
+        let mut states = self.lock();
+        for proxy in removed {
+            states.remove(&(proxy.ip.clone(), proxy.port.clone()));
+        }
+    }
+
+    /// 过滤掉处于隔离期的代理。
+    pub fn available<'a>(&self, candidates: &[&'a Proxy]) -> Vec<&'a Proxy> {
+        candidates.iter().copied().filter(|p| !self.is_quarantined(p)).collect()
+    }
+
+    /// 处理一次客户端反馈：更新代理统计数据，必要时删除或隔离代理。
+    ///
+    /// # 返回
+    /// 代理不在存储中时返回 `None`，不记录任何状态。
+    pub async fn submit(&self, report: &FeedbackReport) -> Result<Option<FeedbackResult>> {
+        let basic = ProxyBasic::new(&report.ip, &report.port);
+        let latency = report.latency_ms.map(|ms| ms as f64 / 1000.0);
+        let Some(updated) = stats::record_outcome(&basic, report.ok, latency).await? else {
+            return Ok(None);
+        };
+        let mut proxy = Some(updated);
+        history::record(get_storage(), &[report.check(latency)]).await;
+
+        let target = report.target.as_deref().unwrap_or("-");
+        let (consecutive_failures, action) = self.track(&report.ip, &report.port, report.ok);
+        match action {
+            Some(FailureAction::Remove) => {
+                warn!("🗑️ 代理 {}:{} 连续 {} 次反馈失败（{}），已删除", report.ip, report.port, consecutive_failures, target);
+                get_storage().remove_proxy(&report.ip, &report.port).await?;
+                selector::forget_removed(std::slice::from_ref(&basic));
+                self.forget(&[basic]);
+                proxy = None;
+            }
+            Some(FailureAction::Quarantine) => {
+                warn!("🚧 代理 {}:{} 连续 {} 次反馈失败（{}），隔离 {} 秒", report.ip, report.port, consecutive_failures, target, self.quarantine.as_secs());
+            }
+            None => info!("📝 收到代理 {}:{} 的反馈（{}）：{}", report.ip, report.port, target, if report.ok { "成功" } else { "失败" }),
+        }
+
+        Ok(Some(FeedbackResult { proxy, consecutive_failures, action }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(action: FailureAction) -> FeedbackTracker {
+        FeedbackTracker::new(&FeedbackConfig { max_consecutive_failures: 3, action, quarantine_secs: 60 })
+    }
+
+    #[test]
+    fn test_track_quarantine() {
+        let tracker = tracker(FailureAction::Quarantine);
+        let proxy = Proxy::new("127.0.0.1".into(), "8080".into());
+
+        assert_eq!(tracker.track("127.0.0.1", "8080", false), (1, None));
+        assert_eq!(tracker.track("127.0.0.1", "8080", false), (2, None));
+        assert_eq!(tracker.track("127.0.0.1", "8080", true), (0, None));
+        assert!(!tracker.is_quarantined(&proxy));
+
+        for _ in 0..2 {
+            tracker.track("127.0.0.1", "8080", false);
+        }
+        assert_eq!(tracker.track("127.0.0.1", "8080", false), (3, Some(FailureAction::Quarantine)));
+        assert!(tracker.is_quarantined(&proxy));
+        assert!(tracker.available(&[&proxy]).is_empty());
+        assert!(!tracker.is_quarantined(&Proxy::new("127.0.0.1".into(), "8081".into())));
+
+        tracker.forget(&[proxy.basic()]);
+        assert!(!tracker.is_quarantined(&proxy));
+        assert!(tracker.lock().is_empty());
+    }
+
+    #[test]
+    fn test_idle_states_evicted() {
+        let tracker = FeedbackTracker::new(&FeedbackConfig {
+            max_consecutive_failures: 1,
+            action: FailureAction::Quarantine,
+            quarantine_secs: 0,
+        });
+        let proxy = Proxy::new("127.0.0.1".into(), "8080".into());
+
+        tracker.track("127.0.0.1", "8080", false);
+        tracker.track("127.0.0.1", "8081", false);
+        tracker.track("127.0.0.1", "8081", true);
+        assert_eq!(tracker.lock().len(), 1);
+        // 隔离期结束后查询时清除
+        assert!(!tracker.is_quarantined(&proxy));
+        assert!(tracker.lock().is_empty());
+    }
+
+    #[test]
+    fn test_track_remove() {
+        let tracker = tracker(FailureAction::Remove);
+        for _ in 0..2 {
+            tracker.track("127.0.0.1", "8080", false);
+        }
+        assert_eq!(tracker.track("127.0.0.1", "8080", false), (3, Some(FailureAction::Remove)));
+        assert_eq!(tracker.track("127.0.0.1", "8080", false), (1, None));
+    }
+}
@@ -0,0 +1,74 @@
+//! # history 模块
+//!
+//! 代理检测历史：验证任务的每次测速请求与客户端反馈都会写入检测历史表（`{代理表名}_checks`），
+//! 便于查看代理质量的变化趋势以及被下线的原因。
+//!
+//! 超过 `[history] retention_days` 天的记录由后台任务定期清理。
+
+use crate::db::get_storage;
+use crate::db::manager::ProxyStorage;
+use crate::model::{ProxyCheck, APP_CONFIG};
+use anyhow::Result;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 过期记录的清理间隔。
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 写入检测记录，失败时只输出警告，不影响验证流程。
+pub async fn record(storage: &dyn ProxyStorage, checks: &[ProxyCheck]) {
+    if let Err(e) = storage.insert_checks(checks).await {
+        warn!("写入代理检测记录失败：{}", e);
+    }
+}
+
+/// 计算保留期的起始时间，早于该时间的记录将被清理；保留天数为 0 或超出可表示的时间范围时不清理。
+fn retention_cutoff(retention_days: u64, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if retention_days == 0 {
+        return None;
+    }
+    let retention = TimeDelta::try_days(i64::try_from(retention_days).ok()?)?;
+    now.checked_sub_signed(retention)
+}
+
+/// 清理超出保留期的检测记录，返回删除的条数。
+pub async fn prune() -> Result<u64> {
+    let Some(before) = retention_cutoff(APP_CONFIG.history.retention_days, Utc::now().naive_utc()) else {
+        return Ok(0);
+    };
+    get_storage().prune_checks(before).await
+}
+
+/// 启动后台定期清理，保留天数为 0 时不启动。
+pub fn start_prune() {
+    if APP_CONFIG.history.retention_days == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match prune().await {
+                Ok(0) => {}
+                Ok(count) => info!("🧹 已清理 {} 条过期的代理检测记录", count),
+                Err(e) => warn!("清理代理检测记录失败：{}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_cutoff() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(retention_cutoff(0, now), None);
+        assert_eq!(retention_cutoff(7, now), Some(now - TimeDelta::days(7)));
+        assert_eq!(retention_cutoff(u64::MAX, now), None);
+        assert_eq!(retention_cutoff(i64::MAX as u64, now), None);
+    }
+}
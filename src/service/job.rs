@@ -0,0 +1,317 @@
+//! # job 模块
+//!
+//! 后台任务模型：代理采集与数据库复检都以后台任务的形式运行，
+//! 提交后立即返回任务 ID，调用方通过 [`JobManager::get`] 查询状态与进度，
+//! 也可以通过 [`JobManager::cancel`] 取消尚未结束的任务。
+//!
+//! 同一类型的任务同时只会运行一个，重复提交时返回正在运行的任务 ID。
+
+use crate::fetcher;
+use crate::fetcher::source::SourceReport;
+use crate::service::verifier::{self, ProgressSnapshot, VerifyProgress};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+/// 最多保留的已结束任务数量，超出后按 ID 从小到大清理。
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// 全局后台任务管理器。
+pub static JOBS: Lazy<JobManager> = Lazy::new(JobManager::new);
+
+/// 后台任务类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// 代理采集：抓取所有代理源并批量验证入库。
+    Collection,
+    /// 数据库复检：重新验证数据库中已有的代理。
+    Verification,
+}
+
+/// 后台任务状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// 正在抓取代理源（仅采集任务）。
+    Fetching,
+    /// 正在验证代理。
+    Verifying,
+    /// 执行成功。
+    Succeeded,
+    /// 执行失败，详见 `error`。
+    Failed,
+    /// 已被取消。
+    Cancelled,
+}
+
+impl JobState {
+    /// 任务是否已经结束。
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+/// 后台任务的对外视图，用于接口返回。
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSnapshot {
+    pub id: u64,
+    pub kind: JobKind,
+    pub state: JobState,
+    /// 验证进度：总数、已验证数、成功数。
+    pub progress: ProgressSnapshot,
+    /// 各代理源的抓取报告（仅采集任务，抓取阶段结束后可用）。
+    pub sources: Option<Vec<SourceReport>>,
+    /// 失败时的错误信息。
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// 任务的内部记录。
+struct JobEntry {
+    kind: JobKind,
+    state: JobState,
+    progress: Arc<VerifyProgress>,
+    sources: Option<Vec<SourceReport>>,
+    error: Option<String>,
+    created_at: NaiveDateTime,
+    finished_at: Option<NaiveDateTime>,
+    done: watch::Receiver<bool>,
+}
+
+impl JobEntry {
+    fn snapshot(&self, id: u64) -> JobSnapshot {
+        JobSnapshot {
+            id,
+            kind: self.kind,
+            state: self.state,
+            progress: self.progress.snapshot(),
+            sources: self.sources.clone(),
+            error: self.error.clone(),
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+        }
+    }
+}
+
+/// 后台任务管理器，负责任务的提交、执行、查询与取消。
+pub struct JobManager {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, JobEntry>>,
+}
+
+impl JobManager {
+    fn new() -> Self {
+        Self { next_id: AtomicU64::new(1), jobs: Mutex::new(HashMap::new()) }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, JobEntry>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 提交一个后台任务并立即返回任务 ID。
+    ///
+    /// 若同类型任务仍在运行，则不会重复启动，直接返回该任务的 ID。
+    pub fn submit(&'static self, kind: JobKind) -> u64 {
+        let mut jobs = self.lock();
+        if let Some((&id, _)) = jobs.iter().find(|(_, job)| job.kind == kind && !job.state.is_finished()) {
+            info!("任务 #{} ({:?}) 仍在运行，复用该任务", id, kind);
+            return id;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let progress = Arc::new(VerifyProgress::default());
+        let (done_tx, done_rx) = watch::channel(false);
+        let state = match kind {
+            JobKind::Collection => JobState::Fetching,
+            JobKind::Verification => JobState::Verifying,
+        };
+
+        jobs.insert(id, JobEntry {
+            kind,
+            state,
+            progress: Arc::clone(&progress),
+            sources: None,
+            error: None,
+            created_at: Utc::now().naive_utc(),
+            finished_at: None,
+            done: done_rx,
+        });
+        Self::prune(&mut jobs);
+        drop(jobs);
+
+        info!("📥 已提交后台任务 #{} ({:?})", id, kind);
+        tokio::spawn(async move {
+            // 任务主体在独立的 task 中运行，panic 时也能记录最终状态
+            let result = tokio::spawn(self.execute(id, kind, progress.clone()))
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("任务异常终止：{}", e)));
+            self.finish(id, result, &progress);
+            let _ = done_tx.send(true);
+        });
+
+        id
+    }
+
+    /// 查询任务状态。
+    pub fn get(&self, id: u64) -> Option<JobSnapshot> {
+        self.lock().get(&id).map(|job| job.snapshot(id))
+    }
+
+    /// 列出所有任务，按 ID 倒序（最新的在前）。
+    pub fn list(&self) -> Vec<JobSnapshot> {
+        let mut list: Vec<_> = self.lock().iter().map(|(&id, job)| job.snapshot(id)).collect();
+        list.sort_by_key(|job| std::cmp::Reverse(job.id));
+        list
+    }
+
+    /// 请求取消任务，返回取消请求发出后的任务状态。
+    ///
+    /// # 错误
+    /// 任务不存在或已经结束时返回错误。
+    pub fn cancel(&self, id: u64) -> Result<JobSnapshot> {
+        let jobs = self.lock();
+        let job = jobs.get(&id).ok_or_else(|| anyhow!("任务 #{} 不存在", id))?;
+        if job.state.is_finished() {
+            return Err(anyhow!("任务 #{} 已结束，无法取消", id));
+        }
+        job.progress.cancel();
+        info!("⏹️ 已请求取消任务 #{}", id);
+        Ok(job.snapshot(id))
+    }
+
+    /// 等待任务结束并返回最终状态，任务不存在时返回 `None`。
+    pub async fn wait(&self, id: u64) -> Option<JobSnapshot> {
+        let mut done = self.lock().get(&id)?.done.clone();
+        let _ = done.wait_for(|finished| *finished).await;
+        self.get(id)
+    }
+
+    /// 执行任务主体，返回验证通过的代理数量。
+    async fn execute(&self, id: u64, kind: JobKind, progress: Arc<VerifyProgress>) -> Result<usize> {
+        match kind {
+            JobKind::Collection => {
+                let report = fetcher::fetch_all_sources().await;
+                let failed = report.sources.iter().filter(|s| !s.is_ok()).count();
+                info!("任务 #{} 抓取到总共 {} 条代理，{} 个代理源失败", id, report.proxies.len(), failed);
+                self.update(id, |job| {
+                    job.sources = Some(report.sources);
+                    job.state = JobState::Verifying;
+                });
+                if progress.is_cancelled() {
+                    return Ok(0);
+                }
+                verifier::verify_all(report.proxies, progress).await
+            }
+            JobKind::Verification => verifier::verify_database(progress).await,
+        }
+    }
+
+    /// 记录任务的最终状态。
+    fn finish(&self, id: u64, result: Result<usize>, progress: &VerifyProgress) {
+        let state = match &result {
+            _ if progress.is_cancelled() => JobState::Cancelled,
+            Ok(_) => JobState::Succeeded,
+            Err(_) => JobState::Failed,
+        };
+        match &result {
+            Ok(ok) => info!("🏁 后台任务 #{} 结束：{:?}，验证通过 {} 条", id, state, ok),
+            Err(e) => error!("❌ 后台任务 #{} 执行失败：{}", id, e),
+        }
+
+        self.update(id, |job| {
+            job.state = state;
+            job.error = result.err().map(|e| e.to_string());
+            job.finished_at = Some(Utc::now().naive_utc());
+        });
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut JobEntry)) {
+        if let Some(job) = self.lock().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// 已结束的任务超过上限时，清理最早的记录。
+    fn prune(jobs: &mut HashMap<u64, JobEntry>) {
+        let mut finished: Vec<u64> = jobs.iter().filter(|(_, j)| j.state.is_finished()).map(|(&id, _)| id).collect();
+        if finished.len() > MAX_FINISHED_JOBS {
+            finished.sort_unstable();
+            for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+                jobs.remove(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(manager: &JobManager, kind: JobKind, state: JobState) -> u64 {
+        let id = manager.next_id.fetch_add(1, Ordering::SeqCst);
+        let (_, done) = watch::channel(state.is_finished());
+        manager.lock().insert(id, JobEntry {
+            kind,
+            state,
+            progress: Arc::new(VerifyProgress::default()),
+            sources: None,
+            error: None,
+            created_at: Utc::now().naive_utc(),
+            finished_at: None,
+            done,
+        });
+        id
+    }
+
+    #[test]
+    fn test_cancel() {
+        let manager = JobManager::new();
+        let running = insert(&manager, JobKind::Verification, JobState::Verifying);
+        let finished = insert(&manager, JobKind::Collection, JobState::Succeeded);
+
+        assert!(manager.cancel(running).is_ok());
+        assert!(manager.lock()[&running].progress.is_cancelled());
+        assert!(manager.cancel(finished).is_err());
+        assert!(manager.cancel(999).is_err());
+
+        let progress = Arc::clone(&manager.lock()[&running].progress);
+        manager.finish(running, Ok(3), &progress);
+        assert_eq!(manager.get(running).unwrap().state, JobState::Cancelled);
+    }
+
+    #[test]
+    fn test_finish_failed() {
+        let manager = JobManager::new();
+        let id = insert(&manager, JobKind::Collection, JobState::Verifying);
+        manager.finish(id, Err(anyhow!("数据库不可用")), &VerifyProgress::default());
+
+        let job = manager.get(id).unwrap();
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(job.error.as_deref(), Some("数据库不可用"));
+        assert!(job.finished_at.is_some());
+    }
+
+    #[test]
+    fn test_list_and_prune() {
+        let manager = JobManager::new();
+        let running = insert(&manager, JobKind::Verification, JobState::Verifying);
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            insert(&manager, JobKind::Collection, JobState::Succeeded);
+        }
+        JobManager::prune(&mut manager.lock());
+
+        let list = manager.list();
+        assert_eq!(list.len(), MAX_FINISHED_JOBS + 1);
+        assert!(list.windows(2).all(|w| w[0].id > w[1].id));
+        assert!(manager.get(running).is_some());
+        assert!(manager.get(running + 1).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+//! # lease 模块
+//!
+//! 代理租约：客户端通过 `POST /proxy/lease` 独占借出一个代理，
+//! 在主动归还（`POST /proxy/release`）或租约到期之前，同一代理不会再分配给其他客户端。
+//!
+//! 每个代理允许同时存在的租约数由 `[lease] max_concurrent` 控制，默认为 1，即完全独占。
+//! `/proxy` 与代理网关同样不会分配租约数已满的代理。
+
+use crate::model::{Proxy, ProxyBasic, SelectStrategy, APP_CONFIG};
+use crate::service::selector::Selector;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+/// 全局租约管理器。
+pub static LEASES: Lazy<LeaseManager> = Lazy::new(|| LeaseManager::new(APP_CONFIG.lease.max_concurrent));
+
+/// 一份代理租约。
+#[derive(Debug, Clone, Serialize)]
+pub struct Lease {
+    /// 租约 ID，归还时使用。
+    pub lease_id: String,
+    /// 借出的代理。
+    pub proxy: ProxyBasic,
+    /// 租约到期时间（UTC），到期后自动失效。
+    pub expires_at: NaiveDateTime,
+}
+
+impl Lease {
+    fn key(&self) -> (String, String) {
+        (self.proxy.ip.clone(), self.proxy.port.clone())
+    }
+}
+
+/// 当前有效的租约，以及按代理统计的租约数和按到期时间排序的索引。
+#[derive(Default)]
+struct Leases {
+    by_id: HashMap<String, Lease>,
+    per_proxy: HashMap<(String, String), usize>,
+    expiry: BTreeSet<(NaiveDateTime, String)>,
+}
+
+impl Leases {
+    fn count(&self, proxy: &Proxy) -> usize {
+        self.per_proxy.get(&(proxy.ip.clone(), proxy.port.clone())).copied().unwrap_or(0)
+    }
+
+    fn insert(&mut self, lease: Lease) {
+        *self.per_proxy.entry(lease.key()).or_default() += 1;
+        self.expiry.insert((lease.expires_at, lease.lease_id.clone()));
+        self.by_id.insert(lease.lease_id.clone(), lease);
+    }
+
+    fn remove(&mut self, lease_id: &str) -> Option<Lease> {
+        let lease = self.by_id.remove(lease_id)?;
+        self.expiry.remove(&(lease.expires_at, lease.lease_id.clone()));
+        let key = lease.key();
+        if let Some(count) = self.per_proxy.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_proxy.remove(&key);
+            }
+        }
+        Some(lease)
+    }
+
+    /// 按到期时间从早到晚移除已到期的租约。
+    fn expire(&mut self, now: NaiveDateTime) {
+        while let Some((expires_at, lease_id)) = self.expiry.first().cloned()
+            && expires_at <= now
+        {
+            self.remove(&lease_id);
+        }
+    }
+}
+
+/// 租约管理器。
+pub struct LeaseManager {
+    max_concurrent: usize,
+    leases: Mutex<Leases>,
+}
+
+impl LeaseManager {
+    /// 创建租约管理器，`max_concurrent` 为每个代理允许同时存在的租约数（至少为 1）。
+    pub fn new(max_concurrent: usize) -> Self {
+        Self { max_concurrent: max_concurrent.max(1), leases: Mutex::new(Leases::default()) }
+    }
+
+    /// 加锁并移除已到期的租约。
+    fn lock(&self) -> std::sync::MutexGuard<'_, Leases> {
+        let mut leases = self.leases.lock().unwrap_or_else(|e| e.into_inner());
+        leases.expire(Utc::now().naive_utc());
+        leases
+    }
+
+    /// 代理的租约数是否还未达到上限。
+    fn has_capacity(&self, leases: &Leases, proxy: &Proxy) -> bool {
+        leases.count(proxy) < self.max_concurrent
+    }
+
+    /// 过滤掉租约数已满的代理，供不经租约直接分配代理的接口与代理网关使用。
+    pub fn available<'a>(&self, candidates: &[&'a Proxy]) -> Vec<&'a Proxy> {
+        let leases = self.lock();
+        candidates.iter().copied().filter(|p| self.has_capacity(&leases, p)).collect()
+    }
+
+    /// 从租约未满的候选代理中按策略选取一个并登记租约，没有可用代理时返回 `None`。
+    pub fn acquire(
+        &self,
+        selector: &Selector,
+        strategy: SelectStrategy,
+        candidates: &[&Proxy],
+        ttl: Duration,
+    ) -> Option<Lease> {
+        let mut leases = self.lock();
+        let available: Vec<&Proxy> = candidates.iter().copied().filter(|p| self.has_capacity(&leases, p)).collect();
+        let proxy = selector.select(strategy, &available)?;
+
+        let lease = Lease {
+            lease_id: format!("{:032x}", rand::rng().random::<u128>()),
+            proxy: proxy.basic(),
+            expires_at: Utc::now().naive_utc() + ttl,
+        };
+        leases.insert(lease.clone());
+        Some(lease)
+    }
+
+    /// 归还租约。
+    ///
+    /// # 错误
+    /// 租约不存在或已过期时返回错误。
+    pub fn release(&self, lease_id: &str) -> Result<Lease> {
+        self.lock().remove(lease_id).ok_or_else(|| anyhow!("租约 {} 不存在或已过期", lease_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies() -> Vec<Proxy> {
+        vec![Proxy::new("127.0.0.1".into(), "1".into()), Proxy::new("127.0.0.1".into(), "2".into())]
+    }
+
+    #[test]
+    fn test_exclusive_lease() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        let manager = LeaseManager::new(1);
+        let selector = Selector::default();
+        let ttl = Duration::seconds(60);
+
+        let first = manager.acquire(&selector, SelectStrategy::Random, &candidates, ttl).unwrap();
+        let second = manager.acquire(&selector, SelectStrategy::Random, &candidates, ttl).unwrap();
+        assert_ne!(first.proxy.port, second.proxy.port);
+        assert!(manager.acquire(&selector, SelectStrategy::Random, &candidates, ttl).is_none());
+        assert!(manager.available(&candidates).is_empty());
+
+        manager.release(&first.lease_id).unwrap();
+        assert!(manager.release(&first.lease_id).is_err());
+        let third = manager.acquire(&selector, SelectStrategy::Random, &candidates, ttl).unwrap();
+        assert_eq!(third.proxy.port, first.proxy.port);
+    }
+
+    #[test]
+    fn test_concurrent_leases_and_expiry() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list[..1].iter().collect();
+        let manager = LeaseManager::new(2);
+        let selector = Selector::default();
+
+        assert!(manager.acquire(&selector, SelectStrategy::Best, &candidates, Duration::zero()).is_some());
+        // 已到期的租约不占用名额
+        for _ in 0..2 {
+            assert!(manager.acquire(&selector, SelectStrategy::Best, &candidates, Duration::seconds(60)).is_some());
+        }
+        assert!(manager.acquire(&selector, SelectStrategy::Best, &candidates, Duration::seconds(60)).is_none());
+    }
+}
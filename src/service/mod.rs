@@ -1,2 +1,10 @@
+pub mod feedback;
+pub mod history;
+pub mod job;
+pub mod lease;
 pub mod quality;
+pub mod scheduler;
+pub mod scoring;
+pub mod selector;
+pub mod stats;
 pub mod verifier;
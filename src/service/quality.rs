@@ -8,55 +8,72 @@
 //! ## 功能简介
 //!
 //! - 向指定目标地址发起多轮请求，评估代理连接的成功率与速度；
-//! - 计算响应时间的平均值与方差，以评估稳定性；
+//! - 计算响应时间的标准差，并按半衰期与历史稳定性加权，以评估稳定性；
+//! - 按命名的目标站点分别校验状态码与响应内容，记录各站点的成功率与评分；
+//! - 响应内容与目标声明的摘要、子串、正则或 JSON 字段不符时记为篡改（`tampered`）；
 //! - 合并多个目标节点的测试结果，生成综合质量报告；
+//! - 通过回显接口检测代理匿名等级（透明 / 普通匿名 / 高匿）；
+//! - 根据 HTTPS 目标能否经 CONNECT 隧道访问，判定 HTTP 代理为 `http` 还是 `https`；
 //! - 根据测试数据打分，生成综合评分，供筛选与排序使用。
 //!
 //! ## 核心结构与函数
 //!
 //! - [`QualityTestResults`]：单个测试任务的统计结果；
 //! - [`QualityConfig`]：质量测试参数配置；
+//! - [`Target`]：目标站点及其响应校验规则；
 //! - [`run_tests`]：对代理执行多个目标的质量测试；
-//! - [`evaluate`]：入口函数，运行测试并生成完整代理对象（含质量信息）。
+//! - [`detect_anonymity`]：通过回显接口检测代理匿名等级；
+//! - [`evaluate`]：入口函数，运行测试并生成完整代理对象（含质量信息）与各站点结果。
 //!
 //! ## 使用场景
 //!
 //! 用于批量代理验证场景中的质量评估步骤，适合代理池清洗、优选策略、自动下线低质量节点等需求。
 
-use crate::common::utils::{round2, speed_to_score};
+use crate::common::utils::{round2, round_ms};
 use crate::db::get_storage;
 use crate::db::manager::ProxyStorage;
-use crate::model::{APP_CONFIG, Proxy, ProxyBasic, ProxyCheckResult};
+use crate::model::{
+    APP_CONFIG, Anonymity, CheckErrorKind, CheckSource, EchoResponse, Protocol, Proxy, ProxyBasic, ProxyCheck,
+    ProxyCheckResult, ProxySiteResult, ScoringConfig, TamperAction, TargetProfile,
+};
+use crate::service::scoring::{self, ScoringModel};
+use std::sync::Arc;
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use std::time::Duration;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use regex::Regex;
+use sha2::{Digest, Sha256};
 use tokio::time::sleep;
-use tracing::log::{debug};
+use tracing::log::{debug, info, warn};
 
 /// 用于配置代理质量评估的权重与测试参数。
 ///
-/// 包括成功率、响应速度、稳定性的权重比例，
-/// 以及测试次数、单次请求超时时间和测试目标 URL 列表。
+/// 包括评分模型（见 [`ScoringModel`]），
+/// 以及测试次数、单次请求超时时间和测试目标站点列表。
 #[derive(Clone, Debug)]
 pub struct QualityConfig {
-    /// 速度评分权重（0.0 - 1.0）。
-    pub speed_weight: f64,
-    /// 成功率评分权重（0.0 - 1.0）。
-    pub success_weight: f64,
-    /// 稳定性评分权重（0.0 - 1.0）。
-    pub stability_weight: f64,
+    /// 评分模型，由配置文件的 `[scoring]` 决定。
+    pub scoring: Arc<dyn ScoringModel>,
     /// 每个代理测试的请求次数。
     pub test_count: u64,
     /// 每个代理测试的失败重试次数。
     pub max_retries: u8,
     /// 每次请求的超时时间。
     pub timeout: Duration,
-    /// 用于测试的目标 URL 列表。
-    pub test_urls: Vec<String>,
+    /// 用于测试的目标站点，来自 `[[verify.targets]]`（或旧的 `test_urls`）。
+    pub targets: Vec<Target>,
     /// 验证等级：快速、标准、细致
     pub verify_level: VerifyLevel,
+    /// 匿名检测使用的回显接口，为 `None` 时不检测匿名等级。
+    pub echo_url: Option<String>,
+    /// 本机真实出口 IP，用于判断代理是否泄露来源。
+    pub egress_ip: Option<String>,
+    /// 稳定性历史的半衰期，为 0 时只使用本轮测试的稳定性。
+    pub stability_half_life: Duration,
+    /// 检测到内容篡改时对代理的处理方式。
+    pub tamper_action: TamperAction,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -65,11 +82,19 @@ pub enum VerifyLevel {
     Standard,
     Detailed,
 }
+/// 计算稳定性至少需要的响应时间样本数。
+const MIN_STABILITY_SAMPLES: u64 = 2;
+
+/// 每个目标站点的测试次数：评分用到稳定性时至少取 [`MIN_STABILITY_SAMPLES`] 次，
+/// 否则单次测试无法算出稳定性，该项只能沿用历史值或默认的 0.5。
+fn sample_count(level_count: u64, scoring: &ScoringConfig) -> u64 {
+    if scoring.weighs_stability() { level_count.max(MIN_STABILITY_SAMPLES) } else { level_count }
+}
+
 /// 提供默认配置：
-/// - 速度、成功率、稳定性权重均为 1.0；
-/// - 测试次数为 3；
-/// - 超时时间为 5 秒；
-/// - 默认测试地址为 `https://cip.cc`。
+/// - 评分模型按 `[scoring]` 选择；
+/// - 测试次数、重试次数与超时时间由验证等级决定，评分用到稳定性时每个站点至少测试 2 次；
+/// - 目标站点来自 `[verify]`，配置在加载时已校验过。
 impl Default for QualityConfig {
     fn default() -> Self {
         let level = match APP_CONFIG.verify.verify_level {
@@ -86,25 +111,172 @@ impl Default for QualityConfig {
         };
 
         Self {
-            speed_weight: 0.4,
-            success_weight: 0.3,
-            stability_weight: 0.3,
-            test_count,
+            scoring: scoring::active_model(),
+            test_count: sample_count(test_count, &APP_CONFIG.scoring),
             max_retries,
             timeout,
-            test_urls: APP_CONFIG.verify.test_urls.clone(),
+            targets: APP_CONFIG
+                .verify
+                .profiles()
+                .into_iter()
+                .map(|profile| Target::new(profile).expect("目标站点配置已在加载时校验"))
+                .collect(),
             verify_level: level,
+            echo_url: APP_CONFIG.verify.echo_url.clone(),
+            egress_ip: APP_CONFIG.verify.egress_ip.clone(),
+            stability_half_life: Duration::from_secs(APP_CONFIG.verify.stability_half_life),
+            tamper_action: APP_CONFIG.verify.tamper_action,
+        }
+    }
+}
+
+#[cfg(test)]
+impl QualityConfig {
+    /// 测试用：不依赖配置文件的评估参数，单个目标站点、每个站点请求一次、不重试。
+    pub(crate) fn for_test(url: &str) -> Self {
+        Self {
+            scoring: Arc::new(scoring::WeightedModel::new(crate::model::ScoringConfig::default())),
+            test_count: 1,
+            max_retries: 0,
+            timeout: Duration::from_secs(2),
+            targets: vec![Target::new(TargetProfile::new("local", url)).unwrap()],
+            verify_level: VerifyLevel::Fast,
+            echo_url: None,
+            egress_ip: None,
+            stability_half_life: Duration::ZERO,
+            tamper_action: TamperAction::Flag,
+        }
+    }
+}
+
+impl QualityConfig {
+    /// 若配置了回显接口但未指定出口 IP，则直连回显接口获取本机真实出口 IP。
+    ///
+    /// 获取失败时只输出警告，本轮验证将跳过匿名等级检测。
+    pub async fn resolve_egress_ip(&mut self) {
+        let Some(echo_url) = &self.echo_url else { return };
+        if self.egress_ip.is_some() {
+            return;
+        }
+
+        let result = async {
+            let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+            let echo = client.get(echo_url).send().await?.json::<EchoResponse>().await?;
+            Ok::<_, anyhow::Error>(echo.origin.split(',').next().unwrap_or("").trim().to_string())
+        }
+        .await;
+
+        match result {
+            Ok(ip) if !ip.is_empty() => {
+                info!("🌐 本机出口 IP：{}", ip);
+                self.egress_ip = Some(ip);
+            }
+            Ok(_) => warn!("回显接口 {} 未返回来源 IP，跳过匿名检测", echo_url),
+            Err(e) => warn!("获取本机出口 IP 失败，跳过匿名检测：{}", e),
+        }
+    }
+}
+
+/// 验证目标站点：配置及预编译的响应校验规则。
+#[derive(Clone, Debug)]
+pub struct Target {
+    pub profile: TargetProfile,
+    body_regex: Option<Regex>,
+}
+
+impl Target {
+    /// 根据目标站点配置创建，`body_regex` 无效时返回错误。
+    pub fn new(profile: TargetProfile) -> Result<Self> {
+        let body_regex = profile.body_regex.as_deref().map(Regex::new).transpose()?;
+        Ok(Self { profile, body_regex })
+    }
+
+    /// 状态码是否符合预期：未配置 `expected_status` 时接受任意 2xx。
+    fn status_ok(&self, status: u16) -> bool {
+        match self.profile.expected_status {
+            Some(expected) => status == expected,
+            None => (200..300).contains(&status),
+        }
+    }
+
+    /// 是否需要读取响应内容进行校验。
+    fn needs_body(&self) -> bool {
+        let profile = &self.profile;
+        profile.body_contains.is_some()
+            || self.body_regex.is_some()
+            || profile.body_sha256.is_some()
+            || profile.json_field.is_some()
+    }
+
+    /// 响应内容是否满足全部已配置的规则：SHA-256 摘要、子串、正则与 JSON 字段。
+    fn body_ok(&self, body: &[u8]) -> bool {
+        let profile = &self.profile;
+        if let Some(expected) = &profile.body_sha256
+            && !hex::encode(Sha256::digest(body)).eq_ignore_ascii_case(expected)
+        {
+            return false;
+        }
+
+        let text = String::from_utf8_lossy(body);
+        profile.body_contains.as_ref().is_none_or(|needle| text.contains(needle.as_str()))
+            && self.body_regex.as_ref().is_none_or(|regex| regex.is_match(&text))
+            && profile.json_field.as_ref().is_none_or(|pointer| self.json_ok(&text, pointer))
+    }
+
+    /// 响应是否为 JSON 且 `pointer` 处的字段存在，配置了 `json_equals` 时还需取值相同。
+    fn json_ok(&self, text: &str, pointer: &str) -> bool {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else { return false };
+        let Some(value) = json.pointer(pointer) else { return false };
+        self.profile.json_equals.as_ref().is_none_or(|expected| match value {
+            serde_json::Value::String(s) => s == expected,
+            other => serde_json::from_str::<serde_json::Value>(expected).is_ok_and(|v| v == *other),
+        })
+    }
+
+    /// 校验一次响应，只有状态码符合预期且配置了内容规则时才读取响应内容。
+    async fn check(&self, resp: reqwest::Response) -> Result<(), CheckErrorKind> {
+        if !self.status_ok(resp.status().as_u16()) {
+            return Err(CheckErrorKind::HttpStatus);
         }
+        if !self.needs_body() {
+            return Ok(());
+        }
+        let limit = self.profile.max_body_size.unwrap_or(MAX_BODY_SIZE);
+        // 截断后的内容无法与摘要、JSON 等规则比较，单独记为 body_too_large，不视为篡改
+        let Some(body) = read_body(resp, limit).await.map_err(|e| classify_error(&e))? else {
+            return Err(CheckErrorKind::BodyTooLarge);
+        };
+        if self.body_ok(&body) { Ok(()) } else { Err(CheckErrorKind::Tampered) }
     }
 }
 
+/// 经代理读取响应内容的默认最大字节数，目标站点可通过 `max_body_size` 调整。
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// 读取响应内容，最多读取 `limit` 字节，避免代理返回超大或无限长的响应。
+///
+/// 内容超过 `limit` 时停止读取并返回 `None`。
+async fn read_body(mut resp: reqwest::Response, limit: usize) -> reqwest::Result<Option<Vec<u8>>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Some(body))
+}
+
 /// 记录代理在多个测试中的响应时间与成功情况。
 ///
-/// 用于计算平均速度、成功率与稳定性。
-struct QualityTestResults {
-    successes: Vec<f64>,
+/// 用于计算平均速度、成功率与稳定性，并保留每次请求的检测记录。
+/// 成功次数与响应时间分开统计：成功但没有耗时的记录只计入成功率。
+pub(crate) struct QualityTestResults {
+    successes: u64,
+    latencies: Vec<f64>,
     failures: u64,
     total: u64,
+    checks: Vec<ProxyCheck>,
 }
 
 impl QualityTestResults {
@@ -112,24 +284,37 @@ impl QualityTestResults {
     ///
     /// # 参数
     /// - `total`: 计划进行的总测试次数。
-    fn new(total: u64) -> Self {
+    pub(crate) fn new(total: u64) -> Self {
         Self {
-            successes: Vec::new(),
+            successes: 0,
+            latencies: Vec::new(),
             failures: 0,
             total,
+            checks: Vec::new(),
+        }
+    }
+
+    /// 记录一次请求的检测结果，并计入成功或失败。
+    fn record(&mut self, check: ProxyCheck) {
+        if check.success {
+            self.record_success(check.latency);
+        } else {
+            self.record_failure();
         }
+        self.checks.push(check);
     }
 
     /// 记录一次成功的代理请求。
     ///
     /// # 参数
-    /// - `duration`: 本次请求的耗时（单位：秒）。
-    fn record_success(&mut self, d: f64) {
-        self.successes.push(d);
+    /// - `latency`: 本次请求的耗时（单位：秒），未知时只计入成功次数。
+    pub(crate) fn record_success(&mut self, latency: Option<f64>) {
+        self.successes += 1;
+        self.latencies.extend(latency);
     }
 
     /// 记录一次失败的代理请求。
-    fn record_failure(&mut self) {
+    pub(crate) fn record_failure(&mut self) {
         self.failures += 1;
     }
 
@@ -137,28 +322,102 @@ impl QualityTestResults {
     ///
     /// # 返回
     /// 成功率（0.0 ~ 1.0）。
-    fn success_rate(&self) -> f64 {
+    pub(crate) fn success_rate(&self) -> f64 {
         let rate = if self.total == 0 {
             0.0
         } else {
-            self.successes.len() as f64 / self.total as f64
+            self.successes as f64 / self.total as f64
         };
 
         round2(rate)
     }
 
-    /// 计算所有成功请求的平均响应时间（单位：秒）。
+    /// 计算所有成功请求的平均响应时间（单位：秒），精确到毫秒。
     ///
-    /// 若无成功记录则返回 `None`。
-    fn average_speed(&self) -> f64 {
-        let speed = if self.successes.is_empty() {
-            0.0
-        } else {
-            self.successes.iter().map(|&x| x).sum::<f64>() / self.successes.len() as f64
-        };
+    /// 没有响应时间记录时返回 `None`。
+    pub(crate) fn average_speed(&self) -> Option<f64> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let speed = self.latencies.iter().copied().sum::<f64>() / self.latencies.len() as f64;
+        Some(round_ms(speed))
+    }
+
+    /// 计算成功请求响应时间的标准差（单位：秒）。
+    ///
+    /// 响应时间记录少于 2 次时无法衡量波动，返回 `None`。
+    fn latency_stddev(&self) -> Option<f64> {
+        if self.latencies.len() < 2 {
+            return None;
+        }
+        let n = self.latencies.len() as f64;
+        let mean = self.latencies.iter().sum::<f64>() / n;
+        let variance = self.latencies.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / n;
+        Some(variance.sqrt())
+    }
+
+    /// 是否有请求的响应内容被判定为篡改。
+    pub(crate) fn tampered(&self) -> bool {
+        self.checks.iter().any(|c| c.error_kind == Some(CheckErrorKind::Tampered))
+    }
+
+    /// 本轮测试的稳定性（0.0 ~ 1.0），由响应时间的变异系数（标准差 / 平均值）换算：
+    /// `1 / (1 + cv)`，响应时间完全一致时为 1.0，波动越大越接近 0。
+    ///
+    /// 全部失败时为 0.0；响应时间记录少于 2 次时返回 `None`。
+    pub(crate) fn stability(&self) -> Option<f64> {
+        if self.successes == 0 {
+            return Some(0.0);
+        }
+        let stddev = self.latency_stddev()?;
+        let mean = self.latencies.iter().sum::<f64>() / self.latencies.len() as f64;
+        let cv = if mean > 0.0 { stddev / mean } else { 0.0 };
+        Some(round2(1.0 / (1.0 + cv)))
+    }
+}
 
-        round2(speed)
+/// 将本轮稳定性与历史稳定性按指数加权合并。
+///
+/// 历史权重为 `0.5 ^ (距上次检测的时长 / 半衰期)`，距离上次检测越久，本轮结果占比越大。
+/// 本轮无法衡量稳定性时保留历史值；都没有时默认为 0.5。
+///
+/// # 参数
+/// - `old`: 历史稳定性
+/// - `last_checked`: 上次检测时间
+/// - `current`: 本轮测试的稳定性
+/// - `now`: 当前时间
+/// - `half_life`: 半衰期，为 0 时忽略历史
+fn blend_stability(
+    old: Option<f64>,
+    last_checked: Option<NaiveDateTime>,
+    current: Option<f64>,
+    now: NaiveDateTime,
+    half_life: Duration,
+) -> f64 {
+    let (old, current) = match (old, current) {
+        (_, None) => return old.unwrap_or(0.5),
+        (None, Some(current)) => return current,
+        (Some(old), Some(current)) => (old, current),
+    };
+    if half_life.is_zero() {
+        return current;
     }
+
+    let elapsed = last_checked
+        .map(|t| (now - t).num_milliseconds().max(0) as f64 / 1000.0)
+        .unwrap_or(f64::INFINITY);
+    let decay = 0.5_f64.powf(elapsed / half_life.as_secs_f64());
+    round2(old * decay + current * (1.0 - decay))
+}
+
+/// 单个代理一次质量评估的结果。
+pub struct QualityReport {
+    /// 带有综合质量信息的代理。
+    pub proxy: Proxy,
+    /// 每次测速请求的检测记录。
+    pub checks: Vec<ProxyCheck>,
+    /// 各目标站点的验证结果。
+    pub sites: Vec<ProxySiteResult>,
 }
 
 /// 对单个代理进行多次测试，并根据响应情况计算评分。
@@ -169,84 +428,247 @@ impl QualityTestResults {
 /// # 参数
 /// - `proxy`: 待测试的代理
 /// - `config`: 质量评估配置
+/// - `storage`: 读取代理上一次评估结果的存储，用于混合历史稳定性
 ///
 /// # 返回
-/// 带有打分结果的完整 `Proxy` 实例。
-pub async fn evaluate(proxy: &ProxyBasic, config: &QualityConfig) -> Result<Proxy> {
+/// 带有打分结果的完整 `Proxy` 实例、每次测速请求的检测记录，以及各目标站点的结果与评分。
+pub async fn evaluate(proxy: &ProxyBasic, config: &QualityConfig, storage: &dyn ProxyStorage) -> Result<QualityReport> {
     let mut result = ProxyCheckResult::default();
-    let test_results = run_tests(proxy, config).await?;
+    let (names, site_results): (Vec<String>, Vec<QualityTestResults>) =
+        run_tests(proxy, config).await?.into_iter().unzip();
+    let test_results = merge_test_results(&site_results);
+    let mut proxy = proxy.clone();
+    proxy.protocol = classify_protocol(proxy.protocol, &test_results.checks);
+    let proxy = &proxy;
 
-    result.speed = Some(test_results.average_speed());
+    result.speed = test_results.average_speed();
     result.success_rate = Some(test_results.success_rate());
+    result.tampered = test_results.tampered();
     result.last_checked = Some(Utc::now().naive_utc());
 
-    if let Some(old) = get_storage()
-        .find_proxy_by_ip_port(&proxy.ip, &proxy.port)
-        .await?
-    {
-        let delta = (result.success_rate.unwrap_or(0.0) - old.success_rate.unwrap_or(0.0)).abs();
-        let stability = old.stability.unwrap_or(0.5) * 0.7 + (1.0 - delta) * 0.3;
-        result.stability = Some(stability.clamp(0.0, 1.0));
-    } else {
-        result.stability = Some(0.5);
+    let old = storage.find_proxy_by_ip_port(&proxy.ip, &proxy.port).await?;
+    // 本轮未能检测出匿名等级时沿用上次的结果
+    if result.success_rate.unwrap_or(0.0) > 0.0 {
+        result.anonymity = detect_anonymity(proxy, config).await;
     }
+    result.anonymity = result.anonymity.or(old.as_ref().and_then(|p| p.anonymity));
+    let stability = blend_stability(
+        old.as_ref().and_then(|p| p.stability),
+        old.as_ref().and_then(|p| p.last_checked),
+        test_results.stability(),
+        Utc::now().naive_utc(),
+        config.stability_half_life,
+    );
+    result.stability = Some(stability.clamp(0.0, 1.0));
+
+    let sites = names
+        .into_iter()
+        .zip(&site_results)
+        .map(|(name, site)| site_result(proxy, name, site, &result, config.scoring.as_ref()))
+        .collect();
+
+    compute_score(&mut result, config.scoring.as_ref());
+    Ok(QualityReport { proxy: Proxy::from_parts(proxy.clone(), result), checks: test_results.checks, sites })
+}
 
-    compute_score(&mut result, config);
-    Ok(Proxy::from_parts(proxy.clone(), result))
+/// 生成单个目标站点的验证结果。
+///
+/// 速度与成功率取该站点自身的测试结果，稳定性与匿名等级沿用整体评估结果，
+/// 再由评分模型计算该站点上的评分。
+fn site_result(
+    proxy: &ProxyBasic,
+    site: String,
+    results: &QualityTestResults,
+    overall: &ProxyCheckResult,
+    model: &dyn ScoringModel,
+) -> ProxySiteResult {
+    let success_rate = results.success_rate();
+    let mut result = ProxyCheckResult {
+        speed: results.average_speed(),
+        success_rate: Some(success_rate),
+        ..overall.clone()
+    };
+    compute_score(&mut result, model);
+
+    ProxySiteResult {
+        ip: proxy.ip.clone(),
+        port: proxy.port.clone(),
+        site,
+        speed: result.speed,
+        success_rate,
+        score: result.score.unwrap_or(0.0),
+        last_checked: overall.last_checked.unwrap_or_else(|| Utc::now().naive_utc()),
+    }
 }
 
 /// 对给定代理执行多个目标地址的多轮请求测试，
 /// 记录每轮成功率、平均速度、稳定性等指标。
 ///
-/// 每个目标地址将进行 `test_count` 次测试，
-/// 所有目标地址的结果将被合并为整体评估结果。
+/// 每个目标站点将进行 `test_count` 次测试，请求成功且通过该站点的状态码与内容校验才计为成功。
 ///
 /// # 参数
 /// - `proxy`: 待测试的代理基本信息（IP 和端口）
 /// - `config`: 质量测试配置，包括测试次数、超时、测试地址等
 ///
 /// # 返回
-/// 返回一个合并后的 `QualityTestResults`，
-/// 包含所有测试节点的综合成功率、平均响应时间与稳定性。
+/// 按目标站点名称分组的 `QualityTestResults`，顺序与配置一致，
+/// 由调用方合并为整体结果（见 [`merge_test_results`]）。
+///
+/// 代理地址按代理协议构建，见 [`Protocol::proxy_url`]；HTTP 类代理访问 `https://` 目标时经 CONNECT 隧道。
 ///
 /// # 错误
 /// - 若 `proxy` 构建或 HTTP 客户端构建失败，返回对应错误。
 /// - 请求目标地址失败不会中断流程，只计为失败记录。
-async fn run_tests(proxy: &ProxyBasic, config: &QualityConfig) -> Result<QualityTestResults> {
-    let proxy_url = format!("http://{}:{}", proxy.ip, proxy.port);
-    let proxy_obj = reqwest::Proxy::all(&proxy_url)?;
-    let client = reqwest::Client::builder()
-        .proxy(proxy_obj)
-        .timeout(config.timeout)
-        .build()?;
+async fn run_tests(proxy: &ProxyBasic, config: &QualityConfig) -> Result<Vec<(String, QualityTestResults)>> {
+    let client = build_client(proxy, config)?;
 
     let mut futs = FuturesUnordered::new();
-    let total_tests = config.test_urls.len() as u64 * config.test_count;
 
-    for test_url in &config.test_urls {
+    for (index, target) in config.targets.iter().enumerate() {
         for _ in 0..config.test_count {
             let client = client.clone();
-            let url = test_url.clone();
-            let label = format!("[{}:{}]", proxy.ip, proxy.port);
+            let url = target.profile.url.clone();
+            let label = format!("[{}://{}:{}]", proxy.protocol, proxy.ip, proxy.port);
 
             futs.push(async move {
-                send_with_retries(&client, &url, config.max_retries, &label).await
+                let probe = send_with_retries(&client, &url, target, config.max_retries, &label).await;
+                (index, url, probe)
             });
         }
     }
 
-    let mut results = QualityTestResults::new(total_tests);
+    let mut results: Vec<_> = config
+        .targets
+        .iter()
+        .map(|target| (target.profile.name.clone(), QualityTestResults::new(config.test_count)))
+        .collect();
 
-    while let Some(res) = futs.next().await {
-        match res {
-            Some(duration) => results.record_success(duration),
-            None => results.record_failure(),
-        }
+    while let Some((index, url, probe)) = futs.next().await {
+        results[index].1.record(ProxyCheck {
+            ip: proxy.ip.clone(),
+            port: proxy.port.clone(),
+            checked_at: Utc::now().naive_utc(),
+            source: CheckSource::Verify,
+            target: url,
+            success: probe.error_kind.is_none(),
+            latency: probe.latency,
+            status_code: probe.status_code.map(i32::from),
+            error_kind: probe.error_kind,
+        });
     }
 
     Ok(results)
 }
 
+/// 构建通过指定代理发送请求的 HTTP 客户端。
+fn build_client(proxy: &ProxyBasic, config: &QualityConfig) -> Result<reqwest::Client> {
+    let proxy_obj = reqwest::Proxy::all(proxy.proxy_url())?;
+    let client = reqwest::Client::builder()
+        .proxy(proxy_obj)
+        .timeout(config.timeout)
+        .build()?;
+    Ok(client)
+}
+
+/// 通过代理访问回显接口，检测代理的匿名等级。
+///
+/// 需要同时配置回显接口与本机出口 IP（见 [`QualityConfig::resolve_egress_ip`]），
+/// 否则直接返回 `None`。请求或解析失败同样返回 `None`，不影响其他指标。
+pub async fn detect_anonymity(proxy: &ProxyBasic, config: &QualityConfig) -> Option<Anonymity> {
+    let (Some(echo_url), Some(egress_ip)) = (&config.echo_url, &config.egress_ip) else {
+        return None;
+    };
+    let label = format!("[{}://{}:{}]", proxy.protocol, proxy.ip, proxy.port);
+
+    let result = async {
+        let client = build_client(proxy, config)?;
+        let body = read_body(client.get(echo_url).send().await?, MAX_BODY_SIZE)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("回显接口响应超过 {} 字节", MAX_BODY_SIZE))?;
+        Ok::<_, anyhow::Error>(serde_json::from_slice::<EchoResponse>(&body)?)
+    }
+    .await;
+
+    match result {
+        Ok(echo) => {
+            let anonymity = classify_anonymity(egress_ip, &echo);
+            debug!("{} 匿名等级：{}，来源 IP：{}", label, anonymity, echo.origin);
+            Some(anonymity)
+        }
+        Err(e) => {
+            debug!("{} 匿名检测失败：{}", label, e);
+            None
+        }
+    }
+}
+
+/// 会暴露代理身份的请求头。
+const PROXY_HEADERS: [&str; 6] = ["Via", "X-Forwarded-For", "Forwarded", "X-Real-Ip", "Proxy-Connection", "X-Proxy-Id"];
+
+/// 根据回显结果判定匿名等级。
+///
+/// - 来源 IP 或任一代理特征头中出现本机出口 IP：透明代理；
+/// - 未泄露真实 IP，但存在代理特征头：普通匿名；
+/// - 两者都没有：高匿代理。
+fn classify_anonymity(egress_ip: &str, echo: &EchoResponse) -> Anonymity {
+    let leaked = echo.origin.split(',').any(|ip| ip.trim() == egress_ip)
+        || PROXY_HEADERS
+            .iter()
+            .filter_map(|h| echo.header(h))
+            .any(|v| v.contains(egress_ip));
+
+    if leaked {
+        Anonymity::Transparent
+    } else if PROXY_HEADERS.iter().any(|h| echo.header(h).is_some()) {
+        Anonymity::Anonymous
+    } else {
+        Anonymity::Elite
+    }
+}
+
+/// 根据 HTTPS 目标的检测记录判定 HTTP 类代理的协议。
+///
+/// 经 HTTP 代理访问 `https://` 地址需要先建立 CONNECT 隧道，隧道建立后才可能收到目标的响应：
+/// 任一 HTTPS 请求成功或收到状态码时判定为 `https`，否则判定为 `http`。
+/// SOCKS 代理，以及没有 HTTPS 目标时，保持原协议。
+fn classify_protocol(protocol: Protocol, checks: &[ProxyCheck]) -> Protocol {
+    if !matches!(protocol, Protocol::Http | Protocol::Https) {
+        return protocol;
+    }
+    let mut https = checks.iter().filter(|c| c.target.starts_with("https://")).peekable();
+    if https.peek().is_none() {
+        return protocol;
+    }
+    if https.any(|c| c.success || c.status_code.is_some()) { Protocol::Https } else { Protocol::Http }
+}
+
+/// 一次测速请求（含重试）的最终结果。
+struct Probe {
+    /// 成功时的耗时（单位：秒，保留两位小数）。
+    latency: Option<f64>,
+    /// 最后一次收到的 HTTP 状态码。
+    status_code: Option<u16>,
+    /// 失败原因，成功时为 `None`。
+    error_kind: Option<CheckErrorKind>,
+}
+
+impl Probe {
+    fn failed(error_kind: CheckErrorKind, status_code: Option<u16>) -> Self {
+        Self { latency: None, status_code, error_kind: Some(error_kind) }
+    }
+}
+
+/// 对请求错误进行分类。
+fn classify_error(e: &reqwest::Error) -> CheckErrorKind {
+    if e.is_timeout() {
+        CheckErrorKind::Timeout
+    } else if e.is_connect() {
+        CheckErrorKind::Connect
+    } else {
+        CheckErrorKind::Request
+    }
+}
+
 /// 向指定 URL 发送 GET 请求，失败时进行最多 `max_retries` 次重试，并记录耗时。
 ///
 /// 每次请求都会打印日志，包括成功、失败和状态码错误的信息，方便调试和跟踪代理质量。
@@ -254,12 +676,13 @@ async fn run_tests(proxy: &ProxyBasic, config: &QualityConfig) -> Result<Quality
 /// # 参数
 /// - `client`: 配置好的 `reqwest::Client`，包含代理设置与超时。
 /// - `url`: 要请求的目标 URL 字符串。
+/// - `target`: 目标站点配置，用于校验状态码与响应内容。
 /// - `max_retries`: 最大重试次数（不包括第一次尝试）。
 /// - `label`: 用于日志输出的代理标签（例如 `[127.0.0.1:8080]`）。
 ///
 /// # 返回
-/// - `Some(f64)`：请求成功时返回耗时（单位：秒，保留两位小数）。
-/// - `None`：请求全部失败或状态码非 2xx。
+/// 最后一次请求的结果：成功时带耗时（单位：秒，保留两位小数），
+/// 全部失败或未通过目标校验时带失败原因。
 ///
 /// # 日志输出示例
 /// ```text
@@ -269,11 +692,13 @@ async fn run_tests(proxy: &ProxyBasic, config: &QualityConfig) -> Result<Quality
 async fn send_with_retries(
     client: &reqwest::Client,
     url: &str,
+    target: &Target,
     max_retries: u8,
     label: &str, // 用于输出代理 IP 信息
-) -> Option<f64> {
+) -> Probe {
     let mut attempt = 0;
     let mut backoff = Duration::from_millis(500);
+    let mut probe = Probe::failed(CheckErrorKind::Request, None);
 
     while attempt <= max_retries {
         debug!(
@@ -285,18 +710,36 @@ async fn send_with_retries(
 
         let start = std::time::Instant::now();
         match client.get(url).send().await {
-            Ok(resp) if resp.status().is_success() => {
+            Ok(resp) => {
                 let elapsed = start.elapsed().as_secs_f64();
-                debug!(
-                    "{} 第 {} 次请求 {} 成功，耗时 {:.2} 秒",
-                    label,
-                    attempt + 1,
-                    url,
-                    elapsed
-                );
-                return Some(round2(elapsed));
+                let status = resp.status().as_u16();
+                match target.check(resp).await {
+                    Ok(()) => {
+                        debug!(
+                            "{} 第 {} 次请求 {} 成功，耗时 {:.2} 秒",
+                            label,
+                            attempt + 1,
+                            url,
+                            elapsed
+                        );
+                        return Probe { latency: Some(round_ms(elapsed)), status_code: Some(status), error_kind: None };
+                    }
+                    Err(kind) => {
+                        probe = Probe::failed(kind, Some(status));
+                        debug!(
+                            "⚠️ {} 第 {} 次请求 {} 未通过目标 {} 的校验：状态 {}，{}",
+                            label,
+                            attempt + 1,
+                            url,
+                            target.profile.name,
+                            status,
+                            kind.as_str()
+                        );
+                    }
+                }
             }
             Err(e) => {
+                probe = Probe::failed(classify_error(&e), None);
                 debug!(
                     "🔁 {} 第 {} 次请求 {} 失败，原因：{}",
                     label,
@@ -304,47 +747,20 @@ async fn send_with_retries(
                     url,
                     e
                 );
-                if attempt < max_retries {
-                    debug!("{} 正在等待 {:?} 后重试...", label, backoff);
-                    sleep(backoff).await;
-                    backoff *= 2; // 指数退避
-                } else {
-                    debug!(
-                        "❌ {} 第 {} 次请求 {} 最终失败，原因：{}",
-                        label,
-                        attempt + 1,
-                        url,
-                        e
-                    );
-                }
-            }
-            Ok(resp) => {
-                debug!(
-                    "⚠️ {} 第 {} 次请求 {} 返回非成功状态：{}",
-                    label,
-                    attempt + 1,
-                    url,
-                    resp.status()
-                );
-                if attempt < max_retries {
-                    debug!("{} 返回状态异常，等待 {:?} 后重试...", label, backoff);
-                    sleep(backoff).await;
-                    backoff *= 2;
-                } else {
-                    debug!(
-                        "❌ {} 第 {} 次请求 {} 最终返回非成功状态：{}",
-                        label,
-                        attempt + 1,
-                        url,
-                        resp.status()
-                    );
-                }
             }
         }
+
+        if attempt < max_retries {
+            debug!("{} 正在等待 {:?} 后重试...", label, backoff);
+            sleep(backoff).await;
+            backoff *= 2; // 指数退避
+        } else {
+            debug!("❌ {} 第 {} 次请求 {} 最终失败", label, attempt + 1, url);
+        }
         attempt += 1;
     }
 
-    None
+    probe
 }
 
 /// 合并多个测试结果为一个整体测试统计。
@@ -363,46 +779,315 @@ fn merge_test_results(results: &[QualityTestResults]) -> QualityTestResults {
     let mut merged = QualityTestResults::new(total_tests);
 
     for r in results {
-        merged.successes.extend(&r.successes);
+        merged.successes += r.successes;
+        merged.latencies.extend(&r.latencies);
         merged.failures += r.failures;
+        merged.checks.extend(r.checks.iter().cloned());
     }
 
     merged
 }
 
-/// 根据配置权重计算代理最终综合评分。
-///
-/// 若某一维度值缺失，将其视为 0 参与加权。
+/// 使用评分模型计算代理最终综合评分。
 ///
 /// # 参数
-/// - `proxy`: 已评估的代理结果（字段将被修改）
-/// - `config`: 权重配置
-fn compute_score(proxy: &mut ProxyCheckResult, config: &QualityConfig) {
-    let speed_score = speed_to_score(proxy.speed.unwrap_or(f64::MAX));
-    let success = proxy.success_rate.unwrap_or(0.0);
-    let stability = proxy.stability.unwrap_or(0.0);
-
-    proxy.score = Some(
-        (speed_score * config.speed_weight
-            + success * config.success_weight
-            + stability * config.stability_weight)
-            .clamp(0.0, 1.0),
-    );
+/// - `proxy`: 已评估的代理结果（`score` 字段将被修改）
+/// - `model`: 评分模型，默认为按 `[scoring]` 权重加权的 [`scoring::WeightedModel`]
+pub fn compute_score(proxy: &mut ProxyCheckResult, model: &dyn ScoringModel) {
+    proxy.score = Some(model.score(proxy).clamp(0.0, 1.0));
+}
+
+/// 使用给定的评分模型重新计算存储中全部代理的评分，不重新测速。
+///
+/// # 返回
+/// 评分发生变化并已写回的代理数量。
+pub async fn rescore_all(model: &dyn ScoringModel) -> Result<usize> {
+    let proxies = get_storage().list_all_proxies().await?;
+    let total = proxies.len();
+
+    let mut changed = Vec::new();
+    for proxy in proxies {
+        let mut result = proxy.result();
+        compute_score(&mut result, model);
+        if result.score != proxy.score {
+            changed.push(Proxy::from_parts(proxy.basic(), result));
+        }
+    }
+    get_storage().upsert_many(&changed).await?;
+
+    info!("📊 重新评分完成：共 {} 条，评分变化 {} 条", total, changed.len());
+    Ok(changed.len())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::db;
-    use crate::model::ProxyBasic;
+    use crate::db::manager::ProxyStorage;
+    use crate::db::memory::MemoryStorage;
+    use crate::model::{Anonymity, CheckSource, EchoResponse, Protocol, ProxyBasic, ProxyCheck, ScoringConfig, TargetProfile};
+    use crate::service::scoring::WeightedModel;
     use crate::service::quality::QualityConfig;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn echo(origin: &str, headers: &[(&str, &str)]) -> EchoResponse {
+        EchoResponse {
+            origin: origin.to_string(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_classify_anonymity() {
+        let real = "8.8.8.8";
+        let transparent = echo("1.1.1.1", &[("X-Forwarded-For", "8.8.8.8")]);
+        assert_eq!(super::classify_anonymity(real, &transparent), Anonymity::Transparent);
+
+        let leaked_origin = echo("8.8.8.8, 1.1.1.1", &[]);
+        assert_eq!(super::classify_anonymity(real, &leaked_origin), Anonymity::Transparent);
+
+        let anonymous = echo("1.1.1.1", &[("via", "1.1 squid")]);
+        assert_eq!(super::classify_anonymity(real, &anonymous), Anonymity::Anonymous);
+
+        let elite = echo("1.1.1.1", &[("User-Agent", "test")]);
+        assert_eq!(super::classify_anonymity(real, &elite), Anonymity::Elite);
+    }
+
+    #[test]
+    fn test_compute_score_anonymity_weight() {
+        let config = WeightedModel::new(ScoringConfig::default());
+        let mut result = crate::model::ProxyCheckResult {
+            speed: Some(0.1),
+            success_rate: Some(1.0),
+            stability: Some(1.0),
+            ..Default::default()
+        };
+
+        // 未检测匿名等级时匿名权重不参与加权
+        super::compute_score(&mut result, &config);
+        let without = result.score.unwrap();
+        assert!((without - 1.0).abs() < 1e-9);
+
+        result.anonymity = Some(Anonymity::Transparent);
+        super::compute_score(&mut result, &config);
+        let transparent = result.score.unwrap();
+        assert!(transparent < without);
+
+        result.anonymity = Some(Anonymity::Elite);
+        super::compute_score(&mut result, &config);
+        assert!(result.score.unwrap() > transparent);
+        assert!((result.score.unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_speed_score_units() {
+        let config = ScoringConfig::default();
+        let model = WeightedModel::new(config.clone());
+        let score = |seconds: f64| {
+            let mut result = crate::model::ProxyCheckResult {
+                speed: Some(seconds),
+                success_rate: Some(1.0),
+                stability: Some(1.0),
+                anonymity: Some(Anonymity::Elite),
+                ..Default::default()
+            };
+            super::compute_score(&mut result, &model);
+            result.score.unwrap()
+        };
+
+        assert!((score(0.2) - 1.0).abs() < 1e-9);
+        assert!(score(0.2) > score(0.8));
+        assert!(score(0.8) > score(3.0));
+        // 速度得 0 分，其余三项满分：(0.3 + 0.3 + 0.2) / 1.2
+        assert!((score(6.0) - 0.8 / 1.2).abs() < 1e-9);
+
+        let slow = crate::common::utils::speed_to_score(Some(config.speed_slow_ms), &config);
+        assert!((slow - crate::common::utils::SLOW_SPEED_SCORE).abs() < 1e-9);
+
+        // 几毫秒的响应时间按毫秒保留，不会被舍为 0 而得 0 分；没有测得响应时间时速度记 0 分
+        let mut fast = super::QualityTestResults::new(2);
+        fast.record_success(Some(0.004));
+        fast.record_success(Some(0.0002));
+        assert_eq!(fast.average_speed(), Some(0.002));
+        assert!((score(0.002) - 1.0).abs() < 1e-9);
+        assert!((score(0.0) - 1.0).abs() < 1e-9);
+        assert_eq!(crate::common::utils::speed_to_score(None, &config), 0.0);
+    }
+
+    #[test]
+    fn test_latency_stability() {
+        let mut steady = super::QualityTestResults::new(3);
+        let mut jittery = super::QualityTestResults::new(3);
+        for d in [0.5, 0.5, 0.5] {
+            steady.record_success(Some(d));
+        }
+        for d in [0.1, 0.5, 1.5] {
+            jittery.record_success(Some(d));
+        }
+        assert_eq!(steady.stability(), Some(1.0));
+        assert!(jittery.stability().unwrap() < 0.7);
+
+        let mut single = super::QualityTestResults::new(2);
+        single.record_success(Some(0.5));
+        single.record_failure();
+        assert_eq!(single.stability(), None);
+
+        let mut failed = super::QualityTestResults::new(1);
+        failed.record_failure();
+        assert_eq!(failed.stability(), Some(0.0));
+
+        let mut unknown = super::QualityTestResults::new(2);
+        unknown.record_success(None);
+        unknown.record_success(None);
+        assert_eq!((unknown.success_rate(), unknown.stability()), (1.0, None));
+        assert_eq!(unknown.average_speed(), None);
+    }
+
+    #[test]
+    fn test_blend_stability() {
+        let now = chrono::Utc::now().naive_utc();
+        let half_life = std::time::Duration::from_secs(3600);
+        let hour_ago = Some(now - chrono::Duration::hours(1));
+
+        assert_eq!(super::blend_stability(Some(1.0), hour_ago, Some(0.0), now, half_life), 0.5);
+        assert_eq!(super::blend_stability(Some(1.0), Some(now), Some(0.0), now, half_life), 1.0);
+        assert_eq!(super::blend_stability(Some(0.8), hour_ago, None, now, half_life), 0.8);
+        assert_eq!(super::blend_stability(None, None, Some(0.9), now, half_life), 0.9);
+        assert_eq!(super::blend_stability(None, None, None, now, half_life), 0.5);
+        assert_eq!(super::blend_stability(Some(1.0), hour_ago, Some(0.2), now, std::time::Duration::ZERO), 0.2);
+    }
+
+    #[test]
+    fn test_sample_count() {
+        let weighted = ScoringConfig::default();
+        assert_eq!(super::sample_count(1, &weighted), 2);
+        assert_eq!(super::sample_count(3, &weighted), 3);
+
+        let logistic = ScoringConfig { model: crate::model::ScoringModelKind::Logistic, ..ScoringConfig::default() };
+        assert_eq!(super::sample_count(1, &logistic), 2);
+
+        // 不计稳定性时保留验证等级的测试次数，单次测试算不出稳定性
+        let unweighted = ScoringConfig { success_weight: 0.6, stability_weight: 0.0, ..ScoringConfig::default() };
+        assert_eq!(super::sample_count(1, &unweighted), 1);
+        let mut single = super::QualityTestResults::new(1);
+        single.record_success(Some(0.2));
+        assert_eq!(single.stability(), None);
+    }
+
+    #[test]
+    fn test_target_check() {
+        let plain = super::Target::new(TargetProfile::new("plain", "https://example.com")).unwrap();
+        assert!(plain.status_ok(204));
+        assert!(!plain.status_ok(302));
+        assert!(!plain.needs_body());
+
+        let profile = TargetProfile {
+            expected_status: Some(403),
+            body_contains: Some("blocked".into()),
+            body_regex: Some(r"id=\d+".into()),
+            ..TargetProfile::new("strict", "https://example.com")
+        };
+        let strict = super::Target::new(profile).unwrap();
+        assert!(strict.status_ok(403));
+        assert!(!strict.status_ok(200));
+        assert!(strict.needs_body());
+        assert!(strict.body_ok(b"blocked, id=42"));
+        assert!(!strict.body_ok(b"blocked, id=x"));
+        assert!(!strict.body_ok(b"id=42"));
+
+        let profile = TargetProfile {
+            body_sha256: Some("2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824".into()),
+            ..TargetProfile::new("hash", "https://example.com")
+        };
+        let hashed = super::Target::new(profile).unwrap();
+        assert!(hashed.body_ok(b"hello"));
+        assert!(!hashed.body_ok(b"hello<script>ad()</script>"));
+
+        let profile = TargetProfile {
+            json_field: Some("/args/n".into()),
+            json_equals: Some("1".into()),
+            ..TargetProfile::new("json", "https://example.com")
+        };
+        let json = super::Target::new(profile).unwrap();
+        assert!(json.body_ok(br#"{"args": {"n": 1}}"#));
+        assert!(!json.body_ok(br#"{"args": {"n": 2}}"#));
+        assert!(!json.body_ok(br#"{"args": {}}"#));
+        assert!(!json.body_ok(b"<html>portal</html>"));
+
+        let invalid = TargetProfile { body_regex: Some("(".into()), ..TargetProfile::new("bad", "https://example.com") };
+        assert!(super::Target::new(invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_target_check_body_too_large() {
+        use crate::model::CheckErrorKind;
+        use sha2::Digest;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 返回 16 字节固定内容的本地 HTTP 服务
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\n0123456789abcdef").await;
+            }
+        });
+
+        let hash = hex::encode(sha2::Sha256::digest(b"0123456789abcdef"));
+        let check = |max_body_size: Option<usize>| {
+            let profile = TargetProfile { body_sha256: Some(hash.clone()), max_body_size, ..TargetProfile::new("hash", &url) };
+            let target = super::Target::new(profile).unwrap();
+            let url = url.clone();
+            async move { target.check(reqwest::get(&url).await.unwrap()).await }
+        };
+        assert_eq!(check(None).await, Ok(()));
+        assert_eq!(check(Some(16)).await, Ok(()));
+        assert_eq!(check(Some(8)).await, Err(CheckErrorKind::BodyTooLarge));
+    }
+
+    #[test]
+    fn test_classify_protocol() {
+        let check = |target: &str, success: bool, status_code: Option<i32>| ProxyCheck {
+            ip: "1.1.1.1".into(),
+            port: "80".into(),
+            checked_at: Utc::now().naive_utc(),
+            source: CheckSource::Verify,
+            target: target.into(),
+            success,
+            latency: None,
+            status_code,
+            error_kind: None,
+        };
+        let classify = super::classify_protocol;
+
+        let connected = [check("http://a.com", true, Some(200)), check("https://b.com", false, Some(403))];
+        assert_eq!(classify(Protocol::Http, &connected), Protocol::Https);
+        let refused = [check("http://a.com", true, Some(200)), check("https://b.com", false, None)];
+        assert_eq!(classify(Protocol::Https, &refused), Protocol::Http);
+        assert_eq!(classify(Protocol::Http, &refused[..1]), Protocol::Http);
+        assert_eq!(classify(Protocol::Https, &refused[..1]), Protocol::Https);
+        assert_eq!(classify(Protocol::Socks5, &refused), Protocol::Socks5);
+    }
 
     #[tokio::test]
     async fn test_evaluate() {
-        db::init().await.unwrap();
-        let basic = ProxyBasic::new("127.0.0.1", "12334");
-        let config = QualityConfig::default();
+        // 端口上没有代理服务，所有请求均失败
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        drop(listener);
+        let basic = ProxyBasic::new("127.0.0.1", &port);
+        let config = QualityConfig::for_test("http://example.com/");
+
+        let storage = MemoryStorage::new();
+        let old = crate::model::Proxy { anonymity: Some(Anonymity::Elite), ..crate::model::Proxy::new(basic.ip.clone(), port.clone()) };
+        storage.upsert_many(&[old]).await.unwrap();
 
-        let proxy = super::evaluate(&basic, &config).await.unwrap();
-        assert_eq!(proxy.ip, basic.ip);
+        let report = super::evaluate(&basic, &config, &storage).await.unwrap();
+        assert_eq!(report.proxy.ip, basic.ip);
+        assert_eq!(report.proxy.success_rate, Some(0.0));
+        assert_eq!(report.proxy.anonymity, Some(Anonymity::Elite));
+        assert_eq!(report.checks.len() as u64, config.targets.len() as u64 * config.test_count);
+        assert_eq!(report.sites.len(), config.targets.len());
     }
 }
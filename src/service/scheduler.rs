@@ -0,0 +1,267 @@
+//! # scheduler 模块
+//!
+//! 内置的定时任务调度器，按 `[schedule]` 配置中的 cron 表达式周期执行：
+//!
+//! - 代理采集：抓取所有代理源并批量验证入库；
+//! - 存活复检：重新验证数据库中已有的代理。
+//!
+//! 两者都通过 [`crate::service::job`] 以后台任务的形式运行，与接口手动触发的任务共享去重与进度。
+//!
+//! 每个任务独立调度，同一任务上一轮未结束时新一轮会被跳过，避免重叠执行；
+//! 每个任务的最近一次运行状态都会被记录，可通过 [`status`] 查询。
+
+use crate::model::APP_CONFIG;
+use crate::service::job::{JobKind, JobState, JOBS};
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDateTime, Utc};
+use cron::Schedule;
+use futures::future::BoxFuture;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// 定时任务执行体，返回本次执行的结果摘要。
+type Job = Arc<dyn Fn() -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// 全局调度器中注册的定时任务。
+static TASKS: OnceCell<Vec<Arc<ScheduledTask>>> = OnceCell::new();
+
+/// 定时任务的运行状态。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskStatus {
+    /// 任务名称。
+    pub name: String,
+    /// 任务的 cron 表达式。
+    pub cron: String,
+    /// 当前是否正在执行。
+    pub running: bool,
+    /// 累计执行次数。
+    pub runs: u64,
+    /// 因上一轮尚未结束而跳过的次数。
+    pub skipped: u64,
+    /// 最近一次开始执行的时间（UTC）。
+    pub last_started: Option<NaiveDateTime>,
+    /// 最近一次执行结束的时间（UTC）。
+    pub last_finished: Option<NaiveDateTime>,
+    /// 最近一次执行是否成功。
+    pub last_success: Option<bool>,
+    /// 最近一次执行的结果摘要或错误信息。
+    pub last_message: Option<String>,
+    /// 下一次计划执行的时间（本地时间）。
+    pub next_run: Option<NaiveDateTime>,
+}
+
+/// 单个定时任务：cron 计划、执行体、防重入标记与运行状态。
+pub struct ScheduledTask {
+    name: String,
+    schedule: Schedule,
+    job: Job,
+    running: AtomicBool,
+    status: Mutex<TaskStatus>,
+}
+
+impl ScheduledTask {
+    /// 根据名称、cron 表达式与执行体创建定时任务。
+    ///
+    /// # 错误
+    /// cron 表达式无法解析时返回错误。
+    pub fn new(name: &str, cron: &str, job: Job) -> Result<Self> {
+        let schedule = Schedule::from_str(cron)
+            .map_err(|e| anyhow!("定时任务 {} 的 cron 表达式不合法：{}，{}", name, cron, e))?;
+        let status = TaskStatus { name: name.to_string(), cron: cron.to_string(), ..Default::default() };
+
+        Ok(Self { name: name.to_string(), schedule, job, running: AtomicBool::new(false), status: Mutex::new(status) })
+    }
+
+    /// 当前运行状态的快照。
+    pub fn status(&self) -> TaskStatus {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        status.running = self.running.load(Ordering::SeqCst);
+        status.next_run = self.schedule.upcoming(Local).next().map(|t| t.naive_local());
+        status
+    }
+
+    /// 执行一次任务。
+    ///
+    /// 若上一轮仍在执行，则本次直接跳过并返回 `false`。
+    pub async fn run_once(&self) -> bool {
+        let name = &self.name;
+        if self.running.swap(true, Ordering::SeqCst) {
+            warn!("⏭️ 定时任务 {} 上一轮尚未结束，跳过本次执行", name);
+            self.update(|s| s.skipped += 1);
+            return false;
+        }
+        let _running = RunningGuard(&self.running);
+
+        info!("⏰ 定时任务 {} 开始执行", name);
+        self.update(|s| {
+            s.runs += 1;
+            s.last_started = Some(Utc::now().naive_utc());
+        });
+
+        let result = (self.job)().await;
+
+        match &result {
+            Ok(msg) => info!("✅ 定时任务 {} 执行完成：{}", name, msg),
+            Err(e) => error!("❌ 定时任务 {} 执行失败：{}", name, e),
+        }
+        self.update(|s| {
+            s.last_finished = Some(Utc::now().naive_utc());
+            s.last_success = Some(result.is_ok());
+            s.last_message = Some(match &result {
+                Ok(msg) => msg.clone(),
+                Err(e) => e.to_string(),
+            });
+        });
+        true
+    }
+
+    /// 按 cron 计划循环调度，每次触发时在独立任务中执行，
+    /// 因此执行时间超过调度间隔时，后续触发会被跳过而不是排队。
+    async fn run_forever(self: Arc<Self>) {
+        loop {
+            let Some(next) = self.schedule.upcoming(Local).next() else {
+                warn!("定时任务 {} 没有后续执行时间，停止调度", self.name);
+                return;
+            };
+
+            let wait = (next - Local::now()).to_std().unwrap_or_default();
+            sleep(wait).await;
+
+            let task = Arc::clone(&self);
+            tokio::spawn(async move {
+                task.run_once().await;
+            });
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TaskStatus)) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut status);
+    }
+}
+
+/// 离开作用域时清除运行标记，执行体 panic 时也不会导致任务被永久跳过。
+struct RunningGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 以后台任务的形式执行并等待结束，使定时触发的运行同样可通过 `/jobs` 查询。
+fn background_job(kind: JobKind) -> Job {
+    Arc::new(move || {
+        Box::pin(async move {
+            let id = JOBS.submit(kind);
+            let job = JOBS.wait(id).await.ok_or_else(|| anyhow!("后台任务 #{} 不存在", id))?;
+            let progress = job.progress;
+            match job.state {
+                JobState::Failed => Err(anyhow!("后台任务 #{} 失败：{}", id, job.error.unwrap_or_default())),
+                JobState::Cancelled => Err(anyhow!("后台任务 #{} 已取消", id)),
+                _ => Ok(format!("后台任务 #{}：验证 {} 条，通过 {} 条", id, progress.total, progress.success)),
+            }
+        })
+    })
+}
+
+/// 根据配置启动调度器，程序启动时调用一次即可。
+///
+/// 未配置任何 cron 表达式时不启动任何任务。
+///
+/// # 错误
+/// cron 表达式不合法或重复启动时返回错误。
+pub fn start() -> Result<()> {
+    let config = &APP_CONFIG.schedule;
+    let mut tasks = Vec::new();
+
+    if let Some(cron) = &config.collect_cron {
+        tasks.push(Arc::new(ScheduledTask::new("collect", cron, background_job(JobKind::Collection))?));
+    }
+    if let Some(cron) = &config.verify_cron {
+        tasks.push(Arc::new(ScheduledTask::new("verify", cron, background_job(JobKind::Verification))?));
+    }
+
+    for task in &tasks {
+        let status = task.status();
+        info!("📅 定时任务 {} 已启用：{}，下次执行：{:?}", status.name, status.cron, status.next_run);
+        tokio::spawn(Arc::clone(task).run_forever());
+    }
+
+    TASKS.set(tasks).map_err(|_| anyhow!("调度器已启动"))?;
+    Ok(())
+}
+
+/// 所有定时任务的运行状态，调度器未启动时为空。
+pub fn status() -> Vec<TaskStatus> {
+    TASKS.get().map(|tasks| tasks.iter().map(|t| t.status()).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn sleeping_job(ms: u64, fail: bool) -> Job {
+        Arc::new(move || {
+            Box::pin(async move {
+                sleep(Duration::from_millis(ms)).await;
+                if fail { Err(anyhow!("模拟失败")) } else { Ok("done".to_string()) }
+            })
+        })
+    }
+
+    #[test]
+    fn test_invalid_cron() {
+        assert!(ScheduledTask::new("bad", "every minute", sleeping_job(0, false)).is_err());
+        assert!(ScheduledTask::new("ok", "0 */5 * * * *", sleeping_job(0, false)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_once_prevents_overlap() {
+        let task = Arc::new(ScheduledTask::new("test", "0 * * * * *", sleeping_job(100, false)).unwrap());
+
+        let first = tokio::spawn({
+            let task = Arc::clone(&task);
+            async move { task.run_once().await }
+        });
+        sleep(Duration::from_millis(20)).await;
+        assert!(task.status().running);
+        assert!(!task.run_once().await);
+        assert!(first.await.unwrap());
+
+        let status = task.status();
+        assert!(!status.running);
+        assert_eq!(status.runs, 1);
+        assert_eq!(status.skipped, 1);
+        assert_eq!(status.last_success, Some(true));
+        assert!(status.next_run.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_run_once_records_failure() {
+        let task = ScheduledTask::new("test", "0 * * * * *", sleeping_job(0, true)).unwrap();
+        assert!(task.run_once().await);
+
+        let status = task.status();
+        assert_eq!(status.last_success, Some(false));
+        assert_eq!(status.last_message.as_deref(), Some("模拟失败"));
+    }
+
+    #[tokio::test]
+    async fn test_run_once_recovers_from_panic() {
+        let job: Job = Arc::new(|| Box::pin(async { panic!("模拟 panic") }));
+        let task = Arc::new(ScheduledTask::new("test", "0 * * * * *", job).unwrap());
+        let panicked = tokio::spawn({
+            let task = Arc::clone(&task);
+            async move { task.run_once().await }
+        });
+        assert!(panicked.await.is_err());
+        assert!(!task.status().running);
+    }
+}
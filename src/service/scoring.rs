@@ -0,0 +1,413 @@
+//! # scoring 模块
+//!
+//! 可插拔的评分模型：[`ScoringModel`] 根据代理的质量指标给出 0.0~1.0 的综合评分，
+//! 由 [`quality::compute_score`](crate::service::quality::compute_score) 调用。
+//!
+//! - [`WeightedModel`]：默认模型，按 `[scoring]` 权重对速度、成功率、稳定性、匿名等级加权；
+//! - [`LogisticModel`]：逻辑回归模型，从检测历史（含客户端反馈）离线训练，保存为 JSON 模型文件。
+//!
+//! 训练样本由检测历史按时间回放得到：以某次检测之前最近若干次检测统计出的成功率、速度、稳定性为特征，
+//! 以该次检测是否成功为标签，即“根据当前指标预测下一次能否成功”。
+//! [`compare`] 在按时间划分出的验证集上比较各模型的预测效果。
+
+use crate::common::utils::speed_to_score;
+use crate::model::{ProxyCheck, ProxyCheckResult, ScoringConfig, ScoringModelKind, APP_CONFIG};
+use crate::service::quality::QualityTestResults;
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// 构造训练样本时回看的历史检测次数。
+const HISTORY_WINDOW: usize = 20;
+/// 构造训练样本时至少需要的历史检测次数。
+const MIN_HISTORY: usize = 3;
+/// 训练集占全部样本的比例，其余（时间上最新的）样本用于验证。
+const TRAIN_RATIO: f64 = 0.8;
+/// 训练所需的最少样本数。
+const MIN_SAMPLES: usize = 20;
+
+/// 评分模型。
+pub trait ScoringModel: Debug + Send + Sync {
+    /// 模型名称，用于日志与比较报告。
+    fn name(&self) -> &str;
+
+    /// 根据质量指标计算综合评分，范围 0.0~1.0。
+    fn score(&self, result: &ProxyCheckResult) -> f64;
+}
+
+/// 当前配置的评分模型。
+///
+/// `[scoring] model = "logistic"` 时从 `model_file` 加载，加载失败时回退到加权模型。
+static ACTIVE_MODEL: Lazy<Arc<dyn ScoringModel>> = Lazy::new(|| {
+    let config = &APP_CONFIG.scoring;
+    match config.model {
+        ScoringModelKind::Weighted => Arc::new(WeightedModel::new(config.clone())),
+        ScoringModelKind::Logistic => match LogisticModel::load(&config.model_file) {
+            Ok(model) => {
+                info!("📈 已加载评分模型 {}（{} 个训练样本）", config.model_file, model.samples);
+                Arc::new(model)
+            }
+            Err(e) => {
+                error!("❌ 加载评分模型 {} 失败，回退到加权评分：{:#}", config.model_file, e);
+                Arc::new(WeightedModel::new(config.clone()))
+            }
+        },
+    }
+});
+
+/// 获取当前配置的评分模型。
+pub fn active_model() -> Arc<dyn ScoringModel> {
+    Arc::clone(&ACTIVE_MODEL)
+}
+
+/// 加权评分模型（默认）。
+///
+/// 速度、成功率、稳定性若缺失，均视为 0 参与加权；
+/// 匿名等级缺失（如未配置回显接口）时不参与加权，其权重也不计入总权重，评分上限仍为 1.0。
+#[derive(Debug, Clone)]
+pub struct WeightedModel {
+    config: ScoringConfig,
+}
+
+impl WeightedModel {
+    pub fn new(config: ScoringConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl ScoringModel for WeightedModel {
+    fn name(&self) -> &str {
+        "weighted"
+    }
+
+    fn score(&self, result: &ProxyCheckResult) -> f64 {
+        let config = &self.config;
+        // speed 以秒存储，速度曲线的分段以毫秒为单位
+        let speed_score = speed_to_score(result.speed.map(|s| s * 1000.0), config);
+        let success = result.success_rate.unwrap_or(0.0);
+        let stability = result.stability.unwrap_or(0.0);
+
+        let mut total =
+            speed_score * config.speed_weight + success * config.success_weight + stability * config.stability_weight;
+        let mut weights = config.speed_weight + config.success_weight + config.stability_weight;
+        if let Some(anonymity) = result.anonymity {
+            total += anonymity.score() * config.anonymity_weight;
+            weights += config.anonymity_weight;
+        }
+
+        let score = if weights > 0.0 { total / weights } else { 0.0 };
+        score.clamp(0.0, 1.0)
+    }
+}
+
+/// 逻辑回归使用的特征数量。
+const FEATURES: usize = 3;
+
+/// 提取模型特征：成功率、稳定性、速度因子 `1 / (1 + 秒数)`，缺失时为 0。
+///
+/// 检测历史中没有匿名等级，因此不作为特征。
+fn features(result: &ProxyCheckResult) -> [f64; FEATURES] {
+    [
+        result.success_rate.unwrap_or(0.0),
+        result.stability.unwrap_or(0.0),
+        result.speed.map_or(0.0, |s| 1.0 / (1.0 + s.max(0.0))),
+    ]
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+/// 逻辑回归评分模型，评分即预测的下一次检测成功概率。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticModel {
+    /// 各特征的系数，顺序见 [`features`]。
+    pub weights: [f64; FEATURES],
+    pub bias: f64,
+    /// 训练时间（UTC）。
+    pub trained_at: NaiveDateTime,
+    /// 训练样本数。
+    pub samples: usize,
+}
+
+impl LogisticModel {
+    /// 梯度下降训练逻辑回归（带 L2 正则）。
+    pub fn train(samples: &[Sample]) -> Self {
+        const EPOCHS: usize = 2000;
+        const LEARNING_RATE: f64 = 0.5;
+        const L2: f64 = 1e-3;
+
+        let mut weights = [0.0; FEATURES];
+        let mut bias = 0.0;
+        let n = samples.len().max(1) as f64;
+        let xs: Vec<[f64; FEATURES]> = samples.iter().map(|s| features(&s.result)).collect();
+
+        for _ in 0..EPOCHS {
+            let mut grad_w = [0.0; FEATURES];
+            let mut grad_b = 0.0;
+            for (x, sample) in xs.iter().zip(samples) {
+                let z = bias + weights.iter().zip(x).map(|(w, x)| w * x).sum::<f64>();
+                let error = sigmoid(z) - if sample.success { 1.0 } else { 0.0 };
+                for (g, x) in grad_w.iter_mut().zip(x) {
+                    *g += error * x;
+                }
+                grad_b += error;
+            }
+            for (w, g) in weights.iter_mut().zip(grad_w) {
+                *w -= LEARNING_RATE * (g / n + L2 * *w);
+            }
+            bias -= LEARNING_RATE * grad_b / n;
+        }
+
+        Self { weights, bias, trained_at: Utc::now().naive_utc(), samples: samples.len() }
+    }
+
+    /// 从 JSON 模型文件加载。
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).with_context(|| format!("读取模型文件 {} 失败", path.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 保存为 JSON 模型文件。
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl ScoringModel for LogisticModel {
+    fn name(&self) -> &str {
+        "logistic"
+    }
+
+    fn score(&self, result: &ProxyCheckResult) -> f64 {
+        let x = features(result);
+        sigmoid(self.bias + self.weights.iter().zip(x).map(|(w, x)| w * x).sum::<f64>())
+    }
+}
+
+/// 训练 / 验证样本。
+#[derive(Debug, Clone)]
+pub struct Sample {
+    /// 由该次检测之前的历史统计出的质量指标。
+    pub result: ProxyCheckResult,
+    /// 该次检测是否成功。
+    pub success: bool,
+    /// 该次检测的时间。
+    pub checked_at: NaiveDateTime,
+}
+
+/// 由检测历史构造样本，按检测时间正序返回。
+///
+/// 对每个代理按时间回放：某次检测之前至少有 [`MIN_HISTORY`] 次记录时，
+/// 用最近 [`HISTORY_WINDOW`] 次记录统计成功率、平均速度与稳定性作为特征。
+pub fn build_samples(checks: &[ProxyCheck]) -> Vec<Sample> {
+    let mut by_proxy: HashMap<(&str, &str), Vec<&ProxyCheck>> = HashMap::new();
+    for check in checks {
+        by_proxy.entry((&check.ip, &check.port)).or_default().push(check);
+    }
+
+    let mut samples = Vec::new();
+    for history in by_proxy.values_mut() {
+        history.sort_by_key(|c| c.checked_at);
+        for i in MIN_HISTORY..history.len() {
+            let window = &history[i.saturating_sub(HISTORY_WINDOW)..i];
+            let mut stats = QualityTestResults::new(window.len() as u64);
+            for check in window {
+                if check.success {
+                    stats.record_success(check.latency);
+                } else {
+                    stats.record_failure();
+                }
+            }
+
+            let success_rate = stats.success_rate();
+            let result = ProxyCheckResult {
+                speed: stats.average_speed(),
+                success_rate: Some(success_rate),
+                stability: stats.stability(),
+                ..Default::default()
+            };
+            samples.push(Sample { result, success: history[i].success, checked_at: history[i].checked_at });
+        }
+    }
+
+    samples.sort_by_key(|s| s.checked_at);
+    samples
+}
+
+/// 模型在验证集上的表现。
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub model: String,
+    pub samples: usize,
+    /// 对数损失，越小越好。
+    pub log_loss: f64,
+    /// Brier 分数（预测概率与实际结果的均方误差），越小越好。
+    pub brier: f64,
+    /// 以 0.5 为阈值的准确率。
+    pub accuracy: f64,
+}
+
+/// 将评分视为成功概率，评估模型在样本上的预测效果。
+pub fn evaluate_model(model: &dyn ScoringModel, samples: &[Sample]) -> Evaluation {
+    const EPSILON: f64 = 1e-6;
+    let n = samples.len().max(1) as f64;
+    let (mut log_loss, mut brier, mut correct) = (0.0, 0.0, 0);
+
+    for sample in samples {
+        let p = model.score(&sample.result).clamp(EPSILON, 1.0 - EPSILON);
+        let y = if sample.success { 1.0 } else { 0.0 };
+        log_loss -= y * p.ln() + (1.0 - y) * (1.0 - p).ln();
+        brier += (p - y).powi(2);
+        if (p >= 0.5) == sample.success {
+            correct += 1;
+        }
+    }
+
+    Evaluation {
+        model: model.name().to_string(),
+        samples: samples.len(),
+        log_loss: log_loss / n,
+        brier: brier / n,
+        accuracy: correct as f64 / n,
+    }
+}
+
+/// 按时间将样本划分为训练集与验证集（验证集为最新的一部分）。
+pub fn split_samples(samples: &[Sample]) -> Result<(&[Sample], &[Sample])> {
+    if samples.len() < MIN_SAMPLES {
+        bail!("检测历史样本不足：需要至少 {} 条，当前 {} 条", MIN_SAMPLES, samples.len());
+    }
+    let train = ((samples.len() as f64 * TRAIN_RATIO) as usize).clamp(1, samples.len() - 1);
+    Ok(samples.split_at(train))
+}
+
+/// 在同一验证集上比较多个模型。
+pub fn compare(models: &[&dyn ScoringModel], samples: &[Sample]) -> Vec<Evaluation> {
+    models.iter().map(|model| evaluate_model(*model, samples)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::manager::ProxyStorage;
+    use crate::db::memory::MemoryStorage;
+    use crate::model::{Anonymity, CheckSource, ProxyBasic};
+
+    fn check(port: &str, minute: i64, success: bool, latency: f64) -> ProxyCheck {
+        ProxyCheck {
+            ip: "127.0.0.1".into(),
+            port: port.into(),
+            checked_at: NaiveDateTime::default() + chrono::Duration::minutes(minute),
+            source: CheckSource::Verify,
+            target: String::new(),
+            success,
+            latency: success.then_some(latency),
+            status_code: None,
+            error_kind: None,
+        }
+    }
+
+    /// 一半代理稳定可用，另一半基本不可用。
+    fn history() -> Vec<ProxyCheck> {
+        let mut checks = Vec::new();
+        for p in 0..10 {
+            let good = p % 2 == 0;
+            for m in 0..12 {
+                let success = if good { m % 6 != 5 } else { m % 6 == 0 };
+                checks.push(check(&p.to_string(), m, success, 0.3 + 0.01 * m as f64));
+            }
+        }
+        checks
+    }
+
+    #[test]
+    fn test_build_samples() {
+        let samples = build_samples(&history());
+        assert_eq!(samples.len(), 10 * (12 - MIN_HISTORY));
+        assert!(samples.windows(2).all(|w| w[0].checked_at <= w[1].checked_at));
+        assert!(samples.iter().all(|s| s.result.success_rate.is_some()));
+    }
+
+    #[test]
+    fn test_build_samples_without_latency() {
+        // 反馈记录的成功请求可能没有耗时，仍应计为成功
+        let checks: Vec<ProxyCheck> =
+            (0..MIN_HISTORY as i64 + 1).map(|m| ProxyCheck { latency: None, ..check("1", m, true, 0.0) }).collect();
+        let samples = build_samples(&checks);
+        assert_eq!(samples.len(), 1);
+        assert_eq!((samples[0].result.success_rate, samples[0].result.speed), (Some(1.0), None));
+    }
+
+    #[test]
+    fn test_logistic_beats_constant() {
+        let samples = build_samples(&history());
+        let (train, test) = split_samples(&samples).unwrap();
+        let model = LogisticModel::train(train);
+
+        let good = ProxyCheckResult { success_rate: Some(0.9), stability: Some(0.9), speed: Some(0.3), ..Default::default() };
+        let bad = ProxyCheckResult { success_rate: Some(0.1), stability: Some(0.0), speed: Some(0.3), ..Default::default() };
+        assert!(model.score(&good) > model.score(&bad));
+
+        let report = evaluate_model(&model, test);
+        assert!(report.accuracy > 0.7);
+        assert!(report.log_loss < std::f64::consts::LN_2);
+    }
+
+    #[tokio::test]
+    async fn test_train_after_removal() {
+        // 失效代理被删除后其检测记录仍保留，训练集中仍有失败样本
+        let storage = MemoryStorage::new();
+        let checks = history();
+        storage.insert_checks(&checks).await.unwrap();
+        let bad: Vec<ProxyBasic> = (0..10).filter(|p| p % 2 == 1).map(|p| ProxyBasic::new("127.0.0.1", &p.to_string())).collect();
+        storage.remove_many(&bad).await.unwrap();
+
+        let samples = build_samples(&storage.list_all_checks().await.unwrap());
+        assert_eq!(samples.len(), build_samples(&checks).len());
+        let failures = samples.iter().filter(|s| !s.success).count();
+        assert!(failures > samples.len() / 3);
+
+        let (train, _) = split_samples(&samples).unwrap();
+        let model = LogisticModel::train(train);
+        let bad = ProxyCheckResult { success_rate: Some(0.1), stability: Some(0.0), speed: Some(0.3), ..Default::default() };
+        assert!(model.score(&bad) < 0.5);
+    }
+
+    #[test]
+    fn test_weighted_model() {
+        let model = WeightedModel::new(ScoringConfig::default());
+        let result = ProxyCheckResult {
+            success_rate: Some(1.0),
+            stability: Some(1.0),
+            speed: Some(0.1),
+            anonymity: Some(Anonymity::Elite),
+            ..Default::default()
+        };
+        assert!((model.score(&result) - 1.0).abs() < 1e-9);
+        assert_eq!(model.score(&ProxyCheckResult::default()), 0.0);
+
+        // 未检测匿名等级时不因匿名权重压低评分
+        let unknown = ProxyCheckResult { anonymity: None, ..result.clone() };
+        assert!((model.score(&unknown) - 1.0).abs() < 1e-9);
+        let transparent = ProxyCheckResult { anonymity: Some(Anonymity::Transparent), ..result };
+        assert!(model.score(&transparent) < model.score(&unknown));
+    }
+
+    #[test]
+    fn test_split_samples() {
+        let samples = build_samples(&history());
+        let (train, test) = split_samples(&samples).unwrap();
+        assert_eq!(train.len() + test.len(), samples.len());
+        assert!(train.last().unwrap().checked_at <= test[0].checked_at);
+        assert!(split_samples(&samples[..5]).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! # selector 模块
+//!
+//! 代理选择器：按 [`SelectStrategy`] 从候选代理中挑选一个，供 `/proxy` 接口与代理网关共用。
+//!
+//! 轮询游标与最近使用时间保存在选择器内部，因此同一个选择器的多次调用之间是有状态的；
+//! 全局共享的实例为 [`SELECTOR`]，代理删除后通过 [`forget_removed`] 清除其使用记录。
+
+use crate::model::{Proxy, ProxyBasic, SelectStrategy};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// 评分加权时的最低权重，避免未评分的代理永远不被选中。
+const MIN_WEIGHT: f64 = 0.01;
+
+/// 全局共享的代理选择器。
+pub static SELECTOR: Lazy<Selector> = Lazy::new(Selector::default);
+
+/// 代理选择器。
+#[derive(Debug, Default)]
+pub struct Selector {
+    /// 轮询游标。
+    cursor: AtomicUsize,
+    /// 每个代理最近一次被选中的时间，按 IP、端口两级索引，查询时无需构造键。
+    last_used: Mutex<HashMap<String, HashMap<String, Instant>>>,
+}
+
+/// 代理从存储中删除后清除其使用记录，选择器尚未初始化时无需处理。
+pub fn forget_removed(removed: &[ProxyBasic]) {
+    if let Some(selector) = Lazy::get(&SELECTOR) {
+        selector.forget(removed);
+    }
+}
+
+impl Selector {
+    /// 按策略从候选代理中挑选一个，候选为空时返回 `None`。
+    ///
+    /// 每次选中都会记录使用时间，供 [`SelectStrategy::LeastRecentlyUsed`] 使用。
+    pub fn select<'a>(&self, strategy: SelectStrategy, candidates: &[&'a Proxy]) -> Option<&'a Proxy> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let mut last_used = self.last_used.lock().unwrap_or_else(|e| e.into_inner());
+        let index = match strategy {
+            SelectStrategy::Random => rand::rng().random_range(0..candidates.len()),
+            SelectStrategy::RoundRobin => self.cursor.fetch_add(1, Ordering::Relaxed) % candidates.len(),
+            SelectStrategy::WeightedByScore => {
+                let weights: Vec<f64> = candidates.iter().map(|p| weight(p)).collect();
+                let mut point = rand::rng().random_range(0.0..weights.iter().sum::<f64>());
+                weights
+                    .iter()
+                    .position(|w| {
+                        point -= w;
+                        point < 0.0
+                    })
+                    .unwrap_or(candidates.len() - 1)
+            }
+            SelectStrategy::LeastRecentlyUsed => {
+                // 从未使用过的代理优先，其次是最久未使用的
+                let mut oldest = (0, used_at(&last_used, candidates[0]));
+                for (i, proxy) in candidates.iter().enumerate().skip(1) {
+                    let Some(b) = oldest.1 else { break };
+                    match used_at(&last_used, proxy) {
+                        None => oldest = (i, None),
+                        Some(a) if a < b => oldest = (i, Some(a)),
+                        _ => {}
+                    }
+                }
+                oldest.0
+            }
+            SelectStrategy::Best => candidates
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| weight(a).total_cmp(&weight(b)))
+                .map(|(i, _)| i)
+                .unwrap_or(0),
+        };
+
+        let proxy = candidates[index];
+        last_used.entry(proxy.ip.clone()).or_default().insert(proxy.port.clone(), Instant::now());
+        Some(proxy)
+    }
+
+    /// 清除已删除代理的使用记录。
+    pub fn forget(&self, removed: &[ProxyBasic]) {
+        let mut last_used = self.last_used.lock().unwrap_or_else(|e| e.into_inner());
+        for proxy in removed {
+            if let Some(ports) = last_used.get_mut(&proxy.ip) {
+                ports.remove(&proxy.port);
+                if ports.is_empty() {
+                    last_used.remove(&proxy.ip);
+                }
+            }
+        }
+    }
+}
+
+/// 代理最近一次被选中的时间。
+fn used_at(last_used: &HashMap<String, HashMap<String, Instant>>, proxy: &Proxy) -> Option<Instant> {
+    last_used.get(&proxy.ip)?.get(&proxy.port).copied()
+}
+
+/// 代理的选择权重，即综合评分（至少为 [`MIN_WEIGHT`]）。
+fn weight(proxy: &Proxy) -> f64 {
+    proxy.score.unwrap_or(0.0).max(MIN_WEIGHT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies() -> Vec<Proxy> {
+        [("1", Some(0.2)), ("2", Some(0.9)), ("3", None)]
+            .into_iter()
+            .map(|(port, score)| {
+                let mut proxy = Proxy::new("127.0.0.1".into(), port.into());
+                proxy.score = score;
+                proxy
+            })
+            .collect()
+    }
+
+    fn ports(selector: &Selector, strategy: SelectStrategy, candidates: &[&Proxy], n: usize) -> Vec<String> {
+        (0..n).map(|_| selector.select(strategy, candidates).unwrap().port.clone()).collect()
+    }
+
+    #[test]
+    fn test_select_empty() {
+        assert!(Selector::default().select(SelectStrategy::Random, &[]).is_none());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        assert_eq!(ports(&Selector::default(), SelectStrategy::RoundRobin, &candidates, 4), ["1", "2", "3", "1"]);
+    }
+
+    #[test]
+    fn test_best() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        assert_eq!(ports(&Selector::default(), SelectStrategy::Best, &candidates, 2), ["2", "2"]);
+    }
+
+    #[test]
+    fn test_least_recently_used() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        let selector = Selector::default();
+        selector.select(SelectStrategy::Best, &candidates);
+        assert_eq!(ports(&selector, SelectStrategy::LeastRecentlyUsed, &candidates, 4), ["1", "3", "2", "1"]);
+    }
+
+    #[test]
+    fn test_forget() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        let selector = Selector::default();
+        ports(&selector, SelectStrategy::RoundRobin, &candidates, 3);
+        selector.forget(&[list[0].basic(), list[1].basic()]);
+        assert_eq!(selector.last_used.lock().unwrap()["127.0.0.1"].len(), 1);
+
+        selector.forget(&[list[2].basic()]);
+        assert!(selector.last_used.lock().unwrap().is_empty());
+        assert_eq!(ports(&selector, SelectStrategy::LeastRecentlyUsed, &candidates, 1), ["1"]);
+    }
+
+    #[test]
+    fn test_weighted_by_score() {
+        let list = proxies();
+        let candidates: Vec<&Proxy> = list.iter().collect();
+        let selected = ports(&Selector::default(), SelectStrategy::WeightedByScore, &candidates, 1000);
+        let count = |port: &str| selected.iter().filter(|p| *p == port).count();
+        assert!(count("2") > count("1"));
+        assert!(count("1") > count("3"));
+    }
+}
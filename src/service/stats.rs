@@ -0,0 +1,144 @@
+//! # stats 模块
+//!
+//! 根据代理在实际使用中的结果（如网关转发成功或失败）更新其统计数据。
+//!
+//! 成功率与速度采用指数滑动平均，新结果按 [`SMOOTHING`] 的比例计入，
+//! 更新后重新计算综合评分并写回存储。
+//!
+//! 网关的每次尝试与客户端反馈都会并发地更新统计数据，同一代理的读取、计算与写回按
+//! `(ip, port)` 加锁串行执行，避免并发结果互相覆盖。
+
+use crate::common::utils::round_ms;
+use crate::db::get_storage;
+use crate::db::manager::ProxyStorage;
+use crate::model::{Proxy, ProxyBasic};
+use crate::service::quality;
+use crate::service::scoring::{self, ScoringModel};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// 滑动平均中新结果所占的比例，越大越偏向最近的使用结果。
+const SMOOTHING: f64 = 0.2;
+
+type OutcomeLocks = Mutex<HashMap<(String, String), Arc<tokio::sync::Mutex<()>>>>;
+
+/// 正在更新统计数据的代理对应的锁，没有持有者或等待者时移除。
+static OUTCOME_LOCKS: Lazy<OutcomeLocks> = Lazy::new(Default::default);
+
+/// 单个代理的统计数据更新锁，离开作用域时释放，并在无人等待时清除对应的锁。
+struct OutcomeGuard {
+    key: (String, String),
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl OutcomeGuard {
+    async fn acquire(basic: &ProxyBasic) -> Self {
+        let key = (basic.ip.clone(), basic.port.clone());
+        let lock = {
+            let mut locks = OUTCOME_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+            Arc::clone(locks.entry(key.clone()).or_default())
+        };
+        Self { key, guard: Some(lock.lock_owned().await) }
+    }
+}
+
+impl Drop for OutcomeGuard {
+    fn drop(&mut self) {
+        let mut locks = OUTCOME_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        self.guard.take();
+        // 只剩表中的引用时说明没有其他等待者
+        if locks.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+/// 将一次使用结果应用到代理统计数据上，返回更新后的代理。
+///
+/// # 参数
+/// - `ok`: 本次使用是否成功
+/// - `latency`: 本次建立连接的耗时（秒），失败时通常为 `None`
+pub fn apply_outcome(proxy: &Proxy, ok: bool, latency: Option<f64>, model: &dyn ScoringModel) -> Proxy {
+    let mut result = proxy.result();
+
+    let outcome = if ok { 1.0 } else { 0.0 };
+    let success_rate = match result.success_rate {
+        Some(old) => old * (1.0 - SMOOTHING) + outcome * SMOOTHING,
+        None => outcome,
+    };
+    result.success_rate = Some(success_rate.clamp(0.0, 1.0));
+
+    if ok && let Some(latency) = latency {
+        let speed = match result.speed {
+            Some(old) => old * (1.0 - SMOOTHING) + latency * SMOOTHING,
+            None => latency,
+        };
+        result.speed = Some(round_ms(speed));
+    }
+
+    quality::compute_score(&mut result, model);
+    Proxy::from_parts(proxy.basic(), result)
+}
+
+/// 记录一次代理使用结果：更新存储中的统计数据（缓存中的副本合并缓冲后更新，见 [`cache_upsert_proxy`](crate::common::cache::cache_upsert_proxy)）。
+///
+/// 同一代理的并发调用依次执行，每次都基于上一次写回的结果计算。
+///
+/// # 返回
+/// 更新后的代理；代理已不在存储中时返回 `None`。
+pub async fn record_outcome(basic: &ProxyBasic, ok: bool, latency: Option<f64>) -> Result<Option<Proxy>> {
+    let _guard = OutcomeGuard::acquire(basic).await;
+    let Some(proxy) = get_storage().find_proxy_by_ip_port(&basic.ip, &basic.port).await? else {
+        return Ok(None);
+    };
+
+    let updated = apply_outcome(&proxy, ok, latency, scoring::active_model().as_ref());
+    get_storage().upsert_quality_proxy(&updated).await?;
+    Ok(Some(updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ScoringConfig;
+    use crate::service::scoring::WeightedModel;
+
+    fn config() -> WeightedModel {
+        WeightedModel::new(ScoringConfig::default())
+    }
+
+    #[test]
+    fn test_apply_outcome() {
+        let mut proxy = Proxy::new("127.0.0.1".into(), "8080".into());
+        proxy.success_rate = Some(1.0);
+        proxy.speed = Some(1.0);
+        proxy.stability = Some(0.5);
+
+        let failed = apply_outcome(&proxy, false, None, &config());
+        assert!((failed.success_rate.unwrap() - 0.8).abs() < 1e-9);
+        assert_eq!(failed.speed, Some(1.0));
+
+        let ok = apply_outcome(&failed, true, Some(0.5), &config());
+        assert!(ok.success_rate.unwrap() > failed.success_rate.unwrap());
+        assert_eq!(ok.speed, Some(0.9));
+        assert!(ok.score.unwrap() > failed.score.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_outcome_guard() {
+        let basic = ProxyBasic::new("127.0.0.1", "65001");
+        let first = OutcomeGuard::acquire(&basic).await;
+        let second = tokio::spawn(async move { OutcomeGuard::acquire(&basic).await });
+
+        tokio::task::yield_now().await;
+        assert!(!second.is_finished());
+        drop(first);
+        drop(second.await.unwrap());
+
+        let key = ("127.0.0.1".to_string(), "65001".to_string());
+        assert!(!OUTCOME_LOCKS.lock().unwrap().contains_key(&key));
+    }
+}
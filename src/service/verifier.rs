@@ -6,13 +6,13 @@
 //!
 //! - 批量去重并验证代理的连通性和质量表现；  
 //! - 使用 `quality` 模块对每个代理进行测速与成功率评估；  
-//! - 将有效代理（成功率 > 0）写入数据库；  
+//! - 将有效代理（成功率 > 0）写入数据库，写入与删除经 [`WriteBatch`] 攒批后批量执行；  
 //! - 支持并发控制（通过信号量限制并发请求数量）；  
 //! - 输出验证过程的详细日志与统计信息。
 //!
 //! ## 主要函数
 //!
-//! - verify_all：验证整个代理列表，返回成功数量；  
+//! - verify_all：验证整个代理列表，通过 [`VerifyProgress`] 汇报进度、支持取消，返回成功数量；  
 //! - verify_single：验证单个代理是否可用（私有辅助函数）。
 //!
 //! ## 使用场景
@@ -21,69 +21,260 @@
 
 
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex
 };
 use anyhow::Result;
-use std::time::Instant;
-use tokio::sync::Semaphore;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Semaphore};
 use tracing::{error, info};
 use tracing::log::warn;
 use crate::common::error::ApiError;
-use crate::model::{Proxy, ProxyBasic, APP_CONFIG};
-use crate::service::quality;
+use crate::model::{Proxy, ProxyBasic, ProxySiteResult, TamperAction, APP_CONFIG};
+use crate::service::{feedback, history, quality, selector};
 use crate::common::utils::dedup_proxies;
 use crate::db::get_storage;
 use crate::db::manager::ProxyStorage;
+use serde::Serialize;
+
+/// 批量验证的实时进度，可在验证过程中被其他任务读取，也可用于请求取消。
+#[derive(Debug, Default)]
+pub struct VerifyProgress {
+    total: AtomicUsize,
+    verified: AtomicUsize,
+    success: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+/// [`VerifyProgress`] 的只读快照。
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ProgressSnapshot {
+    /// 去重后待验证的代理总数。
+    pub total: usize,
+    /// 已完成验证的代理数量。
+    pub verified: usize,
+    /// 验证通过的代理数量。
+    pub success: usize,
+}
+
+impl VerifyProgress {
+    /// 请求取消验证：尚未开始的代理将被跳过，进行中的代理会在本次评估结束后停止。
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// 是否已请求取消。
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 当前进度快照。
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            total: self.total.load(Ordering::SeqCst),
+            verified: self.verified.load(Ordering::SeqCst),
+            success: self.success.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// 每批写入存储的最大代理数量。
+const WRITE_BATCH_SIZE: usize = 100;
+
+/// 验证期间定期写入缓冲的间隔，避免结果迟迟不再增加时已缓冲的结果长时间不可见。
+const WRITE_BATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 验证结果的写入缓冲：攒够 [`WRITE_BATCH_SIZE`] 条时批量写入存储，减少大批量验证时的数据库往返。
+///
+/// 未攒满的结果由 [`flush_periodically`] 每隔 [`WRITE_BATCH_INTERVAL`] 写入一次，与是否有新结果加入无关。
+pub struct WriteBatch<'a> {
+    storage: &'a dyn ProxyStorage,
+    pending: Mutex<PendingWrites>,
+}
+
+#[derive(Debug, Default)]
+struct PendingWrites {
+    upserts: Vec<Proxy>,
+    sites: Vec<ProxySiteResult>,
+    removals: Vec<ProxyBasic>,
+}
+
+impl PendingWrites {
+    fn len(&self) -> usize {
+        self.upserts.len() + self.removals.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0 && self.sites.is_empty()
+    }
+
+    /// 是否已攒够一批。
+    fn is_full(&self) -> bool {
+        self.len() >= WRITE_BATCH_SIZE
+    }
+
+    /// 将写入失败的部分放回缓冲，排在期间新加入的结果之前。
+    fn restore(&mut self, mut failed: PendingWrites) {
+        failed.upserts.append(&mut self.upserts);
+        failed.sites.append(&mut self.sites);
+        failed.removals.append(&mut self.removals);
+        *self = failed;
+    }
+}
+
+impl<'a> WriteBatch<'a> {
+    /// 创建写入 `storage` 的空缓冲。
+    pub fn new(storage: &'a dyn ProxyStorage) -> Self {
+        Self { storage, pending: Mutex::default() }
+    }
+
+    /// 缓冲待写入的代理及其站点结果，缓冲已满时写入存储。
+    pub async fn upsert(&self, proxy: Proxy, sites: Vec<ProxySiteResult>) {
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.upserts.push(proxy);
+            pending.sites.extend(sites);
+            pending.is_full()
+        };
+        self.flush_if_full(full).await;
+    }
+
+    /// 缓冲待删除的代理，缓冲已满时写入存储。
+    pub async fn remove(&self, basic: ProxyBasic) {
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.removals.push(basic);
+            pending.is_full()
+        };
+        self.flush_if_full(full).await;
+    }
+
+    /// 缓冲已满时写入存储。
+    ///
+    /// 写入失败只记录日志：失败的部分留在缓冲中由下一次 `flush` 重试，不影响触发写入的那个代理的验证结果。
+    async fn flush_if_full(&self, full: bool) {
+        if full && let Err(e) = self.flush().await {
+            warn!("批量写入验证结果失败，稍后重试：{}", e);
+        }
+    }
+
+    /// 将缓冲中的写入与删除全部提交到存储：依次批量写入代理、写入站点结果、删除失效代理，每一步各自在一个事务中执行。
+    ///
+    /// # 错误
+    /// 某一步失败时，该步及之后尚未执行的部分放回缓冲，由下一次 `flush` 重试。
+    pub async fn flush(&self) -> Result<()> {
+        let mut pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let storage = self.storage;
+        let result = async {
+            storage.upsert_many(&pending.upserts).await?;
+            pending.upserts.clear();
+            storage.upsert_site_results(&pending.sites).await?;
+            pending.sites.clear();
+            storage.remove_many(&pending.removals).await?;
+            feedback::forget_removed(&pending.removals);
+            selector::forget_removed(&pending.removals);
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        if result.is_err() {
+            self.pending.lock().unwrap().restore(pending);
+        }
+        result
+    }
+}
+
+/// 每隔 `period` 将缓冲写入存储，直到 `stop` 收到信号或发送端被丢弃。
+///
+/// 只在两次写入之间检查停止信号，不会中断进行中的写入；写入失败的部分留在缓冲中，下一次重试。
+async fn flush_periodically(batch: Arc<WriteBatch<'static>>, period: Duration, mut stop: oneshot::Receiver<()>) {
+    let mut ticker = tokio::time::interval(period);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if let Err(e) = batch.flush().await {
+                    warn!("定时写入验证结果失败：{}", e);
+                }
+            }
+            _ = &mut stop => break,
+        }
+    }
+}
 
 /// 批量验证多个代理的可用性，并统计验证成功的代理数量。
 ///
 /// 此函数将：
 /// 1. 对传入代理列表去重；
 /// 2. 并发限制地执行每个代理的质量评估（包括测速与稳定性测试）；
-/// 3. 若评估通过（成功率 > 0），则写入存储；
+/// 3. 若评估通过（成功率 > 0），则按批写入存储，否则按批删除，未攒满的批次定期写入；
 /// 4. 最终返回成功验证的代理数量。
 ///
 /// 日志将记录验证过程及结果统计。
 ///
 /// # 参数
 /// - `basics`: 原始代理列表（含 IP 与端口）
+/// - `progress`: 进度记录，验证过程中实时更新；被取消时尽快停止
 ///
 /// # 返回
-/// 成功验证并写入的代理数量。
+/// 成功验证并写入的代理数量（取消时为已完成部分的统计）。
 ///
 /// # 错误
 /// 如果在代理验证过程中发生错误（如网络异常、数据库写入失败），将返回对应错误。
-pub async fn verify_all(mut basics: Vec<ProxyBasic>) -> Result<usize> {
+pub async fn verify_all(basics: Vec<ProxyBasic>, progress: Arc<VerifyProgress>) -> Result<usize> {
+    let mut quality_config = quality::QualityConfig::default();
+    quality_config.resolve_egress_ip().await;
+    verify_with(basics, progress, quality_config, get_storage(), APP_CONFIG.verify.semaphore).await
+}
+
+/// [`verify_all`] 的实现：使用给定的评估参数与存储，最多同时验证 `concurrency` 个代理。
+async fn verify_with(
+    mut basics: Vec<ProxyBasic>,
+    progress: Arc<VerifyProgress>,
+    quality_config: quality::QualityConfig,
+    storage: &'static dyn ProxyStorage,
+    concurrency: usize,
+) -> Result<usize> {
     info!("========== [代理去重阶段] ==========");
     basics = dedup_proxies(basics);
     let len = basics.len();
+    progress.total.store(len, Ordering::SeqCst);
 
     info!("========== [代理验证阶段] ==========");
     info!("🚀 开始批量验证代理，共 {} 条待验证", len);
 
-    let success_count = Arc::new(AtomicUsize::new(0));
-    let semaphore = Arc::new(Semaphore::new(APP_CONFIG.verify.semaphore));
-    let quality_config = Arc::new(quality::QualityConfig::default());
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let quality_config = Arc::new(quality_config);
+    let batch = Arc::new(WriteBatch::new(storage));
+    let (stop_flush, stopped) = oneshot::channel();
+    let flusher = tokio::spawn(flush_periodically(Arc::clone(&batch), WRITE_BATCH_INTERVAL, stopped));
 
     let tasks: Vec<_> = basics.into_iter().enumerate().map(|(i, basic)| {
-        let success_count = Arc::clone(&success_count);
+        let progress = Arc::clone(&progress);
         let semaphore = Arc::clone(&semaphore);
         let quality_config = Arc::clone(&quality_config);
+        let batch = Arc::clone(&batch);
 
         tokio::spawn(async move {
             let _permit = semaphore.acquire_owned().await.expect("Semaphore acquire failed");
+            if progress.is_cancelled() {
+                return Ok::<(), ApiError>(());
+            }
             let start = Instant::now();
 
             let label = format!("[#{} {}:{}]", i + 1, basic.ip, basic.port);
 
             // 🛰️ 打印参与测速的目标节点地址
-            let nodes = quality_config.test_urls.join(", ");
+            let nodes: Vec<&str> = quality_config.targets.iter().map(|t| t.profile.name.as_str()).collect();
+            let nodes = nodes.join(", ");
             info!("📡 {} 开始验证，测速节点：{}", label, nodes);
 
-            match verify_single(&basic, &quality_config).await {
+            match verify_single(&basic, &quality_config, &batch).await {
                 Ok(true) => {
-                    success_count.fetch_add(1, Ordering::SeqCst);
+                    progress.success.fetch_add(1, Ordering::SeqCst);
                     let ms = start.elapsed().as_millis();
                     info!("🟢 {} 验证通过，耗时 {}ms", label, ms);
                 }
@@ -97,6 +288,7 @@ pub async fn verify_all(mut basics: Vec<ProxyBasic>) -> Result<usize> {
                     error!("❌ {} 验证出错，耗时 {}ms，错误：{}", label, ms, e);
                 }
             }
+            progress.verified.fetch_add(1, Ordering::SeqCst);
             Ok::<(), ApiError>(())
         })
     }).collect();
@@ -104,10 +296,17 @@ pub async fn verify_all(mut basics: Vec<ProxyBasic>) -> Result<usize> {
     for task in tasks {
         task.await??;
     }
+    let _ = stop_flush.send(());
+    flusher.await?;
+    batch.flush().await?;
 
-    let ok = success_count.load(Ordering::SeqCst);
+    let ProgressSnapshot { verified, success: ok, .. } = progress.snapshot();
     info!("========== [结果统计完成 ✅] ==========");
-    info!("✅ 验证完成：总计 {} 条，成功 {} 条，失败 {} 条", len, ok, len - ok);
+    if progress.is_cancelled() {
+        warn!("⏹️ 验证已取消：总计 {} 条，已验证 {} 条，成功 {} 条", len, verified, ok);
+    } else {
+        info!("✅ 验证完成：总计 {} 条，成功 {} 条，失败 {} 条", len, ok, len - ok);
+    }
 
     Ok(ok)
 }
@@ -117,65 +316,155 @@ pub async fn verify_all(mut basics: Vec<ProxyBasic>) -> Result<usize> {
 ///
 /// 该函数将对代理进行质量评估（包括测速、成功率与稳定性），
 /// 并根据成功率判断其是否为有效代理：
+/// - 若有目标站点返回被篡改的内容，按 `tamper_action` 标记后写入或直接删除，返回 `false`；
 /// - 若成功率大于 0，将其写入数据库并返回 `true`；
-/// - 否则返回 `false`。
+/// - 否则将其从数据库删除并返回 `false`。
 ///
 /// # 参数
 /// - `basic`: 代理基本信息（IP 和端口）
 /// - `config`: 质量评估配置参数
+/// - `batch`: 写入缓冲，评估时也从其存储读取代理的历史结果；调用方负责最终 [`WriteBatch::flush`]
 ///
 /// # 返回
 /// `Ok(true)` 表示验证通过；`Ok(false)` 表示验证失败。
-/// 若发生错误（如请求失败、存储异常），则返回 `Err(ApiError)`。
-async fn verify_single(basic: &ProxyBasic, config: &quality::QualityConfig) -> Result<bool> {
+/// 评估时读取存储出错则返回 `Err`；攒批写入的失败只记录日志，不影响本代理的验证结果。
+async fn verify_single(basic: &ProxyBasic, config: &quality::QualityConfig, batch: &WriteBatch<'_>) -> Result<bool> {
     // 调用质量评估，返回完整 Proxy（带质量信息）
-    let updated: Proxy = quality::evaluate(basic, config).await?;
+    let report = quality::evaluate(basic, config, batch.storage).await?;
+    history::record(batch.storage, &report.checks).await;
+
+    if report.proxy.tampered {
+        return match config.tamper_action {
+            TamperAction::Drop => {
+                warn!("🚫 [{}:{}] 返回了被篡改的内容，已删除", basic.ip, basic.port);
+                batch.remove(basic.clone()).await;
+                Ok(false)
+            }
+            TamperAction::Flag => {
+                warn!("🚩 [{}:{}] 返回了被篡改的内容，已标记", basic.ip, basic.port);
+                batch.upsert(report.proxy, report.sites).await;
+                Ok(false)
+            }
+        };
+    }
 
     // 只要成功率大于0就认为有效，存储数据库
-    if updated.success_rate.unwrap_or(0.0) > 0.0 {
-        get_storage().upsert_quality_proxy(&updated).await?;
+    if report.proxy.success_rate.unwrap_or(0.0) > 0.0 {
+        batch.upsert(report.proxy, report.sites).await;
         Ok(true)
     } else {
-        get_storage().remove_proxy(&basic.ip).await?;
+        batch.remove(basic.clone()).await;
         Ok(false)
     }
 }
 
-pub async fn verify_database() -> Result<()> {
+/// 重新验证数据库中的全部代理，进度写入 `progress`，返回验证通过的数量。
+pub async fn verify_database(progress: Arc<VerifyProgress>) -> Result<usize> {
     info!("========== [数据库存活代理校验] ==========");
     let list: Vec<ProxyBasic> = get_storage().list_all_proxies().await?.iter().map(|p| p.basic()).collect();
-    verify_all(list).await?;
-
-    Ok(())
+    verify_all(list, progress).await
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::db;
-    use crate::model::ProxyBasic;
+    use crate::db::manager::ProxyStorage;
+    use crate::db::memory::MemoryStorage;
+    use crate::model::{Proxy, ProxyBasic};
     use crate::service::quality::QualityConfig;
+    use super::{PendingWrites, WriteBatch};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 启动一个对任何请求都返回 `200 OK` 的本地 HTTP 代理，返回其端口。
+    async fn spawn_http_proxy() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => head.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+                });
+            }
+        });
+        port
+    }
+
+    /// 一个没有服务监听的本地端口。
+    fn closed_port() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port().to_string()
+    }
 
     #[tokio::test]
     async fn test_verify_all() {
-        db::init().await.unwrap();
-        
+        let storage: &'static MemoryStorage = Box::leak(Box::new(MemoryStorage::new()));
+        let good = spawn_http_proxy().await;
         let list = vec![
-            ProxyBasic::new("127.0.0.1", "12334"), 
-            ProxyBasic::new("127.0.0.1", "12335"),
+            ProxyBasic::new("127.0.0.1", &good),
+            ProxyBasic::new("127.0.0.1", &closed_port()),
+            ProxyBasic::new("127.0.0.1", &good),
         ];
-        
-        let result = super::verify_all(list).await.unwrap();
-        assert!(result > 0);
+
+        let config = QualityConfig::for_test("http://example.com/");
+        let result = super::verify_with(list, Default::default(), config, storage, 2).await.unwrap();
+        assert_eq!(result, 1);
+        let proxies = storage.list_all_proxies().await.unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0].port, good);
+        assert_eq!(storage.list_checks("127.0.0.1", &good, 10).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_restore_pending_writes() {
+        let failed = PendingWrites { removals: vec![ProxyBasic::new("127.0.0.1", "1")], ..Default::default() };
+        let mut pending = PendingWrites { removals: vec![ProxyBasic::new("127.0.0.1", "2")], ..Default::default() };
+        pending.restore(failed);
+
+        let ports: Vec<&str> = pending.removals.iter().map(|p| p.port.as_str()).collect();
+        assert_eq!(ports, vec!["1", "2"]);
+        assert!(!pending.is_full());
+    }
+
+    #[tokio::test]
+    async fn test_flush_periodically() {
+        let storage: &'static MemoryStorage = Box::leak(Box::new(MemoryStorage::new()));
+        let batch = Arc::new(WriteBatch::new(storage));
+        batch.upsert(Proxy::new("127.0.0.1".into(), "1".into()), Vec::new()).await;
+
+        let (stop, stopped) = oneshot::channel();
+        let flusher = tokio::spawn(super::flush_periodically(Arc::clone(&batch), Duration::from_millis(10), stopped));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(storage.find_proxy_by_ip_port("127.0.0.1", "1").await.unwrap().is_some());
+
+        stop.send(()).unwrap();
+        flusher.await.unwrap();
     }
 
     #[tokio::test]
     async fn test_verify_single() {
-        db::init().await.unwrap();
+        let storage = MemoryStorage::new();
+        let proxy = ProxyBasic::new("127.0.0.1", &spawn_http_proxy().await);
+        let config = QualityConfig::for_test("http://example.com/");
+        let batch = WriteBatch::new(&storage);
 
-        let proxy = ProxyBasic::new("127.0.0.1", "12334");
-        let config = QualityConfig::default();
-        
-        let result = super::verify_single(&proxy, &config).await.unwrap();
+        let result = super::verify_single(&proxy, &config, &batch).await.unwrap();
         assert!(result);
+        assert!(storage.find_proxy_by_ip_port(&proxy.ip, &proxy.port).await.unwrap().is_none());
+
+        batch.flush().await.unwrap();
+        let stored = storage.find_proxy_by_ip_port(&proxy.ip, &proxy.port).await.unwrap().unwrap();
+        assert_eq!(stored.success_rate, Some(1.0));
+        assert_eq!(storage.list_site_results(&proxy.ip, &proxy.port).await.unwrap().len(), 1);
     }
 }
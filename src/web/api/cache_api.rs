@@ -0,0 +1,28 @@
+use crate::common::cache::{self, CacheStats, CACHE};
+use salvo::prelude::*;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct RefreshResult {
+    /// 刷新后缓存中的代理数量。
+    count: usize,
+}
+
+/// 立即从存储重新加载代理池缓存。
+#[handler]
+async fn refresh_cache() -> anyhow::Result<Json<RefreshResult>> {
+    let count = cache::refresh_proxies().await?;
+    Ok(Json(RefreshResult { count }))
+}
+
+/// 查询缓存的命中率、容量与淘汰统计。
+#[handler]
+async fn cache_stats() -> Json<CacheStats> {
+    Json(CACHE.stats())
+}
+
+pub fn cache_router() -> Router {
+    Router::with_path("cache")
+        .push(Router::with_path("refresh").post(refresh_cache))
+        .push(Router::with_path("stats").get(cache_stats))
+}
@@ -0,0 +1,28 @@
+use crate::model::EchoResponse;
+use salvo::prelude::*;
+
+/// 回显接口：返回请求的来源 IP 与请求头，格式与 httpbin 的 `/get` 兼容。
+///
+/// 部署在公网时，可将 `verify.echo_url` 指向该接口，
+/// 在不依赖第三方服务的情况下完成代理匿名等级检测。
+#[handler]
+async fn echo(req: &mut Request) -> Json<EchoResponse> {
+    let origin = req
+        .remote_addr()
+        .clone()
+        .into_std()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+
+    let headers = req
+        .headers()
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.as_str().to_string(), v.to_string())))
+        .collect();
+
+    Json(EchoResponse { origin, headers })
+}
+
+pub fn echo_router() -> Router {
+    Router::with_path("echo").get(echo)
+}
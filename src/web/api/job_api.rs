@@ -0,0 +1,37 @@
+use crate::service::job::{JobSnapshot, JOBS};
+use salvo::prelude::*;
+
+/// 从路径参数中解析任务 ID。
+fn job_id(req: &Request) -> Result<u64, StatusError> {
+    req.param::<u64>("id").ok_or_else(|| StatusError::bad_request().brief("任务 ID 不合法"))
+}
+
+/// 列出最近的后台任务，最新的在前。
+#[handler]
+async fn list_jobs() -> Json<Vec<JobSnapshot>> {
+    Json(JOBS.list())
+}
+
+/// 查询单个后台任务的状态与进度。
+#[handler]
+async fn get_job(req: &mut Request) -> Result<Json<JobSnapshot>, StatusError> {
+    let id = job_id(req)?;
+    JOBS.get(id).map(Json).ok_or_else(|| StatusError::not_found().brief(format!("任务 #{} 不存在", id)))
+}
+
+/// 取消尚未结束的后台任务。
+#[handler]
+async fn cancel_job(req: &mut Request) -> Result<Json<JobSnapshot>, StatusError> {
+    let id = job_id(req)?;
+    JOBS.cancel(id).map(Json).map_err(|e| StatusError::conflict().brief(e.to_string()))
+}
+
+pub fn job_router() -> Router {
+    Router::with_path("jobs")
+        .get(list_jobs)
+        .push(
+            Router::with_path("{id}")
+                .get(get_job)
+                .push(Router::with_path("cancel").post(cancel_job)),
+        )
+}
@@ -1 +1,5 @@
-pub mod proxy_api;
\ No newline at end of file
+pub mod cache_api;
+pub mod echo_api;
+pub mod job_api;
+pub mod proxy_api;
+pub mod schedule_api;
@@ -1,49 +1,179 @@
-use anyhow::{anyhow, Context};
-use crate::common::cache::CACHE;
-use crate::model::ProxyBasic;
-use crate::service::verifier::verify_database;
-use crate::web::hoops::cache::cache_proxies;
-use rand::Rng;
+use anyhow::anyhow;
+use crate::common::cache::cached_proxies;
+use crate::db::get_storage;
+use crate::db::manager::ProxyStorage;
+use crate::db::query::filter_proxies;
+use crate::model::{Proxy, ProxyBasic, ProxyCheck, ProxyQuery, ProxySiteResult, APP_CONFIG, MAX_CHECKED_WITHIN};
+use crate::service::feedback::{FeedbackReport, FeedbackResult, FEEDBACK};
+use crate::service::job::{JobKind, JobSnapshot, JOBS};
+use crate::service::lease::{Lease, LEASES};
+use crate::service::selector::SELECTOR;
 use salvo::prelude::*;
-use tracing::log::info;
-use crate::fetcher;
-use crate::service::verifier;
+use serde::Deserialize;
 
+/// 解析查询参数中的过滤、排序与分页条件，`checked_within` 超出上限时按上限处理。
+fn proxy_query(req: &mut Request) -> anyhow::Result<ProxyQuery> {
+    let mut query = req.parse_queries::<ProxyQuery>().map_err(|e| anyhow!("查询参数不合法：{}", e))?;
+    query.checked_within = query.checked_within.map(|secs| secs.min(MAX_CHECKED_WITHIN));
+    Ok(query)
+}
+
+/// 对符合查询条件的代理执行 `f`。
+///
+/// 条件在代理池缓存的 `Arc` 快照上求值，不复制代理数据；
+/// 快照中没有分站点结果，指定 `site` 时改为由存储执行查询。
+async fn with_matching<R>(query: &ProxyQuery, f: impl FnOnce(&[&Proxy]) -> R) -> anyhow::Result<R> {
+    if query.site.is_some() {
+        let proxies = get_storage().query_proxies(query).await?;
+        return Ok(f(&proxies.iter().collect::<Vec<&Proxy>>()));
+    }
+    let pool = cached_proxies().await?;
+    Ok(f(&filter_proxies(&pool, query)))
+}
+
+/// 从符合查询条件的代理中按 `strategy` 参数（缺省为配置的默认策略）选取一个。
+///
+/// 租约数已满或处于隔离期的代理不会被选中。
+#[handler]
+async fn get_proxy(req: &mut Request) -> anyhow::Result<Json<ProxyBasic>> {
+    let query = proxy_query(req)?;
+    let strategy = query.strategy.unwrap_or(APP_CONFIG.selector.strategy);
+
+    let proxy = with_matching(&query, |proxies| {
+        let candidates = LEASES.available(&FEEDBACK.available(proxies));
+        SELECTOR.select(strategy, &candidates).map(|p| p.basic())
+    })
+    .await?;
+    Ok(Json(proxy.ok_or_else(|| anyhow!("没有符合条件的代理"))?))
+}
+
+/// 租借一个代理：支持与 `/proxy` 相同的过滤与策略参数，`ttl` 为租约时长（秒）。
+///
+/// 在归还或租约到期之前，该代理不会再分配给其他客户端。
+#[handler]
+async fn lease_proxy(req: &mut Request) -> anyhow::Result<Json<Lease>> {
+    let query = proxy_query(req)?;
+    let strategy = query.strategy.unwrap_or(APP_CONFIG.selector.strategy);
+    let config = &APP_CONFIG.lease;
+    let ttl = req.query::<u64>("ttl").unwrap_or(config.default_ttl).clamp(1, config.max_ttl.max(1));
+
+    let lease = with_matching(&query, |proxies| {
+        LEASES.acquire(&SELECTOR, strategy, &FEEDBACK.available(proxies), chrono::Duration::seconds(ttl as i64))
+    })
+    .await?;
+    Ok(Json(lease.ok_or_else(|| anyhow!("没有可租借的代理"))?))
+}
+
+/// 归还租约的请求体。
+#[derive(Debug, Deserialize)]
+struct ReleaseRequest {
+    lease_id: String,
+}
+
+/// 归还租约，`lease_id` 可放在 JSON 请求体或查询参数中。
+#[handler]
+async fn release_proxy(req: &mut Request) -> Result<Json<Lease>, StatusError> {
+    let lease_id = match req.query::<String>("lease_id") {
+        Some(id) => id,
+        None => req
+            .parse_json::<ReleaseRequest>()
+            .await
+            .map(|body| body.lease_id)
+            .map_err(|_| StatusError::bad_request().brief("缺少 lease_id"))?,
+    };
+    LEASES.release(&lease_id).map(Json).map_err(|e| StatusError::not_found().brief(e.to_string()))
+}
+
+/// 检测历史默认返回的条数。
+const DEFAULT_HISTORY_LIMIT: u32 = 100;
+/// 检测历史单次最多返回的条数。
+const MAX_HISTORY_LIMIT: u32 = 1000;
+
+/// 解析路径参数中 `ip:port` 格式的代理地址，IPv6 地址可带方括号。
+fn proxy_addr(req: &Request) -> Result<(String, String), StatusError> {
+    let addr = req.param::<String>("addr").unwrap_or_default();
+    addr.rsplit_once(':')
+        .filter(|(ip, port)| !ip.is_empty() && !port.is_empty())
+        .map(|(ip, port)| (ip.trim_matches(['[', ']']).to_string(), port.to_string()))
+        .ok_or_else(|| StatusError::bad_request().brief("代理地址应为 ip:port 格式"))
+}
+
+/// 查询代理的检测历史，路径参数为 `ip:port`，`limit` 为返回条数，按时间倒序。
 #[handler]
-async fn get_proxy() -> anyhow::Result<Json<ProxyBasic>> {
-    let proxies = CACHE.get(&"proxies").context("代理缓存获取失败")?;
-    let r = rand::rng().random_range(0..proxies.len());
-    Ok(Json(proxies[r].basic()))
+async fn proxy_history(req: &mut Request) -> Result<Json<Vec<ProxyCheck>>, StatusError> {
+    let (ip, port) = proxy_addr(req)?;
+    let limit = req.query::<u32>("limit").unwrap_or(DEFAULT_HISTORY_LIMIT).min(MAX_HISTORY_LIMIT);
+
+    get_storage()
+        .list_checks(&ip, &port, limit)
+        .await
+        .map(Json)
+        .map_err(|e| StatusError::internal_server_error().brief(e.to_string()))
 }
 
+/// 查询代理在各目标站点上最近一次验证的结果，路径参数为 `ip:port`。
 #[handler]
-async fn verify_proxy() -> String {
-    verify_database().await.unwrap();
-    "数据库存活代理校验完成".to_string()
+async fn proxy_sites(req: &mut Request) -> Result<Json<Vec<ProxySiteResult>>, StatusError> {
+    let (ip, port) = proxy_addr(req)?;
+
+    get_storage()
+        .list_site_results(&ip, &port)
+        .await
+        .map(Json)
+        .map_err(|e| StatusError::internal_server_error().brief(e.to_string()))
 }
 
+/// 上报代理的实际使用结果，更新其成功率与评分，连续失败过多时删除或隔离；代理不存在时返回 404。
 #[handler]
-async fn proxy_collection() -> anyhow::Result<()> {
-    info!("========== [代理采集阶段] ==========");
-    let list = fetcher::fetch_all_sources().await?;
-    tracing::info!("抓取到总共 {} 条代理", list.len());
-    verifier::verify_all(list).await?;
+async fn proxy_feedback(req: &mut Request) -> Result<Json<FeedbackResult>, StatusError> {
+    let report = req
+        .parse_json::<FeedbackReport>()
+        .await
+        .map_err(|e| StatusError::bad_request().brief(format!("反馈内容不合法：{}", e)))?;
+    FEEDBACK
+        .submit(&report)
+        .await
+        .map_err(|e| StatusError::internal_server_error().brief(e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| StatusError::not_found().brief(format!("代理 {}:{} 不存在", report.ip, report.port)))
+}
 
-    Ok(())
+/// 提交后台任务并立即返回 `202 Accepted` 与任务状态，进度可通过 `GET /jobs/{id}` 查询。
+fn submit_job(kind: JobKind, res: &mut Response) -> anyhow::Result<Json<JobSnapshot>> {
+    let id = JOBS.submit(kind);
+    let job = JOBS.get(id).ok_or_else(|| anyhow!("任务 #{} 不存在", id))?;
+    res.status_code(StatusCode::ACCEPTED);
+    Ok(Json(job))
 }
 
 #[handler]
-async fn list_proxy() -> anyhow::Result<Json<Vec<ProxyBasic>>> {
-    let proxies = CACHE.get(&"proxies").context("代理缓存获取失败")?;
-    let list: Vec<ProxyBasic> = proxies.iter().map(|p| p.basic()).collect();
-    Ok(Json(list))
+async fn verify_proxy(res: &mut Response) -> anyhow::Result<Json<JobSnapshot>> {
+    submit_job(JobKind::Verification, res)
+}
+
+#[handler]
+async fn proxy_collection(res: &mut Response) -> anyhow::Result<Json<JobSnapshot>> {
+    submit_job(JobKind::Collection, res)
+}
+
+/// 按查询条件列出代理，默认按评分从高到低。
+#[handler]
+async fn list_proxy(req: &mut Request) -> anyhow::Result<Json<Vec<ProxyBasic>>> {
+    let query = proxy_query(req)?;
+    let proxies = with_matching(&query, |proxies| proxies.iter().map(|p| p.basic()).collect()).await?;
+    Ok(Json(proxies))
 }
 
 pub fn proxy_router() -> Router {
     Router::with_path("proxy")
-        .hoop(cache_proxies)
         .get(get_proxy)
         .push(Router::with_path("list").get(list_proxy))
-        .push(Router::with_path("verify").get(verify_proxy))
-        .push(Router::with_path("collection").get(proxy_collection))
+        .push(Router::with_path("lease").post(lease_proxy))
+        .push(Router::with_path("release").post(release_proxy))
+        .push(Router::with_path("feedback").post(proxy_feedback))
+        .push(Router::with_path("{addr}/history").get(proxy_history))
+        .push(Router::with_path("{addr}/sites").get(proxy_sites))
+        .push(Router::with_path("verify").get(verify_proxy).post(verify_proxy))
+        .push(Router::with_path("collection").get(proxy_collection).post(proxy_collection))
 }
+
@@ -0,0 +1,12 @@
+use crate::service::scheduler::{self, TaskStatus};
+use salvo::prelude::*;
+
+/// 查询所有定时任务的运行状态。
+#[handler]
+async fn schedule_status() -> Json<Vec<TaskStatus>> {
+    Json(scheduler::status())
+}
+
+pub fn schedule_router() -> Router {
+    Router::with_path("schedule").get(schedule_status)
+}
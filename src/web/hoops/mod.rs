@@ -1 +0,0 @@
-pub mod cache;
\ No newline at end of file
@@ -1,2 +1 @@
 pub mod api;
-pub mod hoops;
\ No newline at end of file